@@ -0,0 +1,394 @@
+//! One-shot command-line interface (`linux-nitrosense --cli <command>`).
+//!
+//! Most commands are translated into a single [`Request`] sent to the running
+//! daemon, so it needs no privileges beyond access to the socket.
+
+use std::fs;
+use std::io;
+use std::thread;
+use std::time::Duration;
+
+use crate::client::Client;
+use crate::core::device_regs;
+use crate::protocol::{NitroMode, Request, Response, StatusField, StatusFields};
+
+const USAGE: &str = "\
+Usage: linux-nitrosense --cli <command>
+
+Commands:
+  reset-hardware   Reload the EC driver and restore safe defaults
+  reload-ec        Reload the EC driver, keeping every setting
+  reset-keyboard   Restore the factory lighting (static white, full brightness)
+  system-info      Show the detected model and kernel lockdown state
+  ec-info          Show the active EC interface and check that writes work
+  export-regs      Print the active register map and capabilities as JSON
+  dump-ec          Print the raw EC contents as a hex grid
+  diag             Print everything a bug report needs in one go: system
+                   and EC info, DMI model fields, register map, self-test,
+                   EC dump and recent changes (no serials or UUIDs)
+  selftest         Read every mapped register (no writes) and flag odd values
+  mode <name>      Switch the performance mode (Quiet, Default, Extreme, Turbo)
+  bar [--format <template>] [--json]
+                   One status line for i3blocks/waybar/polybar; placeholders:
+                   {cpu_temp} {gpu_temp} {sys_temp} {cpu_fan} {gpu_fan}
+                   {mode} {cpu_fan_mode} {gpu_fan_mode}; --json wraps the
+                   line for waybar
+  run-script <file>
+                   Run one command per line; `sleep <secs>` waits, alone
+                   or before a command on the same line; `#` starts a comment
+  app-rules [<exe>=<mode> ...]
+                   Switch to <mode> (Quiet, Default, Extreme, Turbo) while
+                   <exe> runs; first match wins, no rules clears them";
+
+/// Default `bar` template.
+const BAR_FORMAT: &str = "🌡️ {cpu_temp}°C  🎮 {gpu_temp}°C  🌀 {cpu_fan}/{gpu_fan} RPM  ⚡{mode}";
+
+/// Status groups the `bar` placeholders come from.
+const BAR_FIELDS: [StatusField; 3] = [StatusField::Temps, StatusField::Fans, StatusField::Modes];
+
+fn parse_command(args: &[String]) -> Result<Request, String> {
+    match args.first().map(String::as_str) {
+        Some("reset-hardware") => Ok(Request::HardwareReset),
+        Some("reload-ec") => Ok(Request::ReloadEc),
+        Some("reset-keyboard") => Ok(Request::ResetKeyboard),
+        Some("system-info") => Ok(Request::GetSystemInfo),
+        Some("ec-info") => Ok(Request::GetEcInfo),
+        Some("export-regs") => Ok(Request::GetRegisterMap),
+        Some("dump-ec") => Ok(Request::DumpEc),
+        Some("selftest") => Ok(Request::SelfTest),
+        Some("mode") => match args.get(1) {
+            Some(name) => NitroMode::from_name(name)
+                .map(Request::SetNitroMode)
+                .ok_or_else(|| format!("Unknown mode '{name}'")),
+            None => Err("mode needs a name".into()),
+        },
+        Some("app-rules") => args[1..]
+            .iter()
+            .map(|rule| match rule.split_once('=') {
+                Some((exe, mode)) => Ok((exe.to_string(), mode.to_string())),
+                None => Err(format!("Expected <exe>=<mode>, got '{rule}'")),
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Request::SetAppRules),
+        Some(other) => Err(format!("Unknown command '{other}'")),
+        None => Err("No command given".into()),
+    }
+}
+
+fn connect() -> Option<Client> {
+    match Client::new() {
+        Ok(mut c) => {
+            // Lets an open GUI say where a change came from.
+            let _ = c.send(Request::SetClientName("CLI".into()));
+            Some(c)
+        }
+        Err(e) => {
+            eprintln!("Failed to connect to daemon: {}", e);
+            None
+        }
+    }
+}
+
+/// Run a CLI command and return the process exit code.
+pub fn run_cli(args: &[String]) -> i32 {
+    if args.first().map(String::as_str) == Some("run-script") {
+        return match args.get(1) {
+            Some(path) => run_script(path),
+            None => {
+                eprintln!("run-script needs a file\n\n{USAGE}");
+                2
+            }
+        };
+    }
+
+    if args.first().map(String::as_str) == Some("bar") {
+        return run_bar(&args[1..]);
+    }
+
+    if args.first().map(String::as_str) == Some("diag") {
+        return run_diag();
+    }
+
+    let req = match parse_command(args) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("{e}\n\n{USAGE}");
+            return 2;
+        }
+    };
+
+    let Some(mut client) = connect() else { return 1 };
+    report(client.send(req))
+}
+
+/// Fill the `bar` template from a status snapshot: `{cpu_temp}`,
+/// `{gpu_temp}`, `{sys_temp}` (°C), `{cpu_fan}`, `{gpu_fan}` (RPM),
+/// `{mode}`, `{cpu_fan_mode}` and `{gpu_fan_mode}`.
+fn render_bar(template: &str, data: &StatusFields) -> String {
+    let mut values: Vec<(&str, String)> = Vec::new();
+    if let Some(t) = &data.temps {
+        values.push(("cpu_temp", t.cpu_temp.to_string()));
+        values.push(("gpu_temp", t.gpu_temp.to_string()));
+        values.push(("sys_temp", t.sys_temp.to_string()));
+    }
+    if let Some(f) = &data.fans {
+        values.push(("cpu_fan", f.cpu_fan_speed.to_string()));
+        values.push(("gpu_fan", f.gpu_fan_speed.to_string()));
+    }
+    if let Some(m) = &data.modes {
+        values.push(("mode", format!("{:?}", m.nitro_mode)));
+        values.push(("cpu_fan_mode", format!("{:?}", m.cpu_mode)));
+        values.push(("gpu_fan_mode", format!("{:?}", m.gpu_mode)));
+    }
+    values
+        .iter()
+        .fold(template.to_string(), |out, (name, value)| out.replace(&format!("{{{name}}}"), value))
+}
+
+/// Print one status line and exit; status bars re-run it on their own timer.
+fn run_bar(args: &[String]) -> i32 {
+    let mut template = BAR_FORMAT.to_string();
+    let mut json = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => match args.next() {
+                Some(f) => template = f.clone(),
+                None => {
+                    eprintln!("--format needs a template\n\n{USAGE}");
+                    return 2;
+                }
+            },
+            "--json" => json = true,
+            other => {
+                eprintln!("Unknown bar option '{other}'\n\n{USAGE}");
+                return 2;
+            }
+        }
+    }
+
+    let Some(mut client) = connect() else { return 1 };
+    let data = match client.send(Request::GetStatusFields(BAR_FIELDS.to_vec())) {
+        Ok(Response::StatusFields(data)) => data,
+        other => return report(other),
+    };
+    let line = render_bar(&template, &data);
+    if json {
+        let class = data.modes.map(|m| format!("{:?}", m.nitro_mode).to_lowercase()).unwrap_or_default();
+        println!("{}", serde_json::json!({ "text": line, "class": class }));
+    } else {
+        println!("{line}");
+    }
+    0
+}
+
+/// One titled section of the `diag` bundle.  Failures are printed inline
+/// so they end up in the redirected file; returns whether it succeeded.
+fn diag_section(client: &mut Client, title: &str, req: Request) -> bool {
+    println!("\n== {title} ==");
+    match client.send(req) {
+        Ok(Response::Error(e)) => {
+            println!("Daemon error: {e}");
+            false
+        }
+        Err(e) => {
+            println!("IPC error: {e}");
+            false
+        }
+        reply => report(reply) == 0,
+    }
+}
+
+/// Print the diagnostics bundle.  Keeps going past failed sections so a
+/// half-working setup still produces a useful report.
+fn run_diag() -> i32 {
+    println!("== NitroSense diagnostics ==");
+    println!("Version         : {}", env!("CARGO_PKG_VERSION"));
+
+    println!("\n== DMI ==");
+    for (field, value) in device_regs::dmi_report() {
+        println!("{field:<16}: {}", value.as_deref().unwrap_or("(unreadable)"));
+    }
+
+    let Some(mut client) = connect() else {
+        println!("\nDaemon not reachable; nothing more to report");
+        return 1;
+    };
+    let sections = [
+        ("System", Request::GetSystemInfo),
+        ("EC interface", Request::GetEcInfo),
+        ("Register map", Request::GetRegisterMap),
+        ("Self-test", Request::SelfTest),
+        ("EC dump", Request::DumpEc),
+        ("Recent changes", Request::GetEvents(0)),
+    ];
+    let mut ok = true;
+    for (title, req) in sections {
+        ok &= diag_section(&mut client, title, req);
+    }
+    if ok { 0 } else { 1 }
+}
+
+/// Split a script line into an optional leading delay and a command.
+fn parse_script_line(line: &str) -> Result<(Option<Duration>, Vec<String>), String> {
+    let line = line.split('#').next().unwrap_or_default();
+    let mut words: Vec<String> = line.split_whitespace().map(String::from).collect();
+    if words.first().map(String::as_str) != Some("sleep") {
+        return Ok((None, words));
+    }
+    let secs = words
+        .get(1)
+        .and_then(|w| w.parse::<f64>().ok())
+        .filter(|s| s.is_finite() && *s >= 0.0)
+        .ok_or("sleep needs a number of seconds")?;
+    words.drain(..2);
+    Ok((Some(Duration::from_secs_f64(secs)), words))
+}
+
+/// Run the commands in `path` in order, stopping at the first failure.
+fn run_script(path: &str) -> i32 {
+    let script = match fs::read_to_string(path) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("Failed to read {}: {}", path, e);
+            return 1;
+        }
+    };
+
+    // Check every line before anything is sent.
+    let mut steps = Vec::new();
+    for (n, line) in script.lines().enumerate() {
+        let parsed = parse_script_line(line).and_then(|(delay, words)| {
+            let req = if words.is_empty() { None } else { Some(parse_command(&words)?) };
+            Ok((delay, req))
+        });
+        match parsed {
+            Ok(step) => steps.push((n + 1, line.trim(), step)),
+            Err(e) => {
+                eprintln!("{path}:{}: {e}", n + 1);
+                return 2;
+            }
+        }
+    }
+
+    let Some(mut client) = connect() else { return 1 };
+    for (n, line, (delay, req)) in steps {
+        if let Some(d) = delay {
+            thread::sleep(d);
+        }
+        let Some(req) = req else { continue };
+        println!("> {line}");
+        let code = report(client.send(req));
+        if code != 0 {
+            eprintln!("{path}:{n}: '{line}' failed");
+            return code;
+        }
+    }
+    0
+}
+
+/// Print a daemon reply and map it to an exit code.
+fn report(reply: io::Result<Response>) -> i32 {
+    match reply {
+        Ok(Response::Ok) => {
+            println!("OK");
+            0
+        }
+        Ok(Response::Status(data)) => {
+            println!("{data:#?}");
+            0
+        }
+        Ok(Response::StatusFields(fields)) => {
+            println!("{fields:#?}");
+            0
+        }
+        Ok(Response::Voltage(info)) => {
+            println!("{info:#?}");
+            0
+        }
+        Ok(Response::RgbConfig(cfg)) => {
+            println!("{cfg:#?}");
+            0
+        }
+        Ok(Response::SystemInfo(info)) => {
+            println!("Model           : {}", info.model);
+            match info.kernel_lockdown {
+                Some(mode) => println!("Kernel lockdown : {mode} (EC writes are blocked; disable Secure Boot to allow them)"),
+                None => println!("Kernel lockdown : none"),
+            }
+            println!("RGB keyboard    : {}", if info.keyboard_device { "found" } else { "driver not loaded" });
+            println!("CPU vendor      : {:?} ({})", info.cpu, info.cpu_detail);
+            println!("CPU tooling     : {}", if info.cpu_tooling { "found" } else { "missing (amdctl / msr-tools)" });
+            let features = info.caps.feature_names();
+            println!("Features        : {}", if features.is_empty() { "none".into() } else { features.join(", ") });
+            println!("Keyboard        : {:?}", info.caps.keyboard);
+            println!("Config dir      : {}", info.config_dir);
+            if info.caps.experimental {
+                println!("Register map    : experimental (unconfirmed on real hardware)");
+            }
+            0
+        }
+        Ok(Response::EcInfo(info)) => {
+            println!("Interface       : {}", info.interface);
+            println!("Writes verified : {}", if info.writes_verified { "yes" } else { "NO" });
+            println!("Buffer length   : {} bytes", info.buffer_len);
+            for (path, writable) in &info.probed {
+                println!("Tried           : {path} ({})", if *writable { "writable" } else { "read-only" });
+            }
+            if info.writes_verified { 0 } else { 1 }
+        }
+        Ok(Response::RegisterMap(map)) => match serde_json::to_string_pretty(&map) {
+            Ok(json) => {
+                println!("{json}");
+                0
+            }
+            Err(e) => {
+                eprintln!("Failed to encode register map: {}", e);
+                1
+            }
+        },
+        Ok(Response::EcDump(bytes)) => {
+            println!("     {}", (0..16).map(|c| format!("{c:02X}")).collect::<Vec<_>>().join(" "));
+            for (row, chunk) in bytes.chunks(16).enumerate() {
+                let hex: Vec<String> = chunk.iter().map(|b| format!("{b:02X}")).collect();
+                println!("{:02X}:  {}", row * 16, hex.join(" "));
+            }
+            0
+        }
+        Ok(Response::SelfTest(checks)) => {
+            for c in &checks {
+                let value = c.value.map_or_else(|| String::from("--"), |v| format!("0x{v:02X}"));
+                let verdict = c.problem.as_deref().unwrap_or("OK");
+                println!("{:<26} 0x{:02X}  {value:<5} {verdict}", c.name, c.address);
+            }
+            let bad = checks.iter().filter(|c| c.problem.is_some()).count();
+            if bad == 0 {
+                println!("All {} registers look plausible", checks.len());
+                0
+            } else {
+                println!("{bad} of {} registers look wrong; check the register map for this model", checks.len());
+                1
+            }
+        }
+        Ok(Response::Events(_, events)) => {
+            for event in events {
+                println!("{event}");
+            }
+            0
+        }
+        Ok(Response::ThermalZones(zones)) => {
+            for z in zones {
+                println!("{:<32} {:.1} °C", z.name, z.temp_c);
+            }
+            0
+        }
+        Ok(Response::Error(e)) => {
+            eprintln!("Daemon error: {}", e);
+            1
+        }
+        Err(e) => {
+            eprintln!("IPC error: {}", e);
+            1
+        }
+    }
+}
@@ -1,32 +1,278 @@
-use std::io::{self, BufRead, BufReader, Write};
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
 use std::os::unix::net::UnixStream;
+use std::time::Duration;
 
-use crate::protocol::{Request, Response, SOCKET_PATH};
+use crate::protocol::{self, Encoding, Envelope, Request, Response, BINARY_HANDSHAKE, SOCKET_PATH};
+
+/// How long to wait for the daemon to accept binary frames.  A daemon that
+/// predates them sits on the handshake byte waiting for a newline.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Failures the client itself detects, carried inside the `io::Error`
+/// returned by [`Client::send`].
+#[derive(Debug)]
+pub enum ClientError {
+    /// The daemon hung up (restarted, or dropped this connection) rather
+    /// than sent something unreadable.
+    Disconnected,
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClientError::Disconnected => write!(f, "daemon closed the connection"),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<ClientError> for io::Error {
+    fn from(e: ClientError) -> Self {
+        let kind = match e {
+            ClientError::Disconnected => io::ErrorKind::ConnectionAborted,
+        };
+        io::Error::new(kind, e)
+    }
+}
+
+/// Whether `e` is a [`ClientError::Disconnected`].
+pub fn is_disconnect(e: &io::Error) -> bool {
+    matches!(e.get_ref().and_then(|inner| inner.downcast_ref()), Some(ClientError::Disconnected))
+}
 
 pub struct Client {
+    path: String,
     stream: UnixStream,
     reader: BufReader<UnixStream>,
+    encoding: Encoding,
+    next_id: u64,
+}
+
+/// Open a connection and, for [`Encoding::Binary`], switch it over.
+/// Falls back to a fresh JSON connection if the daemon does not answer
+/// the handshake.
+fn connect(path: &str, encoding: Encoding) -> io::Result<(UnixStream, BufReader<UnixStream>, Encoding)> {
+    let mut stream = UnixStream::connect(path)?;
+    if encoding == Encoding::Binary {
+        stream.write_all(&[BINARY_HANDSHAKE])?;
+        stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+        let mut echo = [0u8; 1];
+        let accepted = matches!(stream.read_exact(&mut echo), Ok(()) if echo[0] == BINARY_HANDSHAKE);
+        if accepted {
+            stream.set_read_timeout(None)?;
+            let reader = BufReader::new(stream.try_clone()?);
+            return Ok((stream, reader, Encoding::Binary));
+        }
+        eprintln!("Daemon at {path} does not speak binary frames; using JSON");
+        stream = UnixStream::connect(path)?;
+    }
+    let reader = BufReader::new(stream.try_clone()?);
+    Ok((stream, reader, Encoding::Json))
 }
 
 impl Client {
     pub fn new() -> io::Result<Self> {
-        let stream = UnixStream::connect(SOCKET_PATH)?;
-        let reader = BufReader::new(stream.try_clone()?);
-        Ok(Self { stream, reader })
+        Self::new_with_path(SOCKET_PATH)
+    }
+
+    /// Connect to a daemon listening on `path`, e.g. a socket forwarded
+    /// from another machine with `ssh -L`.
+    pub fn new_with_path(path: &str) -> io::Result<Self> {
+        Self::new_with_encoding(path, Encoding::Json)
     }
 
+    /// Connect with a chosen wire format.  Binary frames are smaller and
+    /// cheaper to parse, which matters for clients that poll many times a
+    /// second; a daemon without them gets JSON instead.
+    pub fn new_with_encoding(path: &str, encoding: Encoding) -> io::Result<Self> {
+        let (stream, reader, encoding) = connect(path, encoding)?;
+        Ok(Self { path: path.to_string(), stream, reader, encoding, next_id: 1 })
+    }
+
+    /// Send a request and wait for its response.
+    ///
+    /// Any failure leaves the connection in an unknown state (possibly with
+    /// half a response buffered), so the client reconnects before returning
+    /// the error and the next call starts on a clean stream.
     pub fn send(&mut self, req: Request) -> io::Result<Response> {
-        let mut data = serde_json::to_string(&req)?;
+        let result = self.exchange(req);
+        if result.is_err() {
+            if let Err(e) = self.reconnect() {
+                eprintln!("Failed to reconnect to {}: {}", self.path, e);
+            }
+        }
+        result
+    }
+
+    fn exchange(&mut self, req: Request) -> io::Result<Response> {
+        let id = self.next_id;
+        // Skip 0 on wrap-around: it means "untagged" on the wire.
+        self.next_id = self.next_id.checked_add(1).unwrap_or(1);
+
+        let resp = match self.encoding {
+            Encoding::Json => self.exchange_json(id, req),
+            Encoding::Binary => self.exchange_binary(id, req),
+        }
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::ConnectionAborted | io::ErrorKind::ConnectionReset | io::ErrorKind::BrokenPipe => {
+                ClientError::Disconnected.into()
+            }
+            _ => e,
+        })?;
+
+        if resp.id != id {
+            eprintln!("Protocol desync: sent request #{id}, got response #{}", resp.id);
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("response id {} does not match request id {id}", resp.id),
+            ));
+        }
+
+        Ok(resp.body)
+    }
+
+    fn exchange_json(&mut self, id: u64, req: Request) -> io::Result<Envelope<Response>> {
+        let mut data = serde_json::to_string(&Envelope { id, body: req })?;
         data.push('\n');
         self.stream.write_all(data.as_bytes())?;
         self.stream.flush()?;
 
         let mut buf = String::new();
-        self.reader.read_line(&mut buf)?;
-        
-        let resp: Response = serde_json::from_str(&buf)
-            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-            
-        Ok(resp)
+        if self.reader.read_line(&mut buf)? == 0 {
+            return Err(ClientError::Disconnected.into());
+        }
+        if !buf.ends_with('\n') {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "daemon closed the connection mid-response",
+            ));
+        }
+
+        serde_json::from_str(&buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    fn exchange_binary(&mut self, id: u64, req: Request) -> io::Result<Envelope<Response>> {
+        protocol::write_frame(&mut self.stream, id, &req)?;
+        match protocol::read_frame(&mut self.reader)? {
+            Some((id, body)) => Ok(Envelope { id, body }),
+            None => Err(ClientError::Disconnected.into()),
+        }
+    }
+
+    /// Replace the stream and its reader together.  Dropping the old reader
+    /// discards anything it had buffered from the previous connection.
+    /// A client that fell back to JSON stays on it.
+    fn reconnect(&mut self) -> io::Result<()> {
+        let (stream, reader, encoding) = connect(&self.path, self.encoding)?;
+        self.stream = stream;
+        self.reader = reader;
+        self.encoding = encoding;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixListener;
+    use std::thread;
+
+    #[test]
+    fn reconnects_after_mid_response_disconnect() {
+        let path = std::env::temp_dir().join(format!("nitrosense-client-test-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+
+        let server = thread::spawn(move || {
+            // First connection: hang up halfway through the response.
+            let (mut conn, _) = listener.accept().unwrap();
+            let mut line = String::new();
+            BufReader::new(conn.try_clone().unwrap()).read_line(&mut line).unwrap();
+            conn.write_all(br#"{"id":1,"Err"#).unwrap();
+            drop(conn);
+
+            // Second connection: answer properly, echoing the request id.
+            let (mut conn, _) = listener.accept().unwrap();
+            let mut line = String::new();
+            BufReader::new(conn.try_clone().unwrap()).read_line(&mut line).unwrap();
+            let req: Envelope<Request> = serde_json::from_str(&line).unwrap();
+            assert_eq!(req.body, Request::GetVoltage);
+            let resp = Envelope { id: req.id, body: Response::Ok };
+            writeln!(conn, "{}", serde_json::to_string(&resp).unwrap()).unwrap();
+        });
+
+        let mut client = Client::new_with_path(path.to_str().unwrap()).unwrap();
+        assert!(client.send(Request::GetStatus { force: false }).is_err());
+        assert_eq!(client.send(Request::GetVoltage).unwrap(), Response::Ok);
+
+        server.join().unwrap();
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn clean_close_is_a_disconnect() {
+        let path = std::env::temp_dir().join(format!("nitrosense-client-eof-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+
+        let server = thread::spawn(move || {
+            // Read the request, then hang up without a byte of response.
+            let (conn, _) = listener.accept().unwrap();
+            let mut line = String::new();
+            BufReader::new(conn.try_clone().unwrap()).read_line(&mut line).unwrap();
+            drop(conn);
+            // Keep the listener up for the client's reconnect.
+            let _ = listener.accept().unwrap();
+        });
+
+        let mut client = Client::new_with_path(path.to_str().unwrap()).unwrap();
+        let err = client.send(Request::GetVoltage).unwrap_err();
+        assert!(is_disconnect(&err), "unexpected error: {err:?}");
+
+        server.join().unwrap();
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn binary_handshake_and_fallback() {
+        let path = std::env::temp_dir().join(format!("nitrosense-client-bin-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+
+        let server = thread::spawn(move || {
+            // A daemon with binary frames: echo the handshake, answer a frame.
+            let (mut conn, _) = listener.accept().unwrap();
+            let mut byte = [0u8; 1];
+            conn.read_exact(&mut byte).unwrap();
+            assert_eq!(byte[0], BINARY_HANDSHAKE);
+            conn.write_all(&byte).unwrap();
+            let (id, req): (u64, Request) = protocol::read_frame(&mut conn).unwrap().unwrap();
+            assert_eq!(req, Request::GetVoltage);
+            protocol::write_frame(&mut conn, id, &Response::Ok).unwrap();
+
+            // An older daemon: ignore the handshake until the client gives up.
+            let (old, _) = listener.accept().unwrap();
+            let (mut conn, _) = listener.accept().unwrap();
+            drop(old);
+            let mut line = String::new();
+            BufReader::new(conn.try_clone().unwrap()).read_line(&mut line).unwrap();
+            let req: Envelope<Request> = serde_json::from_str(&line).unwrap();
+            let resp = Envelope { id: req.id, body: Response::Ok };
+            writeln!(conn, "{}", serde_json::to_string(&resp).unwrap()).unwrap();
+        });
+
+        let path = path.to_str().unwrap();
+        let mut client = Client::new_with_encoding(path, Encoding::Binary).unwrap();
+        assert_eq!(client.encoding, Encoding::Binary);
+        assert_eq!(client.send(Request::GetVoltage).unwrap(), Response::Ok);
+
+        let mut client = Client::new_with_encoding(path, Encoding::Binary).unwrap();
+        assert_eq!(client.encoding, Encoding::Json);
+        assert_eq!(client.send(Request::GetVoltage).unwrap(), Response::Ok);
+
+        server.join().unwrap();
+        let _ = std::fs::remove_file(path);
     }
 }
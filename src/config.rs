@@ -1,21 +1,62 @@
 /// Persistent configuration for NitroSense and keyboard RGB.
 ///
-/// Files are stored under `$XDG_CONFIG_HOME/nitrosense/` or `~/.config/nitrosense/` 
-/// as simple line-delimited values (matching the original Python behaviour) so that 
-/// existing configs remain compatible.
+/// Files are stored under `$XDG_CONFIG_HOME/nitrosense/` or `~/.config/nitrosense/`,
+/// or, for the daemon, a writable fallback when that directory is read-only
+/// (see [`config_dir`]).
+/// The system, keyboard and safety configs are JSON; the legacy
+/// line-delimited files are still read and migrated once.
+/// The daemon's `--no-persist` turns all of this off (see [`disable_persistence`]).
 
 use crate::protocol::PowerProfile;
-use crate::utils::keyboard::Rgb;
+use crate::utils::keyboard::{Direction, Rgb, MODE_BREATHING};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::collections::HashMap;
 use std::env;
 use std::fs;
-use std::io::{BufRead, BufReader, Write};
-use std::path::PathBuf;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
 
+const NITRO_JSON: &str = "nitrosense.json";
+const RGB_JSON: &str = "rgb.json";
+const APP_RULES_JSON: &str = "app_rules.json";
+const SAFETY_JSON: &str = "safety.json";
+// Legacy line-delimited files, read only for migration.
 const NITRO_CONF: &str = "nitrosense.conf";
 const RGB_CONF: &str = "rgb.conf";
 const TDP_CONF: &str = "tdp.conf";
+const SAFETY_CONF: &str = "safety.conf";
+const CLIENT_CONF: &str = "client.toml";
 
-fn config_dir() -> PathBuf {
+/// Cleared by the daemon's `--no-persist`: settings then live only in
+/// memory, and the config directory is never read, created or written.
+static PERSIST: AtomicBool = AtomicBool::new(true);
+
+pub fn disable_persistence() {
+    PERSIST.store(false, Ordering::Relaxed);
+}
+
+pub fn persistence_enabled() -> bool {
+    PERSIST.load(Ordering::Relaxed)
+}
+
+/// Set by the daemon at startup.  Only it probes the config directory and
+/// moves to a fallback; clients always use the preferred directory.
+static DIR_FALLBACK: AtomicBool = AtomicBool::new(false);
+
+/// Let [`config_dir`] fall back to a writable directory.  Call before the
+/// first config is loaded or saved.
+pub fn enable_dir_fallback() {
+    DIR_FALLBACK.store(true, Ordering::Relaxed);
+}
+
+/// Where configs go by default: `$XDG_CONFIG_HOME/nitrosense` or
+/// `~/.config/nitrosense`.
+fn preferred_config_dir() -> PathBuf {
     if let Ok(xdg) = env::var("XDG_CONFIG_HOME") {
         return PathBuf::from(xdg).join("nitrosense");
     }
@@ -28,6 +69,57 @@ fn config_dir() -> PathBuf {
     PathBuf::from("/tmp/nitrosense")
 }
 
+/// Writable places tried, in order, when the preferred directory is
+/// read-only (immutable-root distros, read-only home).
+fn fallback_config_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![PathBuf::from("/var/lib/nitrosense")];
+    if let Ok(state) = env::var("XDG_STATE_HOME") {
+        dirs.push(PathBuf::from(state).join("nitrosense"));
+    } else if let Ok(home) = env::var("HOME") {
+        dirs.push(PathBuf::from(home).join(".local/state/nitrosense"));
+    }
+    dirs
+}
+
+/// Create `dir` and check a file can be written in it.
+fn check_writable(dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let probe = dir.join(".write-test");
+    fs::write(&probe, b"")?;
+    fs::remove_file(&probe)
+}
+
+/// Directory configs are saved to, resolved once per process: the
+/// preferred directory, unless [`enable_dir_fallback`] was called and it
+/// can't be written, in which case the first writable fallback.
+pub fn config_dir() -> &'static Path {
+    static DIR: OnceLock<PathBuf> = OnceLock::new();
+    DIR.get_or_init(|| {
+        let preferred = preferred_config_dir();
+        if !DIR_FALLBACK.load(Ordering::Relaxed) {
+            return preferred;
+        }
+        let err = match check_writable(&preferred) {
+            Ok(()) => return preferred,
+            Err(e) => e,
+        };
+        match fallback_config_dirs().into_iter().find(|dir| check_writable(dir).is_ok()) {
+            Some(dir) => {
+                eprintln!(
+                    "Config directory {} is not writable ({err}); saving settings in {}",
+                    preferred.display(),
+                    dir.display()
+                );
+                dir
+            }
+            None => {
+                eprintln!("Config directory {} is not writable ({err}); settings will not be saved", preferred.display());
+                preferred
+            }
+        }
+    })
+}
+
 fn ensure_dir() {
     let _ = fs::create_dir_all(config_dir());
 }
@@ -36,9 +128,132 @@ fn conf_path(name: &str) -> PathBuf {
     config_dir().join(name)
 }
 
+/// Where to read config file `name` from: the config directory, or the
+/// preferred directory when the file has not been saved to a fallback yet,
+/// so settings left in a now read-only directory still load.
+fn read_path(name: &str) -> PathBuf {
+    let path = conf_path(name);
+    if !path.exists() {
+        let original = preferred_config_dir().join(name);
+        if original.exists() {
+            return original;
+        }
+    }
+    path
+}
+
+/// Path of config file `name`, if it exists and configs are persisted.
+fn existing_conf(name: &str) -> Option<PathBuf> {
+    if !persistence_enabled() {
+        return None;
+    }
+    let path = read_path(name);
+    path.exists().then_some(path)
+}
+
+/// Replace config file `name` with `data` atomically: write a temp file in
+/// the same directory, flush it to disk, then rename it over the target so a
+/// crash mid-save never leaves a half-written config behind.
+fn save_atomic(name: &str, data: &str) {
+    if !persistence_enabled() {
+        return;
+    }
+    invalidate_cached(name);
+    ensure_dir();
+    let path = conf_path(name);
+    let tmp = conf_path(&format!(".{name}.tmp"));
+
+    let result = (|| {
+        let mut f = fs::File::create(&tmp)?;
+        f.write_all(data.as_bytes())?;
+        f.sync_all()?;
+        fs::rename(&tmp, &path)
+    })();
+
+    if let Err(e) = result {
+        eprintln!("Failed to write {}: {}", path.display(), e);
+        let _ = fs::remove_file(&tmp);
+    }
+}
+
+/// A parsed config file and the modification time of the file it came from.
+type CacheEntry = (SystemTime, Box<dyn Any + Send>);
+
+/// Parsed JSON configs, so bursts of loads skip re-reading unchanged files.
+fn parse_cache() -> &'static Mutex<HashMap<String, CacheEntry>> {
+    static CACHE: OnceLock<Mutex<HashMap<String, CacheEntry>>> = OnceLock::new();
+    CACHE.get_or_init(Default::default)
+}
+
+fn invalidate_cached(name: &str) {
+    if let Ok(mut cache) = parse_cache().lock() {
+        cache.remove(name);
+    }
+}
+
+fn write_json<T: Serialize>(name: &str, value: &T) {
+    match serde_json::to_string_pretty(value) {
+        Ok(data) => save_atomic(name, &data),
+        Err(e) => eprintln!("Failed to serialise {}: {}", conf_path(name).display(), e),
+    }
+}
+
+/// Read and parse `name`, reusing the previous parse while the file's
+/// mtime is unchanged.
+fn read_json<T: DeserializeOwned + Clone + Send + 'static>(name: &str) -> Option<T> {
+    if !persistence_enabled() {
+        return None;
+    }
+    let path = read_path(name);
+    let mtime = fs::metadata(&path).and_then(|m| m.modified()).ok();
+    if let (Some(mtime), Ok(cache)) = (mtime, parse_cache().lock()) {
+        if let Some((cached_at, value)) = cache.get(name) {
+            if *cached_at == mtime {
+                if let Some(v) = value.downcast_ref::<T>() {
+                    return Some(v.clone());
+                }
+            }
+        }
+    }
+
+    let data = fs::read_to_string(&path).ok()?;
+    match serde_json::from_str::<T>(&data) {
+        Ok(v) => {
+            if let (Some(mtime), Ok(mut cache)) = (mtime, parse_cache().lock()) {
+                cache.insert(name.to_string(), (mtime, Box::new(v.clone())));
+            }
+            Some(v)
+        }
+        Err(e) => {
+            eprintln!("Ignoring malformed {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+// Per-application performance-mode rules
+
+/// `(executable name, mode name)` pairs; the first running match wins.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppRulesConfig {
+    pub rules: Vec<(String, String)>,
+}
+
+impl AppRulesConfig {
+    pub fn load_or_default() -> Self {
+        read_json(APP_RULES_JSON).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        write_json(APP_RULES_JSON, self);
+    }
+}
+
 // NitroSense system config
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct NitroConfig {
     pub cpu_mode: u8,
     pub gpu_mode: u8,
@@ -46,43 +261,36 @@ pub struct NitroConfig {
     pub usb_charging: u8,
     pub nitro_mode: u8,
     pub battery_charge_limit: u8,
+    /// Step fans up gradually on large speed increases (opt-in).
+    pub fan_ramp: bool,
+    /// Lowest manual fan speed in percent; lower requests are raised to it
+    /// (0 = no floor).
+    pub manual_fan_floor: u8,
+    /// Tint the keyboard with the performance mode's colour (opt-in).
+    pub mode_lighting: bool,
 }
 
 impl NitroConfig {
     pub fn load_or_default() -> Self {
-        Self::load().unwrap_or_else(|| Self {
-            cpu_mode: 0, 
-            gpu_mode: 0,
-            kb_timeout: 0,
-            usb_charging: 0,
-            nitro_mode: 0,
-            battery_charge_limit: 0,
-        })
+        Self::load().unwrap_or_default()
     }
 
     pub fn save(&self) {
-        ensure_dir();
-        let path = conf_path(NITRO_CONF);
-        let mut f = match fs::File::create(&path) {
-            Ok(f) => f,
-            Err(e) => {
-                eprintln!("Failed to write {}: {}", path.display(), e);
-                return;
-            }
-        };
-        let _ = writeln!(f, "{}", self.cpu_mode);
-        let _ = writeln!(f, "{}", self.gpu_mode);
-        let _ = writeln!(f, "{}", self.kb_timeout);
-        let _ = writeln!(f, "{}", self.usb_charging);
-        let _ = writeln!(f, "{}", self.nitro_mode);
-        let _ = writeln!(f, "{}", self.battery_charge_limit);
+        write_json(NITRO_JSON, self);
     }
 
+    /// Load the JSON config, migrating the legacy line format if needed.
     pub fn load() -> Option<Self> {
-        let path = conf_path(NITRO_CONF);
-        if !path.exists() {
-            return None;
-        }
+        read_json(NITRO_JSON).or_else(|| {
+            let cfg = Self::load_legacy()?;
+            println!("Migrating {} to {}", NITRO_CONF, NITRO_JSON);
+            cfg.save();
+            Some(cfg)
+        })
+    }
+
+    fn load_legacy() -> Option<Self> {
+        let path = existing_conf(NITRO_CONF)?;
         let f = fs::File::open(&path).ok()?;
         let mut lines = BufReader::new(f).lines();
 
@@ -102,20 +310,35 @@ impl NitroConfig {
             usb_charging: next_u8()?,
             nitro_mode: next_u8()?,
             battery_charge_limit: next_u8()?,
+            fan_ramp: false,
+            manual_fan_floor: 0,
+            mode_lighting: false,
         })
     }
 }
 
 // Keyboard RGB config
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct RgbConfig {
     pub mode: u8,
     pub zone: u8,
     pub speed: u8,
     pub brightness: u8,
-    pub direction: u8,
+    pub direction: Direction,
     pub color: Rgb,
+    /// Static mode only: blend from `color` on zone 1 to this on zone 4.
+    pub gradient_end: Option<Rgb>,
+    /// Breathing only: colours to cycle through, one per breath, starting
+    /// with `color`.  The firmware breathes a single colour, so this needs
+    /// `software_effects`.
+    pub breathing_colors: Vec<Rgb>,
+    /// Let the daemon animate effects the firmware can't run itself.
+    pub software_effects: bool,
+    /// Logo / light-bar colour on models that have one; `None` leaves it
+    /// as the firmware set it.
+    pub logo_color: Option<Rgb>,
 }
 
 impl Default for RgbConfig {
@@ -125,38 +348,44 @@ impl Default for RgbConfig {
             zone: 0,
             speed: 0,
             brightness: 0,
-            direction: 0,
+            direction: Direction::default(),
             color: Rgb::default(),
+            gradient_end: None,
+            breathing_colors: Vec::new(),
+            software_effects: false,
+            logo_color: None,
         }
     }
 }
 
 impl RgbConfig {
+    /// Out-of-box lighting: static white on every zone, and the logo,
+    /// at full brightness.
+    pub fn factory() -> Self {
+        Self { brightness: 100, logo_color: Some(Rgb::default()), ..Self::default() }
+    }
+
+    /// Breathing through several colours, which the daemon animates.
+    pub fn software_breathing(&self) -> bool {
+        self.mode == MODE_BREATHING && self.software_effects && self.breathing_colors.len() > 1
+    }
+
     pub fn save(&self) {
-        ensure_dir();
-        let path = conf_path(RGB_CONF);
-        let mut f = match fs::File::create(&path) {
-            Ok(f) => f,
-            Err(e) => {
-                eprintln!("Failed to write {}: {}", path.display(), e);
-                return;
-            }
-        };
-        let _ = writeln!(f, "{}", self.mode);
-        let _ = writeln!(f, "{}", self.zone);
-        let _ = writeln!(f, "{}", self.speed);
-        let _ = writeln!(f, "{}", self.brightness);
-        let _ = writeln!(f, "{}", self.direction);
-        let _ = writeln!(f, "{}", self.color.r);
-        let _ = writeln!(f, "{}", self.color.g);
-        let _ = writeln!(f, "{}", self.color.b);
+        write_json(RGB_JSON, self);
     }
 
+    /// Load the JSON config, migrating the legacy line format if needed.
     pub fn load() -> Option<Self> {
-        let path = conf_path(RGB_CONF);
-        if !path.exists() {
-            return None;
-        }
+        read_json(RGB_JSON).or_else(|| {
+            let cfg = Self::load_legacy()?;
+            println!("Migrating {} to {}", RGB_CONF, RGB_JSON);
+            cfg.save();
+            Some(cfg)
+        })
+    }
+
+    fn load_legacy() -> Option<Self> {
+        let path = existing_conf(RGB_CONF)?;
         let f = fs::File::open(&path).ok()?;
         let mut lines = BufReader::new(f).lines();
 
@@ -174,12 +403,16 @@ impl RgbConfig {
             zone: next_u8()?,
             speed: next_u8()?,
             brightness: next_u8()?,
-            direction: next_u8()?,
+            direction: next_u8()?.try_into().unwrap_or_default(),
             color: Rgb {
                 r: next_u8()?,
                 g: next_u8()?,
                 b: next_u8()?,
             },
+            gradient_end: None,
+            breathing_colors: Vec::new(),
+            software_effects: false,
+            logo_color: None,
         })
     }
 }
@@ -209,29 +442,16 @@ impl TdpConfig {
     }
 
     pub fn save(&self) {
-        ensure_dir();
-        let path = conf_path(TDP_CONF);
-        let mut f = match fs::File::create(&path) {
-            Ok(f) => f,
-            Err(e) => {
-                eprintln!("Failed to write {}: {}", path.display(), e);
-                return;
-            }
-        };
-        let _ = writeln!(f, "{}", self.tdp_mw);
         let profile_idx: u8 = match self.profile {
             PowerProfile::PowerSaving => 0,
             PowerProfile::Balanced => 1,
             PowerProfile::MaxPerformance => 2,
         };
-        let _ = writeln!(f, "{}", profile_idx);
+        save_atomic(TDP_CONF, &format!("{}\n{}\n", self.tdp_mw, profile_idx));
     }
 
     pub fn load() -> Option<Self> {
-        let path = conf_path(TDP_CONF);
-        if !path.exists() {
-            return None;
-        }
+        let path = existing_conf(TDP_CONF)?;
         let f = fs::File::open(&path).ok()?;
         let mut lines = BufReader::new(f).lines();
 
@@ -247,3 +467,221 @@ impl TdpConfig {
         Some(TdpConfig { tdp_mw, profile })
     }
 }
+
+// Thermal safety config
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SafetyConfig {
+    /// Whether the daemon may override manual fan levels when overheating.
+    pub override_enabled: bool,
+    /// CPU/GPU temperature (°C) at which manual fans are forced to Turbo.
+    pub critical_temp: u8,
+    /// Opt-in last resort: power the machine off once CPU or GPU has stayed
+    /// at or above this temperature (°C) for `critical_shutdown_seconds`.
+    /// `None` (the default) never shuts down.
+    pub critical_shutdown_temp: Option<u8>,
+    /// How long the temperature must stay above the shutdown threshold, so
+    /// a momentary spike cannot power the machine off.
+    pub critical_shutdown_seconds: u32,
+    /// What to do with Turbo fans and the Extreme/Turbo modes on battery.
+    pub battery_turbo: BatteryTurboPolicy,
+}
+
+/// Battery interlock for high-power requests.  Many firmwares ignore Turbo
+/// fans on battery, and the ones that don't drain it quickly.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BatteryTurboPolicy {
+    /// Apply the request as asked.
+    #[default]
+    Allow,
+    /// Refuse with an error saying AC power is needed.
+    Refuse,
+    /// Apply Auto fans or the Default mode instead.
+    Cap,
+}
+
+impl Default for SafetyConfig {
+    fn default() -> Self {
+        Self {
+            override_enabled: true,
+            critical_temp: 90,
+            critical_shutdown_temp: None,
+            critical_shutdown_seconds: 30,
+            battery_turbo: BatteryTurboPolicy::default(),
+        }
+    }
+}
+
+impl SafetyConfig {
+    pub fn load_or_default() -> Self {
+        Self::load().unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        write_json(SAFETY_JSON, self);
+    }
+
+    /// Load the JSON config, migrating the legacy line format if needed.
+    pub fn load() -> Option<Self> {
+        read_json(SAFETY_JSON).or_else(|| {
+            let cfg = Self::load_legacy()?;
+            println!("Migrating {} to {}", SAFETY_CONF, SAFETY_JSON);
+            cfg.save();
+            Some(cfg)
+        })
+    }
+
+    fn load_legacy() -> Option<Self> {
+        let path = existing_conf(SAFETY_CONF)?;
+        let f = fs::File::open(&path).ok()?;
+        let mut lines = BufReader::new(f).lines();
+
+        let enabled: u8 = lines.next()?.ok()?.trim().parse().ok()?;
+        let critical_temp: u8 = lines.next()?.ok()?.trim().parse().ok()?;
+
+        // Later settings are optional; older files simply omit them.
+        let defaults = SafetyConfig::default();
+        let mut next_opt = || lines.next()?.ok()?.trim().parse::<u32>().ok();
+        let shutdown_temp = next_opt().unwrap_or(0);
+        let shutdown_seconds = next_opt().unwrap_or(defaults.critical_shutdown_seconds);
+        let battery_turbo = match next_opt() {
+            Some(1) => BatteryTurboPolicy::Refuse,
+            Some(2) => BatteryTurboPolicy::Cap,
+            _ => BatteryTurboPolicy::Allow,
+        };
+
+        Some(SafetyConfig {
+            override_enabled: enabled != 0,
+            critical_temp,
+            critical_shutdown_temp: u8::try_from(shutdown_temp).ok().filter(|&t| t > 0),
+            critical_shutdown_seconds: shutdown_seconds,
+            battery_turbo,
+        })
+    }
+}
+
+// GUI client preferences
+
+/// Unit used to display temperatures.  The daemon always reports Celsius.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum TempUnit {
+    #[default]
+    Celsius,
+    Fahrenheit,
+}
+
+impl TempUnit {
+    /// Convert a Celsius value to this unit.
+    pub fn celsius_to_unit(self, celsius: f64) -> f64 {
+        match self {
+            TempUnit::Celsius => celsius,
+            TempUnit::Fahrenheit => celsius * 9.0 / 5.0 + 32.0,
+        }
+    }
+
+    /// Convert a value in this unit back to Celsius.
+    pub fn to_celsius(self, value: f64) -> f64 {
+        match self {
+            TempUnit::Celsius => value,
+            TempUnit::Fahrenheit => (value - 32.0) * 5.0 / 9.0,
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            TempUnit::Celsius => "°C",
+            TempUnit::Fahrenheit => "°F",
+        }
+    }
+
+    /// Format a Celsius reading in this unit, e.g. `"72°C"` or `"162°F"`.
+    pub fn format(&self, celsius: u8) -> String {
+        format!("{}{}", self.celsius_to_unit(celsius as f64).round(), self.symbol())
+    }
+}
+
+/// GUI colour scheme.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Theme {
+    /// The built-in NitroSense dark stylesheet.
+    #[default]
+    Dark,
+    /// Plain GTK theme from the desktop.
+    System,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClientConfig {
+    pub temp_unit: TempUnit,
+    /// How often the GUI polls the daemon, in milliseconds.
+    pub poll_interval_ms: u32,
+    pub theme: Theme,
+    /// Temperatures (°C) at or above this are highlighted in the UI.
+    /// `None` follows the model's rated maximum.
+    pub temp_alarm: Option<u8>,
+    /// Daemon socket to use when `--socket` is not given.
+    pub socket_path: Option<String>,
+    /// Show fan RPM as an exponential moving average instead of raw readings.
+    pub smooth_rpm: bool,
+    /// Weight of the newest reading, 0.0 (frozen) to 1.0 (no smoothing).
+    pub rpm_smoothing_alpha: f64,
+    /// Preferred manual fan slider step in percent (rounded up to what the
+    /// model supports).
+    pub fan_step_percent: u8,
+    /// Write keyboard changes as they are made instead of on "Apply".
+    pub kb_live_apply: bool,
+    /// Switch both fans to Turbo when Turbo mode is picked, and back to
+    /// Auto when another mode is.  Independent of the EC mode itself.
+    pub turbo_max_fans: bool,
+    /// Window size saved on close and restored at startup.
+    pub window_width: i32,
+    pub window_height: i32,
+    pub window_maximized: bool,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            temp_unit: TempUnit::default(),
+            poll_interval_ms: 1500,
+            theme: Theme::default(),
+            temp_alarm: None,
+            socket_path: None,
+            smooth_rpm: true,
+            rpm_smoothing_alpha: 0.3,
+            fan_step_percent: 5,
+            kb_live_apply: false,
+            turbo_max_fans: true,
+            window_width: 780,
+            window_height: 520,
+            window_maximized: false,
+        }
+    }
+}
+
+impl ClientConfig {
+    pub fn load_or_default() -> Self {
+        Self::load().unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        match toml::to_string_pretty(self) {
+            Ok(data) => save_atomic(CLIENT_CONF, &data),
+            Err(e) => eprintln!("Failed to serialise {}: {}", conf_path(CLIENT_CONF).display(), e),
+        }
+    }
+
+    pub fn load() -> Option<Self> {
+        let path = read_path(CLIENT_CONF);
+        let data = fs::read_to_string(&path).ok()?;
+        match toml::from_str(&data) {
+            Ok(cfg) => Some(cfg),
+            Err(e) => {
+                eprintln!("Ignoring malformed {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+}
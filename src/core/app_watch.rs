@@ -0,0 +1,31 @@
+//! Running-process scan for per-application performance-mode rules.
+//!
+//! Rules match on the executable's file name, e.g. `cyberpunk2077.exe`
+//! under Proton or `dota2` natively.  `/proc/<pid>/exe` gives the full
+//! name (the daemon runs as root, so every process is readable); `comm`,
+//! which the kernel truncates to 15 bytes, is the fallback.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+const PROC_DIR: &str = "/proc";
+
+fn exe_name(proc_dir: &Path) -> Option<String> {
+    let from_exe = fs::read_link(proc_dir.join("exe"))
+        .ok()
+        .and_then(|p| p.file_name().map(|n| n.to_string_lossy().into_owned()));
+    from_exe.or_else(|| fs::read_to_string(proc_dir.join("comm")).ok().map(|s| s.trim().to_string()))
+}
+
+/// Names of every running executable.
+pub fn running_exes() -> HashSet<String> {
+    let Ok(entries) = fs::read_dir(PROC_DIR) else {
+        return HashSet::new();
+    };
+    entries
+        .flatten()
+        .filter(|e| e.file_name().to_string_lossy().bytes().all(|b| b.is_ascii_digit()))
+        .filter_map(|e| exe_name(&e.path()))
+        .collect()
+}
@@ -4,6 +4,8 @@
 /// [`CpuType`].  On unsupported CPUs every operation is a no-op that returns
 /// a human-readable message.
 
+use std::env;
+use std::path::Path;
 use std::process::Command;
 
 use serde::{Deserialize, Serialize};
@@ -15,7 +17,7 @@ use super::device_regs::CpuType;
 // ---------------------------------------------------------------------------
 
 /// Snapshot of the current voltage state.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct VoltageInfo {
     pub voltage: f64,
     pub min_recorded: f64,
@@ -43,6 +45,12 @@ impl VoltageInfo {
             self.max_recorded = v;
         }
     }
+
+    /// Restart min/max tracking from the current reading.
+    pub fn reset_stats(&mut self) {
+        self.min_recorded = self.voltage;
+        self.max_recorded = self.voltage;
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -57,6 +65,12 @@ fn run_command(cmd: &str, args: &[&str]) -> String {
         .unwrap_or_default()
 }
 
+/// Whether `cmd` is an executable somewhere on `PATH`.
+pub fn in_path(cmd: &str) -> bool {
+    env::var_os("PATH")
+        .is_some_and(|paths| env::split_paths(&paths).any(|dir| dir.join(cmd).is_file()))
+}
+
 // ---------------------------------------------------------------------------
 // AMD backend
 // ---------------------------------------------------------------------------
@@ -101,7 +115,7 @@ mod amd {
         check_undervolt_status()
     }
 
-    pub fn check_voltage(info: &mut VoltageInfo) {
+    pub fn read_voltage() -> Option<f64> {
         let raw = run_command("amdctl", &["-g", "-c0"]);
         let mut voltages = Vec::new();
 
@@ -115,10 +129,10 @@ mod amd {
             }
         }
 
-        if !voltages.is_empty() {
-            let avg = voltages.iter().sum::<f64>() / voltages.len() as f64;
-            info.update(avg);
+        if voltages.is_empty() {
+            return None;
         }
+        Some(voltages.iter().sum::<f64>() / voltages.len() as f64)
     }
 }
 
@@ -137,7 +151,7 @@ mod intel {
         "Undervolt not supported for Intel CPUs.".to_string()
     }
 
-    pub fn check_voltage(info: &mut VoltageInfo) {
+    pub fn read_voltage() -> Option<f64> {
         // `rdmsr 0x198` – reads IA32_PERF_STATUS from all cores
         let raw = run_command("sudo", &["rdmsr", "0x198", "-a", "-u", "--bitfield", "47:32"]);
 
@@ -146,11 +160,11 @@ mod intel {
             .filter_map(|l| l.trim().parse::<f64>().ok())
             .collect();
 
-        if !values.is_empty() {
-            let avg = values.iter().sum::<f64>() / values.len() as f64;
-            let voltage = avg / 8192.0;
-            info.update(voltage);
+        if values.is_empty() {
+            return None;
         }
+        let avg = values.iter().sum::<f64>() / values.len() as f64;
+        Some(avg / 8192.0)
     }
 }
 
@@ -165,12 +179,14 @@ pub struct CpuController {
     pub undervolt_status: String,
 }
 
+const UNKNOWN_VENDOR_MSG: &str = "CPU vendor not detected – undervolt is disabled.";
+
 impl CpuController {
     pub fn new(cpu_type: CpuType) -> Self {
         let undervolt_status = match cpu_type {
             CpuType::Amd => amd::check_undervolt_status(),
             CpuType::Intel => intel::check_undervolt_status(),
-            CpuType::Unknown => "Undervolt not supported for this CPU type.".into(),
+            CpuType::Unknown => UNKNOWN_VENDOR_MSG.into(),
         };
 
         Self {
@@ -184,15 +200,35 @@ impl CpuController {
         self.undervolt_status = match self.cpu_type {
             CpuType::Amd => amd::apply_undervolt(dropdown_index),
             CpuType::Intel => intel::apply_undervolt(dropdown_index),
-            CpuType::Unknown => "Undervolt not supported for this CPU type.".into(),
+            CpuType::Unknown => UNKNOWN_VENDOR_MSG.into(),
         };
     }
 
-    pub fn refresh_voltage(&mut self) {
-        match self.cpu_type {
-            CpuType::Amd => amd::check_voltage(&mut self.voltage_info),
-            CpuType::Intel => intel::check_voltage(&mut self.voltage_info),
-            CpuType::Unknown => {}
-        }
+    /// Whether undervolting and voltage readings are available at all.
+    pub fn is_supported(&self) -> bool {
+        self.cpu_type != CpuType::Unknown
+    }
+
+    pub fn cpu_type(&self) -> CpuType {
+        self.cpu_type
+    }
+}
+
+/// Whether the external tools the backend shells out to are installed.
+pub fn tooling_available(cpu_type: CpuType) -> bool {
+    match cpu_type {
+        CpuType::Amd => in_path("amdctl"),
+        CpuType::Intel => in_path("rdmsr") && Path::new("/dev/cpu/0/msr").exists(),
+        CpuType::Unknown => false,
+    }
+}
+
+/// Take one voltage sample without touching any controller state, so the
+/// slow `amdctl`/`rdmsr` call can run without holding a lock.
+pub fn read_voltage(cpu_type: CpuType) -> Option<f64> {
+    match cpu_type {
+        CpuType::Amd => amd::read_voltage(),
+        CpuType::Intel => intel::read_voltage(),
+        CpuType::Unknown => None,
     }
 }
@@ -6,18 +6,27 @@
 /// writing the wrong value to the wrong register can brick your firmware.
 
 use std::collections::HashMap;
+use std::env;
 use std::fs;
+use std::io;
+use std::ops::RangeInclusive;
 use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::keyboard::KeyboardLayout;
 
 // ---------------------------------------------------------------------------
 // Register set
 // ---------------------------------------------------------------------------
 
 /// Complete set of EC register addresses for one laptop model.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct EcRegisters {
-    // GPU fan
+    // GPU fan (ignored unless `ModelCaps::independent_gpu_fan`)
     pub gpu_fan_mode_control: u8,
     pub gpu_auto_mode: u8,
     pub gpu_turbo_mode: u8,
@@ -31,21 +40,37 @@ pub struct EcRegisters {
     pub cpu_manual_mode: u8,
     pub cpu_manual_speed_control: u8,
 
+    /// Firmware fan-stop mode values for the fan mode registers (ignored
+    /// unless `ModelCaps::fan_stop`; not yet confirmed on any model).
+    pub cpu_passive_mode: u8,
+    pub gpu_passive_mode: u8,
+
     // Keyboard backlight timeout
     pub kb_30_sec_auto: u8,
     pub kb_30_auto_off: u8,
     pub kb_30_auto_on: u8,
+    /// Current backlight brightness, 0-100 (ignored unless
+    /// `ModelCaps::kb_brightness_readback`; address not yet confirmed).
+    pub kb_brightness: u8,
 
     // Fan speed readback
     pub cpu_fan_speed_high: u8,
     pub cpu_fan_speed_low: u8,
     pub gpu_fan_speed_high: u8,
     pub gpu_fan_speed_low: u8,
+    /// Commanded fan duty, 0-100 (ignored unless
+    /// `ModelCaps::fan_duty_readback`; addresses not yet confirmed).
+    pub cpu_fan_duty: u8,
+    pub gpu_fan_duty: u8,
 
     // Temperatures
     pub cpu_temp: u8,
     pub gpu_temp: u8,
     pub sys_temp: u8,
+    /// Added to the raw temperature reading, e.g. -40 for `raw - 40`.
+    pub temp_offset: i16,
+    /// Temperature registers hold two's-complement values.
+    pub temp_is_signed: bool,
 
     // Power / battery
     pub power_status: u8,
@@ -55,6 +80,9 @@ pub struct EcRegisters {
     pub battery_charge_limit: u8,
     pub battery_limit_on: u8,
     pub battery_limit_off: u8,
+    /// Handshake the firmware wants before a charge-limit write (`None`
+    /// when writes are accepted directly).
+    pub battery_limit_unlock: Option<EcUnlock>,
 
     pub battery_status: u8,
     pub battery_charging: u8,
@@ -65,12 +93,138 @@ pub struct EcRegisters {
     pub usb_charging_reg: u8,
     pub usb_charging_on: u8,
     pub usb_charging_off: u8,
+    /// Same, for USB-charging writes.
+    pub usb_charging_unlock: Option<EcUnlock>,
 
     // Nitro performance mode
     pub nitro_mode: u8,
     pub quiet_mode: u8,
     pub default_mode: u8,
     pub extreme_mode: u8,
+    /// Fourth performance level (ignored unless `ModelCaps::turbo_mode`).
+    pub turbo_mode: u8,
+
+    // CoolBoost toggle (ignored unless `ModelCaps::coolboost`)
+    pub coolboost_reg: u8,
+    pub coolboost_on: u8,
+    pub coolboost_off: u8,
+
+    // Windows/Super key lock (ignored unless `ModelCaps::win_key_lock`)
+    pub win_key_lock: u8,
+    pub win_key_lock_on: u8,
+    pub win_key_lock_off: u8,
+
+    // Touchpad lock (ignored unless `ModelCaps::touchpad_lock`)
+    pub touchpad_lock: u8,
+    pub touchpad_lock_on: u8,
+    pub touchpad_lock_off: u8,
+}
+
+/// Byte some firmware expects in a companion register before it accepts a
+/// write; without it the write is silently ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EcUnlock {
+    pub reg: u8,
+    pub value: u8,
+}
+
+// ---------------------------------------------------------------------------
+// Capabilities
+// ---------------------------------------------------------------------------
+
+/// Optional features a model supports.  Register addresses for a feature
+/// live in [`EcRegisters`] and are only used when the capability is set.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelCaps {
+    /// Separate GPU fan controller.  Without one, GPU fan requests drive
+    /// the CPU fan (the only fan).
+    pub independent_gpu_fan: bool,
+    /// Fourth "Turbo" performance level above Extreme.
+    pub turbo_mode: bool,
+    /// The EC can stop the fans below a temperature by itself.  Without
+    /// it, passive mode is emulated with Manual 0%.
+    pub fan_stop: bool,
+    /// Dedicated CoolBoost toggle.
+    pub coolboost: bool,
+    /// EC can disable the Windows/Super key.
+    pub win_key_lock: bool,
+    /// EC can disable the touchpad.
+    pub touchpad_lock: bool,
+    /// Wattage of the adapter the model ships with.
+    pub rated_adapter_watts: u16,
+    /// Rated CPU TDP in watts (0 = unknown).  Typical for the map; models
+    /// sharing it may ship different CPUs.
+    pub tdp_watts: u16,
+    /// Highest CPU temperature (°C) the CPU is rated for (0 = unknown).
+    pub max_temp: u8,
+    /// Keyboard backlight zones.
+    pub keyboard: KeyboardLayout,
+    /// Separate logo or front light-bar LED on its own static zone.
+    pub logo_zone: bool,
+    /// Backlight brightness can be read back from the EC.
+    pub kb_brightness_readback: bool,
+    /// The duty cycle the EC is driving each fan at can be read back.
+    pub fan_duty_readback: bool,
+    /// Manual fan speed granularity in percent; the EC only honours
+    /// multiples of this (1 = continuous).
+    pub fan_step_percent: u8,
+    /// Highest accepted manual fan speed in percent.
+    pub fan_max_percent: u8,
+    /// Fan speed and duty register value meaning 100% (100 where the
+    /// registers hold percent directly).
+    pub fan_raw_max: u8,
+    /// Approximate top fan speed in RPM (0 = unknown).  Readings well
+    /// above it are taken as torn EC reads.
+    pub max_fan_rpm: u16,
+    /// The EC's GPU fan registers follow a shared chassis fan, so the
+    /// dGPU's own fan is read from the NVIDIA driver instead.
+    pub gpu_fan_from_nvidia: bool,
+    /// Register map not yet confirmed on real hardware.
+    pub experimental: bool,
+}
+
+impl ModelCaps {
+    /// Register value for a manual fan speed in percent, clamped to
+    /// `fan_max_percent` and snapped to the nearest `fan_step_percent`.
+    /// The daemon is the only place that should convert.
+    pub fn fan_percent_to_raw(&self, percent: u8) -> u8 {
+        let step = self.fan_step_percent.max(1) as u16;
+        let snapped = ((percent as u16 + step / 2) / step * step).min(self.fan_max_percent.min(100) as u16);
+        let raw_max = self.fan_raw_max.max(1) as u16;
+        ((snapped * raw_max + 50) / 100) as u8
+    }
+
+    /// Percent (0-100) for a fan speed or duty register value.
+    pub fn fan_raw_to_percent(&self, raw: u8) -> u8 {
+        let raw_max = self.fan_raw_max.max(1) as u16;
+        ((raw as u16 * 100 + raw_max / 2) / raw_max).min(100) as u8
+    }
+
+    /// Whether a fan could really be spinning at `rpm`: anything over 1.5×
+    /// the model's top speed is a glitch, e.g. 0xFFFF from a read that
+    /// caught the high and low bytes mid-update.
+    pub fn rpm_plausible(&self, rpm: u16) -> bool {
+        self.max_fan_rpm == 0 || rpm as u32 * 2 <= self.max_fan_rpm as u32 * 3
+    }
+
+    /// Names of the optional features that are present, for display.
+    pub fn feature_names(&self) -> Vec<&'static str> {
+        [
+            (self.independent_gpu_fan, "separate GPU fan"),
+            (self.turbo_mode, "Turbo mode"),
+            (self.fan_stop, "fan stop"),
+            (self.coolboost, "CoolBoost"),
+            (self.win_key_lock, "Windows key lock"),
+            (self.touchpad_lock, "touchpad lock"),
+            (self.logo_zone, "logo lighting"),
+            (self.kb_brightness_readback, "brightness readback"),
+            (self.fan_duty_readback, "fan duty readback"),
+            (self.gpu_fan_from_nvidia, "GPU fan from NVIDIA driver"),
+        ]
+        .into_iter()
+        .filter_map(|(present, name)| present.then_some(name))
+        .collect()
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -90,19 +244,26 @@ pub const ECS_AN515_46: EcRegisters = EcRegisters {
     cpu_turbo_mode: 0x08,
     cpu_manual_mode: 0x0C,
     cpu_manual_speed_control: 0x37,
+    cpu_passive_mode: 0x00,
+    gpu_passive_mode: 0x00,
 
     kb_30_sec_auto: 0x06,
     kb_30_auto_off: 0x00,
     kb_30_auto_on: 0x1E,
+    kb_brightness: 0x00,
 
     cpu_fan_speed_high: 0x13,
     cpu_fan_speed_low: 0x14,
     gpu_fan_speed_high: 0x15,
     gpu_fan_speed_low: 0x16,
+    cpu_fan_duty: 0x00,
+    gpu_fan_duty: 0x00,
 
     cpu_temp: 0xB0,
     gpu_temp: 0xB6,
     sys_temp: 0xB3,
+    temp_offset: 0,
+    temp_is_signed: false,
 
     power_status: 0x00,
     power_plugged_in: 0x01,
@@ -111,6 +272,7 @@ pub const ECS_AN515_46: EcRegisters = EcRegisters {
     battery_charge_limit: 0x03,
     battery_limit_on: 0x51,
     battery_limit_off: 0x11,
+    battery_limit_unlock: None,
 
     battery_status: 0xC1,
     battery_charging: 0x02,
@@ -120,11 +282,25 @@ pub const ECS_AN515_46: EcRegisters = EcRegisters {
     usb_charging_reg: 0x08,
     usb_charging_on: 0x0F,
     usb_charging_off: 0x1F,
+    usb_charging_unlock: None,
 
     nitro_mode: 0x2C,
     quiet_mode: 0x00,
     default_mode: 0x01,
     extreme_mode: 0x04,
+    turbo_mode: 0x00,
+
+    coolboost_reg: 0x00,
+    coolboost_on: 0x00,
+    coolboost_off: 0x00,
+
+    win_key_lock: 0x00,
+    win_key_lock_on: 0x00,
+    win_key_lock_off: 0x00,
+
+    touchpad_lock: 0x00,
+    touchpad_lock_on: 0x00,
+    touchpad_lock_off: 0x00,
 };
 
 /// AN515-44 register set (some addresses differ).
@@ -140,19 +316,26 @@ pub const ECS_AN515_44: EcRegisters = EcRegisters {
     cpu_turbo_mode: 0x08,
     cpu_manual_mode: 0x0C,
     cpu_manual_speed_control: 0x37,
+    cpu_passive_mode: 0x00,
+    gpu_passive_mode: 0x00,
 
     kb_30_sec_auto: 0x06,
     kb_30_auto_off: 0x00,
     kb_30_auto_on: 0x1E,
+    kb_brightness: 0x00,
 
     cpu_fan_speed_high: 0x13,
     cpu_fan_speed_low: 0x14,
     gpu_fan_speed_high: 0x15,
     gpu_fan_speed_low: 0x16,
+    cpu_fan_duty: 0x00,
+    gpu_fan_duty: 0x00,
 
     cpu_temp: 0xB0,
     gpu_temp: 0xB4,
     sys_temp: 0xB0,
+    temp_offset: 0,
+    temp_is_signed: false,
 
     power_status: 0x00,
     power_plugged_in: 0x01,
@@ -161,6 +344,82 @@ pub const ECS_AN515_44: EcRegisters = EcRegisters {
     battery_charge_limit: 0x03,
     battery_limit_on: 0x40,
     battery_limit_off: 0x00,
+    battery_limit_unlock: None,
+
+    battery_status: 0xC1,
+    battery_charging: 0x02,
+    battery_draining: 0x01,
+    battery_off: 0x00,
+
+    usb_charging_reg: 0x08,
+    usb_charging_on: 0x0F,
+    usb_charging_off: 0x1F,
+    usb_charging_unlock: None,
+
+    nitro_mode: 0x2C,
+    quiet_mode: 0x00,
+    default_mode: 0x01,
+    extreme_mode: 0x04,
+    turbo_mode: 0x00,
+
+    coolboost_reg: 0x00,
+    coolboost_on: 0x00,
+    coolboost_off: 0x00,
+
+    win_key_lock: 0x00,
+    win_key_lock_on: 0x00,
+    win_key_lock_off: 0x00,
+
+    touchpad_lock: 0x00,
+    touchpad_lock_on: 0x00,
+    touchpad_lock_off: 0x00,
+};
+
+/// Predator Helios PH315-54 / PH317-56 register set.
+///
+/// **Experimental** – shares the Nitro fan/battery layout but adds a fourth
+/// "Turbo" performance level.  Not yet confirmed on real hardware.
+pub const ECS_PH315_54: EcRegisters = EcRegisters {
+    gpu_fan_mode_control: 0x21,
+    gpu_auto_mode: 0x10,
+    gpu_turbo_mode: 0x20,
+    gpu_manual_mode: 0x30,
+    gpu_manual_speed_control: 0x3A,
+
+    cpu_fan_mode_control: 0x22,
+    cpu_auto_mode: 0x04,
+    cpu_turbo_mode: 0x08,
+    cpu_manual_mode: 0x0C,
+    cpu_manual_speed_control: 0x37,
+    cpu_passive_mode: 0x00,
+    gpu_passive_mode: 0x00,
+
+    kb_30_sec_auto: 0x06,
+    kb_30_auto_off: 0x00,
+    kb_30_auto_on: 0x1E,
+    kb_brightness: 0x00,
+
+    cpu_fan_speed_high: 0x13,
+    cpu_fan_speed_low: 0x14,
+    gpu_fan_speed_high: 0x15,
+    gpu_fan_speed_low: 0x16,
+    cpu_fan_duty: 0x00,
+    gpu_fan_duty: 0x00,
+
+    cpu_temp: 0xB0,
+    gpu_temp: 0xB7,
+    sys_temp: 0xB3,
+    temp_offset: 0,
+    temp_is_signed: false,
+
+    power_status: 0x00,
+    power_plugged_in: 0x01,
+    power_unplugged: 0x00,
+
+    battery_charge_limit: 0x03,
+    battery_limit_on: 0x51,
+    battery_limit_off: 0x11,
+    battery_limit_unlock: None,
 
     battery_status: 0xC1,
     battery_charging: 0x02,
@@ -170,18 +429,203 @@ pub const ECS_AN515_44: EcRegisters = EcRegisters {
     usb_charging_reg: 0x08,
     usb_charging_on: 0x0F,
     usb_charging_off: 0x1F,
+    usb_charging_unlock: None,
 
     nitro_mode: 0x2C,
     quiet_mode: 0x00,
     default_mode: 0x01,
     extreme_mode: 0x04,
+    turbo_mode: 0x05,
+
+    coolboost_reg: 0x00,
+    coolboost_on: 0x00,
+    coolboost_off: 0x00,
+
+    win_key_lock: 0x00,
+    win_key_lock_on: 0x00,
+    win_key_lock_off: 0x00,
+
+    touchpad_lock: 0x00,
+    touchpad_lock_on: 0x00,
+    touchpad_lock_off: 0x00,
+};
+
+/// Capabilities of the models using [`ECS_AN515_46`].
+pub const CAPS_AN515_46: ModelCaps = ModelCaps {
+    independent_gpu_fan: true,
+    turbo_mode: false,
+    fan_stop: false,
+    coolboost: false,
+    win_key_lock: false,
+    touchpad_lock: false,
+    rated_adapter_watts: 180,
+    tdp_watts: 45,
+    max_temp: 95,
+    keyboard: KeyboardLayout::FourZone,
+    logo_zone: false,
+    kb_brightness_readback: false,
+    fan_duty_readback: false,
+    fan_step_percent: 5,
+    fan_max_percent: 100,
+    fan_raw_max: 100,
+    max_fan_rpm: 6000,
+    gpu_fan_from_nvidia: false,
+    experimental: false,
+};
+
+/// Capabilities of the AN515-44.
+pub const CAPS_AN515_44: ModelCaps = ModelCaps {
+    independent_gpu_fan: false,
+    turbo_mode: false,
+    fan_stop: false,
+    coolboost: false,
+    win_key_lock: false,
+    touchpad_lock: false,
+    rated_adapter_watts: 135,
+    tdp_watts: 45,
+    max_temp: 105,
+    keyboard: KeyboardLayout::FourZone,
+    logo_zone: false,
+    kb_brightness_readback: false,
+    fan_duty_readback: false,
+    fan_step_percent: 5,
+    fan_max_percent: 100,
+    fan_raw_max: 100,
+    max_fan_rpm: 5500,
+    gpu_fan_from_nvidia: false,
+    experimental: false,
+};
+
+/// Capabilities of the Predator Helios models using [`ECS_PH315_54`].
+pub const CAPS_PH315_54: ModelCaps = ModelCaps {
+    independent_gpu_fan: true,
+    turbo_mode: true,
+    fan_stop: false,
+    coolboost: false,
+    win_key_lock: false,
+    touchpad_lock: false,
+    rated_adapter_watts: 180,
+    tdp_watts: 45,
+    max_temp: 100,
+    keyboard: KeyboardLayout::FourZone,
+    logo_zone: false,
+    kb_brightness_readback: false,
+    fan_duty_readback: false,
+    fan_step_percent: 5,
+    fan_max_percent: 100,
+    fan_raw_max: 100,
+    max_fan_rpm: 6500,
+    gpu_fan_from_nvidia: false,
+    experimental: true,
 };
 
+/// How long implausible RPM readings are replaced before they are
+/// believed; about one client poll.
+const RPM_GLITCH_GRACE: Duration = Duration::from_secs(1);
+
+/// De-glitches one fan's RPM: a reading the model can't reach is replaced
+/// by the last good one.  Implausible readings that last longer than
+/// [`RPM_GLITCH_GRACE`] are passed through, so a fan that really keeps
+/// reporting them isn't hidden.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RpmFilter {
+    last_good: u16,
+    rejecting_since: Option<Instant>,
+}
+
+impl RpmFilter {
+    /// The RPM to report for a `raw` reading taken at `now`.
+    pub fn filter(&mut self, raw: u16, caps: &ModelCaps, now: Instant) -> u16 {
+        if caps.rpm_plausible(raw) {
+            self.last_good = raw;
+            self.rejecting_since = None;
+            return raw;
+        }
+        let since = *self.rejecting_since.get_or_insert(now);
+        if now.duration_since(since) < RPM_GLITCH_GRACE { self.last_good } else { raw }
+    }
+}
+
+/// Pause before the one retry of a failed procfs/sysfs read.
+const READ_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Plausible decoded temperatures in °C; anything outside is a bad read.
+pub const TEMP_RANGE: RangeInclusive<i16> = 0..=120;
+
+impl EcRegisters {
+    /// Every register address in the map by field name, skipping those of
+    /// features `caps` says the model lacks (their addresses are
+    /// placeholders).  Values written to registers are not included.
+    pub fn named_addresses(&self, caps: &ModelCaps) -> Vec<(&'static str, u8)> {
+        let mut regs = vec![
+            ("cpu_fan_mode_control", self.cpu_fan_mode_control),
+            ("cpu_manual_speed_control", self.cpu_manual_speed_control),
+            ("kb_30_sec_auto", self.kb_30_sec_auto),
+            ("cpu_fan_speed_high", self.cpu_fan_speed_high),
+            ("cpu_fan_speed_low", self.cpu_fan_speed_low),
+            ("gpu_fan_speed_high", self.gpu_fan_speed_high),
+            ("gpu_fan_speed_low", self.gpu_fan_speed_low),
+            ("cpu_temp", self.cpu_temp),
+            ("gpu_temp", self.gpu_temp),
+            ("sys_temp", self.sys_temp),
+            ("power_status", self.power_status),
+            ("battery_charge_limit", self.battery_charge_limit),
+            ("battery_status", self.battery_status),
+            ("usb_charging_reg", self.usb_charging_reg),
+            ("nitro_mode", self.nitro_mode),
+        ];
+        if caps.independent_gpu_fan {
+            regs.push(("gpu_fan_mode_control", self.gpu_fan_mode_control));
+            regs.push(("gpu_manual_speed_control", self.gpu_manual_speed_control));
+        }
+        if caps.kb_brightness_readback {
+            regs.push(("kb_brightness", self.kb_brightness));
+        }
+        if caps.fan_duty_readback {
+            regs.push(("cpu_fan_duty", self.cpu_fan_duty));
+            regs.push(("gpu_fan_duty", self.gpu_fan_duty));
+        }
+        if caps.coolboost {
+            regs.push(("coolboost_reg", self.coolboost_reg));
+        }
+        if caps.win_key_lock {
+            regs.push(("win_key_lock", self.win_key_lock));
+        }
+        if caps.touchpad_lock {
+            regs.push(("touchpad_lock", self.touchpad_lock));
+        }
+        regs
+    }
+
+    /// Unlock handshake to perform before writing `address`, if any.
+    pub fn unlock_for(&self, address: u8) -> Option<EcUnlock> {
+        if address == self.battery_charge_limit {
+            self.battery_limit_unlock
+        } else if address == self.usb_charging_reg {
+            self.usb_charging_unlock
+        } else {
+            None
+        }
+    }
+
+    /// Decode a raw temperature register to °C.  `Err` carries the decoded
+    /// value when it falls outside [`TEMP_RANGE`].
+    pub fn decode_temp(&self, raw: u8) -> Result<u8, i16> {
+        let value = if self.temp_is_signed { raw as i8 as i16 } else { raw as i16 };
+        let celsius = value + self.temp_offset;
+        if TEMP_RANGE.contains(&celsius) {
+            Ok(celsius as u8)
+        } else {
+            Err(celsius)
+        }
+    }
+}
+
 // ---------------------------------------------------------------------------
 // CPU type detection
 // ---------------------------------------------------------------------------
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum CpuType {
     Amd,
     Intel,
@@ -192,17 +636,35 @@ pub enum CpuType {
 // Model → register map
 // ---------------------------------------------------------------------------
 
-fn model_to_ecs() -> HashMap<&'static str, EcRegisters> {
+fn model_to_ecs() -> HashMap<&'static str, (EcRegisters, ModelCaps)> {
     let mut m = HashMap::new();
-    m.insert("Nitro AN515-44", ECS_AN515_44);
-    m.insert("Nitro AN515-46", ECS_AN515_46);
-    m.insert("Nitro AN515-54", ECS_AN515_46);
-    m.insert("Nitro AN515-56", ECS_AN515_46);
-    m.insert("Nitro AN515-57", ECS_AN515_46);
-    m.insert("Nitro AN515-58", ECS_AN515_46);
+    m.insert("Nitro AN515-44", (ECS_AN515_44, CAPS_AN515_44));
+    m.insert("Nitro AN515-46", (ECS_AN515_46, CAPS_AN515_46));
+    m.insert("Nitro AN515-54", (ECS_AN515_46, CAPS_AN515_46));
+    m.insert("Nitro AN515-56", (ECS_AN515_46, CAPS_AN515_46));
+    m.insert("Nitro AN515-57", (ECS_AN515_46, CAPS_AN515_46));
+    m.insert("Nitro AN515-58", (ECS_AN515_46, CAPS_AN515_46));
+    m.insert("Predator PH315-54", (ECS_PH315_54, CAPS_PH315_54));
+    m.insert("Predator PH317-56", (ECS_PH315_54, CAPS_PH315_54));
     m
 }
 
+/// A model's register map and capabilities as one shareable document
+/// (`--cli export-regs`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelMap {
+    /// DMI product name the map was taken from.
+    pub model: String,
+    pub registers: EcRegisters,
+    pub caps: ModelCaps,
+}
+
+fn warn_if_experimental(name: &str, caps: &ModelCaps) {
+    if caps.experimental {
+        eprintln!("Warning: support for {name} is experimental – register map unconfirmed.");
+    }
+}
+
 // ---------------------------------------------------------------------------
 // DMI helpers (reads directly from sysfs, no external crate needed)
 // ---------------------------------------------------------------------------
@@ -212,52 +674,246 @@ fn read_dmi_field(field: &str) -> Option<String> {
     fs::read_to_string(path).ok().map(|s| s.trim().to_string())
 }
 
-fn detect_model() -> String {
-    // product_name usually contains e.g. "Nitro AN515-46"
-    read_dmi_field("product_name").unwrap_or_else(|| "Unknown".into())
+/// Fields that may name the model, best first.  `product_name` usually
+/// holds e.g. "Nitro AN515-46"; the others rescue boards whose firmware
+/// left it blank.
+const DMI_MODEL_FIELDS: [&str; 4] = ["product_name", "board_name", "product_sku", "product_family"];
+
+/// Fields worth attaching to a bug report.  Serial numbers and UUIDs are
+/// left out on purpose.
+const DMI_REPORT_FIELDS: [&str; 7] =
+    ["sys_vendor", "product_name", "product_family", "product_sku", "board_name", "bios_version", "bios_date"];
+
+/// The [`DMI_REPORT_FIELDS`] as `(field, value)`, `None` where unreadable.
+pub fn dmi_report() -> Vec<(&'static str, Option<String>)> {
+    DMI_REPORT_FIELDS.iter().map(|&field| (field, read_dmi_field(field))).collect()
+}
+
+/// OEM filler found in DMI fields instead of a real value (lowercase).
+const DMI_PLACEHOLDERS: [&str; 12] = [
+    "to be filled by o.e.m.",
+    "default string",
+    "system product name",
+    "system version",
+    "not applicable",
+    "not specified",
+    "type1productconfigid",
+    "0123456789",
+    "none",
+    "n/a",
+    "oem",
+    "unknown",
+];
+
+fn is_placeholder(value: &str) -> bool {
+    let lower = value.trim().to_lowercase();
+    !lower.chars().any(char::is_alphanumeric) || DMI_PLACEHOLDERS.contains(&lower.as_str())
+}
+
+/// Every DMI model string worth matching, best first.
+fn dmi_model_candidates() -> Vec<String> {
+    DMI_MODEL_FIELDS
+        .iter()
+        .filter_map(|field| read_dmi_field(field))
+        .filter(|value| !is_placeholder(value))
+        .collect()
+}
+
+/// Map a vendor string ("AuthenticAMD", "Intel(R) Corporation", …) to a CPU type.
+fn cpu_type_from_vendor(vendor: &str) -> Option<CpuType> {
+    let lower = vendor.trim().to_lowercase();
+    if lower.contains("authenticamd") || lower.starts_with("amd") || lower.starts_with("advanced micro devices") {
+        Some(CpuType::Amd)
+    } else if lower.contains("genuineintel") || lower.starts_with("intel") {
+        Some(CpuType::Intel)
+    } else {
+        None
+    }
+}
+
+/// Read a procfs/sysfs file, retrying once: procfs reads can fail
+/// transiently while the system is still coming up.
+fn read_with_retry(path: &str) -> io::Result<Vec<u8>> {
+    fs::read(path).or_else(|_| {
+        thread::sleep(READ_RETRY_DELAY);
+        fs::read(path)
+    })
+}
+
+/// `vendor_id` (x86) or, failing that, the `model name` line of `/proc/cpuinfo`.
+fn cpu_type_from_cpuinfo() -> Result<CpuType, String> {
+    let raw = read_with_retry("/proc/cpuinfo").map_err(|e| format!("unreadable ({e})"))?;
+    let cpuinfo = String::from_utf8_lossy(&raw);
+    let field = |key: &str| {
+        cpuinfo
+            .lines()
+            .find(|l| l.split(':').next().is_some_and(|k| k.trim() == key))
+            .and_then(|l| l.split_once(':'))
+            .map(|(_, v)| v.trim().to_string())
+    };
+    let vendor = field("vendor_id").or_else(|| field("model name"));
+    let vendor = vendor.ok_or("no vendor_id or model name line")?;
+    cpu_type_from_vendor(&vendor).ok_or_else(|| format!("vendor '{vendor}' not recognised"))
+}
+
+/// x86 vendor code from the CPU modalias, e.g. `cpu:type:x86,ven0002fam0019…`.
+fn cpu_type_from_modalias() -> Result<CpuType, String> {
+    let raw = read_with_retry("/sys/devices/system/cpu/modalias").map_err(|e| format!("unreadable ({e})"))?;
+    let alias = String::from_utf8_lossy(&raw);
+    let ven = alias.split("ven").nth(1).and_then(|v| v.get(..4)).ok_or("no vendor code")?;
+    match ven {
+        "0000" => Ok(CpuType::Intel),
+        "0002" => Ok(CpuType::Amd),
+        _ => Err(format!("vendor code {ven} not recognised")),
+    }
+}
+
+/// Processor manufacturer from the SMBIOS type 4 table (root only).
+fn cpu_type_from_dmi() -> Result<CpuType, String> {
+    let raw = read_with_retry("/sys/firmware/dmi/entries/4-0/raw").map_err(|e| format!("unreadable ({e})"))?;
+    let manufacturer = (|| {
+        let header_len = *raw.get(1)? as usize;
+        let string_idx = *raw.get(0x07)? as usize;
+        if string_idx == 0 {
+            return None;
+        }
+        let strings = raw.get(header_len..)?;
+        strings.split(|&b| b == 0).nth(string_idx - 1)
+    })()
+    .ok_or("no manufacturer string")?;
+    let manufacturer = String::from_utf8_lossy(manufacturer);
+    cpu_type_from_vendor(&manufacturer).ok_or_else(|| format!("manufacturer '{manufacturer}' not recognised"))
+}
+
+/// One source of CPU vendor information; `Err` says why it gave no answer.
+type CpuProbe = fn() -> Result<CpuType, String>;
+
+/// Set to `amd` or `intel` to skip detection, e.g. to exercise either
+/// undervolt backend on a machine or container that can't be detected.
+const CPU_ENV_VAR: &str = "NITROSENSE_CPU";
+
+/// The detected CPU vendor and where it came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CpuDetection {
+    pub cpu: CpuType,
+    /// Source of a detected vendor, or why every source failed.
+    pub detail: String,
+}
+
+fn forced_cpu_type() -> Option<CpuType> {
+    let value = env::var(CPU_ENV_VAR).ok()?;
+    match value.trim().to_lowercase().as_str() {
+        "amd" => Some(CpuType::Amd),
+        "intel" => Some(CpuType::Intel),
+        _ => {
+            eprintln!("Ignoring {CPU_ENV_VAR}={value}: expected 'amd' or 'intel'");
+            None
+        }
+    }
 }
 
-fn detect_cpu_type() -> CpuType {
-    if let Ok(cpuinfo) = fs::read_to_string("/proc/cpuinfo") {
-        let lower = cpuinfo.to_lowercase();
-        if lower.contains("amd") {
-            return CpuType::Amd;
-        } else if lower.contains("intel") {
-            return CpuType::Intel;
+fn detect_cpu_type() -> CpuDetection {
+    if let Some(cpu) = forced_cpu_type() {
+        println!("CPU vendor forced by {CPU_ENV_VAR}");
+        return CpuDetection { cpu, detail: format!("forced by {CPU_ENV_VAR}") };
+    }
+    let probes: [(&str, CpuProbe); 3] = [
+        ("/proc/cpuinfo", cpu_type_from_cpuinfo),
+        ("CPU modalias", cpu_type_from_modalias),
+        ("DMI processor info", cpu_type_from_dmi),
+    ];
+    let mut failures = Vec::new();
+    for (source, probe) in probes {
+        match probe() {
+            Ok(cpu) => {
+                println!("CPU vendor from {source}");
+                return CpuDetection { cpu, detail: format!("from {source}") };
+            }
+            Err(reason) => {
+                eprintln!("CPU vendor: {source} {reason}");
+                failures.push(format!("{source} {reason}"));
+            }
         }
     }
-    CpuType::Unknown
+    eprintln!("CPU vendor not detected – undervolt and voltage readings are disabled.");
+    CpuDetection { cpu: CpuType::Unknown, detail: failures.join("; ") }
 }
 
 // ---------------------------------------------------------------------------
 // Public API – detect hardware and return the register set
 // ---------------------------------------------------------------------------
 
-/// Detects the laptop model and CPU type.  Returns `(EcRegisters, CpuType)` or
-/// terminates the process with a helpful message when the model is unsupported.
-pub fn detect_device() -> (EcRegisters, CpuType) {
-    let model = detect_model();
-    let cpu = detect_cpu_type();
+/// Register map for a model string: exact match first, then substring,
+/// since some BIOS strings include extra text.
+fn find_model(
+    map: &HashMap<&'static str, (EcRegisters, ModelCaps)>,
+    model: &str,
+) -> Option<(&'static str, EcRegisters, ModelCaps)> {
+    if let Some((name, (regs, caps))) = map.get_key_value(model) {
+        return Some((name, regs.clone(), caps.clone()));
+    }
+    map.iter()
+        .find(|(name, _)| model.contains(*name))
+        .map(|(name, (regs, caps))| (*name, regs.clone(), caps.clone()))
+}
 
-    println!("Detected model : {model}");
-    println!("Detected CPU   : {cpu:?}");
+/// Detects the laptop model and CPU type.  `model_override` (`--model`)
+/// replaces the DMI lookup.  Returns `(model, EcRegisters, ModelCaps,
+/// CpuDetection)` or terminates the process with a helpful message when the
+/// model is unsupported or DMI doesn't name one.
+pub fn detect_device(model_override: Option<&str>) -> (String, EcRegisters, ModelCaps, CpuDetection) {
+    let cpu = detect_cpu_type();
+    println!("Detected CPU   : {:?}", cpu.cpu);
 
     let map = model_to_ecs();
-
-    // Try exact match first, then substring match
-    if let Some(regs) = map.get(model.as_str()) {
-        println!("Using registers for {model}");
-        return (regs.clone(), cpu);
+    let candidates = match model_override {
+        Some(model) => vec![model.to_string()],
+        None => dmi_model_candidates(),
+    };
+
+    if candidates.is_empty() {
+        let mut known: Vec<_> = map.keys().copied().collect();
+        known.sort_unstable();
+        eprintln!("The firmware does not report a model name (DMI fields are blank or placeholders).");
+        eprintln!("Start the daemon with --model <name>; known models: {}", known.join(", "));
+        process::exit(1);
     }
 
-    // Substring fallback – some BIOS strings include extra text
-    for (name, regs) in &map {
-        if model.contains(name) {
-            println!("Using registers for {name} (matched from '{model}')");
-            return (regs.clone(), cpu);
+    for model in &candidates {
+        println!("Detected model : {model}");
+        if let Some((name, regs, caps)) = find_model(&map, model) {
+            if name == model {
+                println!("Using registers for {model}");
+            } else {
+                println!("Using registers for {name} (matched from '{model}')");
+            }
+            warn_if_experimental(name, &caps);
+            return (model.clone(), regs, caps, cpu);
         }
     }
 
-    eprintln!("Device '{model}' is not supported!");
+    eprintln!("Device '{}' is not supported!", candidates[0]);
     process::exit(1);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn torn_rpm_read_is_replaced_until_it_persists() {
+        let caps = CAPS_AN515_46;
+        let t0 = Instant::now();
+        let mut f = RpmFilter::default();
+        assert_eq!(f.filter(3200, &caps, t0), 3200);
+        // Torn hi/lo bytes: the last good reading is reported instead.
+        assert_eq!(f.filter(0xFFFF, &caps, t0 + Duration::from_millis(500)), 3200);
+        assert_eq!(f.filter(3300, &caps, t0 + Duration::from_millis(1000)), 3300);
+        // A reading that stays implausible is believed after the grace period.
+        assert_eq!(f.filter(0xFFFF, &caps, t0 + Duration::from_millis(2000)), 3300);
+        assert_eq!(f.filter(0xFFFF, &caps, t0 + Duration::from_millis(3500)), 0xFFFF);
+        // Models without a known top speed are never filtered.
+        let unknown = ModelCaps { max_fan_rpm: 0, ..CAPS_AN515_46 };
+        assert_eq!(RpmFilter::default().filter(0xFFFF, &unknown, t0), 0xFFFF);
+    }
+}
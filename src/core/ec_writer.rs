@@ -4,6 +4,9 @@
 ///   1. `ec_sys`  → `/sys/kernel/debug/ec/ec0/io`
 ///   2. `acpi_ec` → `/dev/ec`
 ///   3. raw I/O ports → `/dev/port`  (uses EC command protocol on ports 0x62/0x66)
+///
+/// The first one whose writes reach the EC wins.  If none do, the first
+/// one that opened is kept for monitoring only.
 
 use std::fs::{self, File, OpenOptions};
 use std::io::{self, Read, Seek, SeekFrom, Write};
@@ -20,11 +23,32 @@ enum EcBackend {
     DevPort,
 }
 
+/// A register with two values it can be switched between for a moment
+/// without anything noticeable happening, used to check that writes reach
+/// the EC.
+#[derive(Debug, Clone, Copy)]
+pub struct WriteProbe {
+    pub address: u8,
+    pub values: [u8; 2],
+}
+
 /// Handle for communicating with the EC.
 pub struct EcWriter {
     file: File,
     buffer: Vec<u8>,
     backend: EcBackend,
+    /// Device file backing `file`.
+    path: &'static str,
+    /// When `buffer` was last filled; cleared by writes.
+    refreshed_at: Option<Instant>,
+    /// Register switched and restored to test a backend.
+    probe: WriteProbe,
+    /// Every backend that opened, with whether writes reached the EC.
+    probed: Vec<(&'static str, bool)>,
+    /// Writes are refused, e.g. for an unconfirmed register map.
+    locked: bool,
+    /// A failed [`reinit`](Self::reinit) left no device open.
+    offline: bool,
 }
 
 /// Errors that can occur during EC operations.
@@ -51,6 +75,10 @@ impl From<io::Error> for EcError {
     }
 }
 
+const EC_SYS_PATH: &str = "/sys/kernel/debug/ec/ec0/io";
+const ACPI_EC_PATH: &str = "/dev/ec";
+const DEV_PORT_PATH: &str = "/dev/port";
+
 /// EC command bytes written to port 0x66.
 const EC_CMD_READ: u8 = 0x80;
 const EC_CMD_WRITE: u8 = 0x81;
@@ -68,26 +96,60 @@ const EC_TIMEOUT: Duration = Duration::from_millis(500);
 
 impl EcWriter {
     /// Open the EC device file.
-    /// Tries `ec_sys` first, then `acpi_ec`, then raw `/dev/port`.
-    pub fn new() -> Result<Self, EcError> {
-        if let Some(f) = Self::load_ec_sys() {
-            return Ok(EcWriter { file: f, buffer: Vec::new(), backend: EcBackend::MappedFile });
-        }
-        if let Some(f) = Self::load_acpi_ec() {
-            return Ok(EcWriter { file: f, buffer: Vec::new(), backend: EcBackend::MappedFile });
-        }
-        if let Some(f) = Self::load_dev_port() {
-            return Ok(EcWriter { file: f, buffer: Vec::new(), backend: EcBackend::DevPort });
+    /// Tries `ec_sys` first, then `acpi_ec`, then raw `/dev/port`, and keeps
+    /// the first whose writes reach the EC, as checked with `probe`.  With
+    /// `locked` every write is refused, the probe included, and the first backend that opens is
+    /// used for monitoring.
+    pub fn new(probe: WriteProbe, locked: bool) -> Result<Self, EcError> {
+        type Loader = fn() -> Option<File>;
+        let candidates: [(Loader, EcBackend, &'static str); 3] = [
+            (Self::load_ec_sys, EcBackend::MappedFile, EC_SYS_PATH),
+            (Self::load_acpi_ec, EcBackend::MappedFile, ACPI_EC_PATH),
+            (Self::load_dev_port, EcBackend::DevPort, DEV_PORT_PATH),
+        ];
+
+        let mut probed = Vec::new();
+        let mut read_only: Option<EcWriter> = None;
+        for (load, backend, path) in candidates {
+            let Some(file) = load() else { continue };
+            let mut ec = EcWriter {
+                file,
+                buffer: Vec::new(),
+                backend,
+                path,
+                refreshed_at: None,
+                probe,
+                probed: Vec::new(),
+                locked,
+                offline: false,
+            };
+            if locked {
+                probed.push((path, false));
+                ec.probed = probed;
+                return Ok(ec);
+            }
+            let writable = ec.verify_write();
+            probed.push((path, writable));
+            if writable {
+                ec.probed = probed;
+                return Ok(ec);
+            }
+            eprintln!("{path} opened, but writes do not reach the EC; trying the next backend.");
+            read_only.get_or_insert(ec);
         }
-        Err(EcError::NoDevice)
+
+        let mut ec = read_only.ok_or(EcError::NoDevice)?;
+        eprintln!("No writable EC backend; using {} for monitoring only.", ec.path);
+        ec.probed = probed;
+        Ok(ec)
     }
 
     // -- kernel module helpers ----------------------------------------------
 
     fn load_ec_sys() -> Option<File> {
         // First, check if the file already exists and is writable
-        if fs::metadata("/sys/kernel/debug/ec/ec0/io").is_ok() {
-            if let Ok(f) = OpenOptions::new().read(true).write(true).open("/sys/kernel/debug/ec/ec0/io") {
+        if fs::metadata(EC_SYS_PATH).is_ok() {
+            if let Ok(f) = OpenOptions::new().read(true).write(true).open(EC_SYS_PATH) {
                 println!("'ec_sys' interface found and writable.");
                 return Some(f);
             }
@@ -100,7 +162,7 @@ impl EcWriter {
             .args(["modprobe", "ec_sys", "write_support=on"])
             .status();
 
-        let path = "/sys/kernel/debug/ec/ec0/io";
+        let path = EC_SYS_PATH;
         if fs::metadata(path).is_ok() {
             match OpenOptions::new().read(true).write(true).open(path) {
                 Ok(f) => {
@@ -121,7 +183,7 @@ impl EcWriter {
     fn load_acpi_ec() -> Option<File> {
         let _ = Command::new("/usr/bin/env").args(["modprobe", "acpi_ec"]).status();
 
-        let path = "/dev/ec";
+        let path = ACPI_EC_PATH;
         if fs::metadata(path).is_ok() {
             match OpenOptions::new().read(true).write(true).open(path) {
                 Ok(f) => {
@@ -137,8 +199,8 @@ impl EcWriter {
     }
 
     fn load_dev_port() -> Option<File> {
-        if fs::metadata("/dev/port").is_ok() {
-            match OpenOptions::new().read(true).write(true).open("/dev/port") {
+        if fs::metadata(DEV_PORT_PATH).is_ok() {
+            match OpenOptions::new().read(true).write(true).open(DEV_PORT_PATH) {
                 Ok(f) => {
                     println!("'/dev/port' interface found.");
                     return Some(f);
@@ -219,28 +281,108 @@ impl EcWriter {
 
     // -- public interface ---------------------------------------------------
 
-    /// Write a single byte to an EC register.
-    pub fn write(&mut self, address: u8, value: u8) {
+    fn try_write(&mut self, address: u8, value: u8) -> io::Result<()> {
+        if self.offline {
+            return Err(io::Error::new(io::ErrorKind::NotConnected, "EC is not open"));
+        }
+        if self.locked {
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied, "EC writes are locked"));
+        }
+        // The buffer no longer reflects the EC once anything is written.
+        self.refreshed_at = None;
         match self.backend {
             EcBackend::MappedFile => {
-                if let Err(e) = self.file.seek(SeekFrom::Start(address as u64)) {
-                    eprintln!("Error seeking EC to 0x{address:02X}: {e}");
-                    return;
-                }
-                if let Err(e) = self.file.write_all(&[value]) {
-                    eprintln!("Error writing 0x{value:02X} to EC 0x{address:02X}: {e}");
-                }
+                self.file.seek(SeekFrom::Start(address as u64))?;
+                self.file.write_all(&[value])
             }
-            EcBackend::DevPort => {
-                if let Err(e) = self.ec_port_write(address, value) {
-                    eprintln!("Error writing 0x{value:02X} to EC 0x{address:02X} via /dev/port: {e}");
-                }
+            EcBackend::DevPort => self.ec_port_write(address, value),
+        }
+    }
+
+    /// Write a single byte to an EC register.
+    pub fn write(&mut self, address: u8, value: u8) {
+        if let Err(e) = self.try_write(address, value) {
+            eprintln!("Error writing 0x{value:02X} to EC 0x{address:02X} via {}: {e}", self.path);
+        }
+    }
+
+    /// Whether every write is refused, e.g. for an unconfirmed register map.
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Check that writes reach the EC: switch the probe register to its
+    /// other value, read it back, then restore the original.  Writing the
+    /// same value back would also pass when the driver drops the write.
+    fn verify_write(&mut self) -> bool {
+        let WriteProbe { address, values } = self.probe;
+        let Ok(original) = self.read_one(address) else {
+            return false;
+        };
+        let other = if original == values[0] { values[1] } else { values[0] };
+        if self.try_write(address, other).is_err() {
+            return false;
+        }
+        let reached = self.read_one(address).is_ok_and(|v| v == other);
+        if let Err(e) = self.try_write(address, original) {
+            eprintln!("Failed to restore EC 0x{address:02X} to 0x{original:02X} after the write check: {e}");
+        }
+        reached
+    }
+
+    /// Whether writes through the backend in use reached the EC when it
+    /// was opened.
+    pub fn writes_verified(&self) -> bool {
+        self.probed.iter().any(|&(path, ok)| ok && path == self.path)
+    }
+
+    /// Read one register straight from the device, bypassing the buffer.
+    /// The buffered copy of `address` is updated if there is one.
+    pub fn read_one(&mut self, address: u8) -> io::Result<u8> {
+        if self.offline {
+            return Err(io::Error::new(io::ErrorKind::NotConnected, "EC is not open"));
+        }
+        let value = match self.backend {
+            EcBackend::MappedFile => {
+                self.file.seek(SeekFrom::Start(address as u64))?;
+                let mut buf = [0u8; 1];
+                self.file.read_exact(&mut buf)?;
+                buf[0]
             }
+            EcBackend::DevPort => self.ec_port_read(address)?,
+        };
+        if let Some(slot) = self.buffer.get_mut(address as usize) {
+            *slot = value;
         }
+        Ok(value)
+    }
+
+    /// Device file in use, e.g. `/sys/kernel/debug/ec/ec0/io`.
+    pub fn path(&self) -> &'static str {
+        self.path
+    }
+
+    /// Backends tried when the handle was opened, in order, with whether
+    /// writes through them reached the EC.
+    pub fn probed(&self) -> &[(&'static str, bool)] {
+        &self.probed
+    }
+
+    /// Number of bytes read by the last [`refresh`](Self::refresh).
+    pub fn buffer_len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// The buffered EC data as of the last [`refresh`](Self::refresh).
+    pub fn snapshot(&self) -> Vec<u8> {
+        self.buffer.clone()
     }
 
     /// Re-read the entire EC address space into an internal buffer.
     pub fn refresh(&mut self) {
+        if self.offline {
+            return;
+        }
         match self.backend {
             EcBackend::MappedFile => {
                 if let Err(e) = self.file.seek(SeekFrom::Start(0)) {
@@ -271,7 +413,18 @@ impl EcWriter {
         }
         if self.buffer.is_empty() {
             eprintln!("Warning: empty EC buffer after refresh!");
+        } else {
+            self.refreshed_at = Some(Instant::now());
+        }
+    }
+
+    /// [`refresh`](Self::refresh) unless the buffer is younger than
+    /// `max_age` and nothing has been written since.
+    pub fn refresh_if_older_than(&mut self, max_age: Duration) {
+        if self.refreshed_at.is_some_and(|t| t.elapsed() < max_age) {
+            return;
         }
+        self.refresh();
     }
 
     /// Read a value from the buffered EC data.  Call [`refresh`] first.
@@ -283,6 +436,32 @@ impl EcWriter {
         })
     }
 
+    /// Release the device, reload the EC kernel modules and reopen from scratch.
+    ///
+    /// On failure the handle is left [offline](Self::is_offline): the buffer
+    /// is empty and writes fail until a later reinit succeeds.
+    pub fn reinit(&mut self) -> Result<(), EcError> {
+        // The module cannot be unloaded while we still hold it open.
+        self.file = File::open("/dev/null")?;
+        self.path = "/dev/null";
+        self.buffer.clear();
+        self.refreshed_at = None;
+        self.offline = true;
+
+        println!("Reinitialising EC interface...");
+        let _ = Command::new("/usr/bin/env").args(["modprobe", "-r", "ec_sys"]).status();
+        let _ = Command::new("/usr/bin/env").args(["modprobe", "-r", "acpi_ec"]).status();
+
+        *self = Self::new(self.probe, self.locked)?;
+        Ok(())
+    }
+
+    /// Whether a failed [`reinit`](Self::reinit) left no device open.  Every
+    /// read is then meaningless, not just zero.
+    pub fn is_offline(&self) -> bool {
+        self.offline
+    }
+
     /// Gracefully close the EC file handle.
     pub fn shutdown(&mut self) {
         // `File` is closed on drop, but we print a message for parity.
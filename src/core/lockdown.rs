@@ -0,0 +1,33 @@
+//! Kernel lockdown detection.
+//!
+//! With Secure Boot the kernel usually enables the lockdown LSM, which
+//! rejects `ec_sys write_support=1` and raw `/dev/port` access – even for
+//! root.  Without this check the only symptom is EC writes silently failing.
+
+use std::fs;
+
+const LOCKDOWN_PATH: &str = "/sys/kernel/security/lockdown";
+
+/// Active lockdown mode (`"integrity"` or `"confidentiality"`), or `None` when
+/// the kernel is not locked down or doesn't expose the setting.
+pub fn kernel_lockdown() -> Option<String> {
+    let data = fs::read_to_string(LOCKDOWN_PATH).ok()?;
+    parse_active(&data).filter(|mode| mode != "none")
+}
+
+/// The file lists every mode with the active one bracketed, e.g.
+/// `none [integrity] confidentiality`.
+fn parse_active(data: &str) -> Option<String> {
+    let start = data.find('[')? + 1;
+    let end = start + data[start..].find(']')?;
+    Some(data[start..end].to_string())
+}
+
+/// Print an explanation of why EC writes will fail under `mode`.
+pub fn warn_locked_down(mode: &str) {
+    eprintln!("Kernel lockdown is active ({mode}): EC writes are blocked.");
+    eprintln!("  ec_sys write support and /dev/port are disabled by the lockdown LSM,");
+    eprintln!("  so fan, mode and battery settings cannot be changed.");
+    eprintln!("  To fix this, disable Secure Boot in the firmware setup, or boot with");
+    eprintln!("  `lockdown=none` if your distribution permits it.");
+}
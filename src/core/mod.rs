@@ -1,4 +1,9 @@
+pub mod app_watch;
 pub mod cpu_ctl;
 pub mod device_regs;
 pub mod ec_writer;
+pub mod lockdown;
+pub mod nvidia;
+pub mod power_supply;
 pub mod tdp_ctl;
+pub mod thermal;
@@ -0,0 +1,85 @@
+//! Discrete NVIDIA GPU fan readings through `nvidia-smi`.
+//!
+//! On some hybrid models the EC's "GPU fan" registers follow a shared
+//! chassis fan, while the dGPU's own fan is only visible to the NVIDIA
+//! driver.  The driver reports it as a percentage of full speed, not RPM.
+
+use std::env;
+use std::fs;
+use std::process::Command;
+
+use crate::core::cpu_ctl;
+
+const NVIDIA_SMI: &str = "nvidia-smi";
+
+/// PCI vendor ID of NVIDIA devices in sysfs.
+const NVIDIA_VENDOR: &str = "0x10de";
+
+/// Set to `nvidia` or `ec` to override where the model's map says the GPU
+/// fan reading comes from.
+const ENV_GPU_FAN_SOURCE: &str = "NITROSENSE_GPU_FAN_SOURCE";
+
+/// Whether the GPU fan should be read from the NVIDIA driver: the model
+/// needs it (or `NITROSENSE_GPU_FAN_SOURCE=nvidia` says so) and
+/// `nvidia-smi` is installed.
+pub fn detect_fan_source(model_uses_nvidia: bool) -> bool {
+    let wanted = match env::var(ENV_GPU_FAN_SOURCE).as_deref() {
+        Ok("nvidia") => true,
+        Ok("ec") => false,
+        Ok(other) => {
+            eprintln!("Ignoring {ENV_GPU_FAN_SOURCE}={other} (expected nvidia or ec)");
+            model_uses_nvidia
+        }
+        Err(_) => model_uses_nvidia,
+    };
+    if wanted && !cpu_ctl::in_path(NVIDIA_SMI) {
+        eprintln!("GPU fan should come from {NVIDIA_SMI}, which is not installed; using the EC reading");
+        return false;
+    }
+    wanted
+}
+
+/// Fan speed of the first NVIDIA GPU in percent, or `None` when the driver
+/// has no reading (GPU powered down, or a passively cooled card).  A
+/// runtime-suspended GPU is left asleep: `nvidia-smi` would wake it.
+pub fn read_fan_percent() -> Option<u8> {
+    if runtime_suspended() {
+        return None;
+    }
+    let output = Command::new(NVIDIA_SMI)
+        .args(["--query-gpu=fan.speed", "--format=csv,noheader,nounits"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_fan_percent(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Whether every NVIDIA display controller is runtime-suspended, per
+/// `power/runtime_status` in sysfs.
+fn runtime_suspended() -> bool {
+    let Ok(devices) = fs::read_dir("/sys/bus/pci/devices") else {
+        return false;
+    };
+    let mut found = false;
+    for dev in devices.flatten() {
+        let path = dev.path();
+        let read = |name: &str| fs::read_to_string(path.join(name)).map(|s| s.trim().to_string()).unwrap_or_default();
+        // Class 0x03xxxx: VGA and 3D controllers.
+        if read("vendor") != NVIDIA_VENDOR || !read("class").starts_with("0x03") {
+            continue;
+        }
+        if read("power/runtime_status") != "suspended" {
+            return false;
+        }
+        found = true;
+    }
+    found
+}
+
+/// One line per GPU, e.g. `45`, or `[N/A]` when there is no fan reading.
+fn parse_fan_percent(out: &str) -> Option<u8> {
+    let percent: u8 = out.lines().next()?.trim().parse().ok()?;
+    Some(percent.min(100))
+}
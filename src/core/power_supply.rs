@@ -0,0 +1,117 @@
+//! AC adapter and battery details via `/sys/class/power_supply`.
+//!
+//! The EC only reports plugged/unplugged, so the adapter's advertised
+//! wattage is taken from the kernel's power-supply class where exposed
+//! (typically USB-C PD sources; barrel-jack adapters often only expose
+//! `online`).  Battery wear and power flow come from the same place.
+
+use std::fs;
+use std::path::Path;
+
+const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+
+fn read_attr(dir: &Path, name: &str) -> Option<String> {
+    fs::read_to_string(dir.join(name)).ok().map(|s| s.trim().to_string())
+}
+
+fn read_u64(dir: &Path, name: &str) -> Option<u64> {
+    read_attr(dir, name)?.parse().ok()
+}
+
+/// Magnitude of a signed attribute; some drivers report `current_now`
+/// and `power_now` negative while discharging.
+fn read_abs(dir: &Path, name: &str) -> Option<u64> {
+    read_attr(dir, name)?.parse::<i64>().ok().map(i64::unsigned_abs)
+}
+
+/// Wattage advertised by the first online adapter, if the kernel reports it.
+pub fn adapter_watts() -> Option<u16> {
+    let entries = fs::read_dir(POWER_SUPPLY_DIR).ok()?;
+    for entry in entries.flatten() {
+        let dir = entry.path();
+        let kind = read_attr(&dir, "type").unwrap_or_default();
+        if kind != "Mains" && !kind.starts_with("USB") {
+            continue;
+        }
+        if read_u64(&dir, "online") != Some(1) {
+            continue;
+        }
+
+        // Values are in µV / µA / µW.
+        let micro_watts = match (read_u64(&dir, "voltage_max"), read_u64(&dir, "current_max")) {
+            (Some(uv), Some(ua)) => uv * ua / 1_000_000,
+            _ => match read_u64(&dir, "power_max") {
+                Some(uw) => uw,
+                None => continue,
+            },
+        };
+        let watts = micro_watts / 1_000_000;
+        if watts > 0 {
+            return Some(watts.min(u16::MAX as u64) as u16);
+        }
+    }
+    None
+}
+
+/// First battery the kernel knows about (usually `BAT0` or `BAT1`).
+fn battery_dir() -> Option<std::path::PathBuf> {
+    fs::read_dir(POWER_SUPPLY_DIR)
+        .ok()?
+        .flatten()
+        .map(|e| e.path())
+        .find(|dir| read_attr(dir, "type").as_deref() == Some("Battery"))
+}
+
+/// Charge cycles so far; not every firmware exposes `cycle_count`.
+pub fn battery_cycles() -> Option<u32> {
+    read_u64(&battery_dir()?, "cycle_count").map(|c| c.min(u32::MAX as u64) as u32)
+}
+
+/// Power flowing into or out of the battery right now.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BatteryFlow {
+    pub watts: f64,
+    /// Minutes until empty while discharging, or until full while
+    /// charging; `None` when idle or the firmware doesn't say.
+    pub minutes_left: Option<u32>,
+}
+
+/// Current battery power, from `power_now` or `current_now * voltage_now`.
+pub fn battery_flow() -> Option<BatteryFlow> {
+    let dir = battery_dir()?;
+    let current = read_abs(&dir, "current_now");
+    let micro_watts = match read_abs(&dir, "power_now") {
+        Some(uw) => uw,
+        None => current? * read_abs(&dir, "voltage_now")? / 1_000_000,
+    };
+    Some(BatteryFlow { watts: micro_watts as f64 / 1_000_000.0, minutes_left: minutes_left(&dir, micro_watts, current) })
+}
+
+/// Time left at the present rate.  Level and rate have to be in matching
+/// units: energy (µWh) with power (µW), or charge (µAh) with current (µA).
+fn minutes_left(dir: &Path, micro_watts: u64, current: Option<u64>) -> Option<u32> {
+    let (now, full, rate) = match (read_u64(dir, "energy_now"), read_u64(dir, "energy_full")) {
+        (Some(now), Some(full)) => (now, full, micro_watts),
+        _ => (read_u64(dir, "charge_now")?, read_u64(dir, "charge_full")?, current?),
+    };
+    let remaining = match read_attr(dir, "status")?.as_str() {
+        "Discharging" => now,
+        "Charging" => full.saturating_sub(now),
+        _ => return None,
+    };
+    (rate > 0).then(|| (remaining * 60 / rate).min(u32::MAX as u64) as u32)
+}
+
+/// Full capacity as a percentage of design capacity.  Batteries report
+/// either energy (µWh) or charge (µAh); both give the same ratio.
+pub fn battery_health_pct() -> Option<u8> {
+    let dir = battery_dir()?;
+    let (full, design) = match (read_u64(&dir, "energy_full"), read_u64(&dir, "energy_full_design")) {
+        (Some(f), Some(d)) => (f, d),
+        _ => (read_u64(&dir, "charge_full")?, read_u64(&dir, "charge_full_design")?),
+    };
+    if design == 0 {
+        return None;
+    }
+    Some((full * 100 / design).min(100) as u8)
+}
@@ -0,0 +1,80 @@
+//! Extra temperature sensors from sysfs.
+//!
+//! The EC only reports CPU, GPU and system temperatures.  The kernel often
+//! knows about more (NVMe, Wi-Fi, chipset, …) through the thermal-zone and
+//! hwmon classes, so those are collected here for display.
+
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+const THERMAL_DIR: &str = "/sys/class/thermal";
+const HWMON_DIR: &str = "/sys/class/hwmon";
+
+/// One named temperature reading.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThermalZone {
+    pub name: String,
+    pub temp_c: f32,
+}
+
+fn read_attr(dir: &Path, name: &str) -> Option<String> {
+    fs::read_to_string(dir.join(name)).ok().map(|s| s.trim().to_string())
+}
+
+/// Millidegrees → °C.
+fn read_milli_c(dir: &Path, name: &str) -> Option<f32> {
+    read_attr(dir, name)?.parse::<i64>().ok().map(|m| m as f32 / 1000.0)
+}
+
+fn sorted_entries(dir: &str) -> Vec<std::path::PathBuf> {
+    let mut paths: Vec<_> = match fs::read_dir(dir) {
+        Ok(entries) => entries.flatten().map(|e| e.path()).collect(),
+        Err(_) => return Vec::new(),
+    };
+    paths.sort();
+    paths
+}
+
+/// `thermal_zone*` entries, named by their `type`.
+fn thermal_zones() -> Vec<ThermalZone> {
+    sorted_entries(THERMAL_DIR)
+        .iter()
+        .filter(|p| p.file_name().is_some_and(|n| n.to_string_lossy().starts_with("thermal_zone")))
+        .filter_map(|dir| {
+            let name = read_attr(dir, "type")?;
+            let temp_c = read_milli_c(dir, "temp")?;
+            Some(ThermalZone { name, temp_c })
+        })
+        .collect()
+}
+
+/// `hwmon*/temp*_input` readings, named `<chip> <label>`.
+fn hwmon_sensors() -> Vec<ThermalZone> {
+    let mut out = Vec::new();
+    for dir in sorted_entries(HWMON_DIR) {
+        let chip = read_attr(&dir, "name").unwrap_or_else(|| "hwmon".into());
+        for input in sorted_entries(&dir.to_string_lossy()) {
+            let Some(file) = input.file_name().map(|n| n.to_string_lossy().into_owned()) else {
+                continue;
+            };
+            let Some(prefix) = file.strip_prefix("temp").and_then(|f| f.strip_suffix("_input")) else {
+                continue;
+            };
+            let Some(temp_c) = read_milli_c(&dir, &file) else {
+                continue;
+            };
+            let label = read_attr(&dir, &format!("temp{prefix}_label")).unwrap_or_else(|| format!("temp{prefix}"));
+            out.push(ThermalZone { name: format!("{chip} {label}"), temp_c });
+        }
+    }
+    out
+}
+
+/// All readable thermal zones followed by hwmon temperature sensors.
+pub fn read_all() -> Vec<ThermalZone> {
+    let mut zones = thermal_zones();
+    zones.extend(hwmon_sensors());
+    zones
+}
@@ -1,46 +1,527 @@
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs;
 use std::io::{self, BufRead, BufReader, Write};
+use std::os::fd::AsRawFd;
 use std::os::unix::fs::PermissionsExt;
 use std::os::unix::net::{UnixListener, UnixStream};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
-use crate::config::{NitroConfig, RgbConfig, TdpConfig};
-use crate::core::cpu_ctl::CpuController;
-use crate::core::device_regs::{detect_device, EcRegisters};
-use crate::core::ec_writer::EcWriter;
+use crate::config::{self, AppRulesConfig, BatteryTurboPolicy, NitroConfig, RgbConfig, SafetyConfig, TdpConfig};
+use crate::core::app_watch;
+use crate::core::cpu_ctl::{self, CpuController};
+use crate::core::device_regs::{detect_device, CpuType, EcRegisters, ModelCaps, ModelMap, RpmFilter, TEMP_RANGE};
+use crate::core::ec_writer::{EcError, EcWriter, WriteProbe};
+use crate::core::lockdown;
+use crate::core::nvidia;
+use crate::core::power_supply;
 use crate::core::tdp_ctl;
+use crate::core::thermal;
 use crate::protocol::{
-    BatteryStatus, EcData, FanMode, NitroMode, PowerProfile, Request, Response, SOCKET_PATH,
+    self, BatteryStatus, EcData, EcInfo, Encoding, Envelope, FanMode, FanStatus, KeyboardStatus, ModeStatus,
+    NitroMode, PowerProfile, PowerStatus, RegisterCheck, Request, Response, StatusField, StatusFields,
+    SystemInfo, TempStatus, BINARY_HANDSHAKE, SOCKET_PATH,
 };
-use crate::utils::keyboard::{self, Rgb};
+use crate::utils::keyboard::{self, Direction, KeyboardLayout, Rgb};
+
+/// How often the thermal safety watchdog samples the EC.
+const SAFETY_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How often the daemon samples CPU voltage.  Clients only ever see the
+/// cached value, so this is the one place `amdctl`/`rdmsr` gets run.
+const VOLTAGE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How often the daemon asks `nvidia-smi` for the GPU fan speed, on
+/// models that need it.  Each run takes tens of milliseconds, far too slow
+/// for the status path.
+const NVIDIA_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long after the last fan status request the NVIDIA sampler keeps
+/// running.  With no client polling it stops, so the dGPU can stay asleep.
+const NVIDIA_IDLE_AFTER: Duration = Duration::from_secs(10);
+
+/// Degrees below the critical temperature before manual control is restored.
+const SAFETY_HYSTERESIS: u8 = 10;
+
+/// Number of EC writes that can be undone.
+const UNDO_DEPTH: usize = 16;
+
+/// Minimum time between a settings change and writing it to disk.
+const CONFIG_SAVE_DELAY: Duration = Duration::from_secs(1);
+
+/// How often running processes are matched against the app rules.
+const APP_RULE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long a rule match (or its absence) must persist before the mode
+/// changes, so short-lived processes don't flip it back and forth.
+const APP_RULE_DEBOUNCE: Duration = Duration::from_secs(6);
+
+/// Validate `(executable, mode name)` pairs.
+fn parse_app_rules(rules: &[(String, String)]) -> Result<Vec<(String, NitroMode)>, String> {
+    rules
+        .iter()
+        .map(|(exe, name)| match NitroMode::from_name(name) {
+            Some(mode) if !exe.is_empty() => Ok((exe.clone(), mode)),
+            Some(_) => Err("Empty executable name in app rule".into()),
+            None => Err(format!("Unknown mode '{name}' for {exe} (use Quiet, Default, Extreme or Turbo)")),
+        })
+        .collect()
+}
+
+/// Default for how long an EC snapshot serves status requests before the
+/// EC is read again (`--ec-cache-ms`).
+const EC_CACHE_MAX_AGE: Duration = Duration::from_millis(200);
+
+/// Attempts to bind the socket while a previous daemon still holds it.
+const BIND_ATTEMPTS: u32 = 5;
+
+/// Initial delay between bind attempts; doubled after each one.
+const BIND_BACKOFF: Duration = Duration::from_millis(200);
+
+/// Duty added per ramp step, and how often steps happen; a full ramp
+/// takes about a second.
+const RAMP_STEP_PERCENT: u8 = 10;
+const RAMP_STEP_INTERVAL: Duration = Duration::from_millis(150);
+
+/// Frame interval of software keyboard effects.
+const EFFECT_FRAME_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long startup waits for the keyboard driver's device nodes, which
+/// can appear after the daemon during boot, and how often it looks.
+const KB_RESTORE_WAIT: Duration = Duration::from_secs(30);
+const KB_RESTORE_POLL: Duration = Duration::from_secs(1);
+
+/// How often the daemon checks whether the system was suspended, and how
+/// much longer than that the boot clock has to advance to count as a sleep.
+const RESUME_POLL_INTERVAL: Duration = Duration::from_secs(5);
+const RESUME_MIN_GAP: Duration = Duration::from_secs(3);
+
+/// How often the safety watchdog retries reopening the EC after a failed
+/// reinit left the daemon without one.
+const EC_RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Answer to requests that write the EC when the register map is unconfirmed.
+const EC_LOCKED: &str = "EC writes are disabled for this unconfirmed register map (see --allow-experimental)";
+
+/// Answer to every EC request while a failed reinit has left no EC open.
+const EC_OFFLINE: &str = "EC unavailable after a failed reinit; fans were left on Auto. Retry with reload-ec.";
+
+/// Smaller increases are applied at once.
+const RAMP_MIN_DELTA: u8 = 20;
+
+/// Assumed duty of a fan on Auto when the model can't report it.
+const RAMP_AUTO_START: u8 = 30;
+
+/// Time the EC gets to apply (or override) a fan mode before it is read back.
+const MODE_READBACK_DELAY: Duration = Duration::from_millis(50);
+
+/// Requests one connection may send per [`RATE_WINDOW`].  Changes are
+/// capped well below reads so a runaway client can't hammer the EC or
+/// the config files; status polling stays far below either.
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+const WRITE_RATE_LIMIT: u32 = 20;
+const READ_RATE_LIMIT: u32 = 200;
+
+/// Fixed-window request counter for one connection.
+struct RateLimit {
+    limit: u32,
+    window_start: Instant,
+    count: u32,
+}
+
+impl RateLimit {
+    fn new(limit: u32) -> Self {
+        Self { limit, window_start: Instant::now(), count: 0 }
+    }
+
+    /// Count one request; `false` once this window's budget is spent.
+    fn allow(&mut self) -> bool {
+        if self.window_start.elapsed() >= RATE_WINDOW {
+            self.window_start = Instant::now();
+            self.count = 0;
+        }
+        self.count += 1;
+        self.count <= self.limit
+    }
+}
+
+/// Registers and mode values of one fan.
+#[derive(Clone, Copy)]
+struct FanRegs {
+    mode_ctrl: u8,
+    auto_mode: u8,
+    turbo_mode: u8,
+    manual_mode: u8,
+    passive_mode: u8,
+    speed_ctrl: u8,
+    duty: u8,
+}
+
+/// A fan mode write to read back once the firmware has had time to apply
+/// it.  The wait happens with the state unlocked (see [`handle_client`]).
+struct ModeReadback {
+    gpu: bool,
+    fan: FanRegs,
+    value: u8,
+    /// Passive emulated with Manual 0%.
+    emulated_passive: bool,
+}
+
+/// A fan being stepped up towards `target` in Manual mode.  Levels are in
+/// percent.
+struct FanRamp {
+    fan: FanRegs,
+    /// Last level written.
+    level: u8,
+    target: u8,
+    /// Mode written once `target` is reached (Turbo); `None` stays Manual.
+    finish: Option<u8>,
+}
+
+/// Change notices kept for other clients to pick up.
+const MAX_EVENTS: usize = 32;
+
+/// A settings change, announced to the clients that didn't make it.
+struct ChangeEvent {
+    seq: u64,
+    /// Connection that made the change; `None` for the daemon itself.
+    source: Option<u64>,
+    text: String,
+}
+
+/// Fans forced out of manual mode by the thermal safety override, together
+/// with the manual level to restore once temperatures drop.
+struct FanOverride {
+    cpu_level: Option<u8>,
+    gpu_level: Option<u8>,
+}
+
+/// Progress of the per-application mode rules.
+#[derive(Default)]
+struct AppRuleState {
+    rules: Vec<(String, NitroMode)>,
+    /// Mode the running processes call for, and since when, while it
+    /// waits out [`APP_RULE_DEBOUNCE`].  `None` inside means "no rule".
+    pending: Option<(Option<NitroMode>, Instant)>,
+    /// Register value a rule wrote, and the one to restore afterwards.
+    active: Option<(u8, u8)>,
+}
 
 struct DaemonState {
+    model: String,
+    /// Active kernel lockdown mode, detected once at startup.
+    kernel_lockdown: Option<String>,
     ec: EcWriter,
     regs: EcRegisters,
+    caps: ModelCaps,
     cpu_ctl: CpuController,
+    /// Where the CPU vendor came from, or why it couldn't be found.
+    cpu_detail: String,
     tdp_mw: u32,
     power_profile: PowerProfile,
+    safety: SafetyConfig,
+    fan_override: Option<FanOverride>,
+    /// When temperatures first reached the critical-shutdown threshold.
+    critical_since: Option<Instant>,
+    shutdown_requested: bool,
+    /// `(register, previous value)` for recent client-initiated writes.
+    undo_stack: VecDeque<(u8, u8)>,
+    /// In-memory copy of `nitro.json`; the only one the daemon mutates.
+    nitro_cfg: NitroConfig,
+    /// When `nitro_cfg` first diverged from disk, if it has.
+    nitro_cfg_dirty_since: Option<Instant>,
+    app_rules: AppRuleState,
+    /// Maximum age of the EC snapshot used for status requests.
+    ec_cache: Duration,
+    /// In-progress fan ramps, at most one per fan.
+    fan_ramps: Vec<FanRamp>,
+    /// Mode registers of fans running emulated Passive (Manual 0%), so
+    /// readback can tell them apart from a user-set Manual level.
+    passive_fans: Vec<u8>,
+    /// Temperature registers that have given an implausible reading, so
+    /// each is reported once rather than on every poll.
+    bogus_temps: RefCell<HashSet<u8>>,
+    /// Torn-read filters for the CPU and GPU fan RPM.
+    rpm_filters: RefCell<[RpmFilter; 2]>,
+    /// Last dGPU fan reading from `nvidia-smi`, kept by its sampler thread.
+    nvidia_fan_percent: Option<u8>,
+    /// When a client last asked for fan speeds.
+    fans_polled_at: Cell<Option<Instant>>,
+    /// Recent changes, oldest first, and the last sequence number used.
+    events: VecDeque<ChangeEvent>,
+    event_seq: u64,
+    /// Connection whose request is being handled.
+    current_client: u64,
+    /// Names clients gave themselves with `SetClientName`.
+    client_names: HashMap<u64, String>,
+    /// Keyboard lighting; the daemon is the only writer of `rgb.json`.
+    rgb_cfg: RgbConfig,
+    /// Mode colour the keyboard shows over `rgb_cfg`, if any.
+    mode_tint: Option<Rgb>,
+    /// Start of the software lighting effect being animated, if any.
+    effect_started: Option<Instant>,
+    /// Fan mode change left for the caller to read back.
+    mode_readback: Option<ModeReadback>,
 }
 
 impl DaemonState {
-    fn new() -> io::Result<Self> {
-        let (regs, cpu_type) = detect_device();
-        let ec = EcWriter::new().map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+    fn new(ec_cache: Duration, model_override: Option<&str>, allow_experimental: bool) -> io::Result<Self> {
+        // Resolve (and log) where settings live before anything is loaded.
+        if config::persistence_enabled() {
+            println!("Config directory: {}", config::config_dir().display());
+        } else {
+            println!("Persistence disabled: settings are kept in memory only");
+        }
+        let (model, regs, mut caps, cpu) = detect_device(model_override);
+        caps.keyboard = keyboard::detect_layout(caps.keyboard);
+        caps.logo_zone = keyboard::detect_logo(caps.logo_zone);
+        caps.gpu_fan_from_nvidia = nvidia::detect_fan_source(caps.gpu_fan_from_nvidia);
+        let kernel_lockdown = lockdown::kernel_lockdown();
+        if let Some(mode) = &kernel_lockdown {
+            lockdown::warn_locked_down(mode);
+        }
+        // An unconfirmed map may put the wrong value in the wrong register.
+        let locked = caps.experimental && !allow_experimental;
+        if locked {
+            eprintln!("Register map for {model} is unconfirmed: monitoring only. Start with --allow-experimental to enable EC writes.");
+        }
+        // Keyboard timeout: switching it for a moment goes unnoticed.
+        let probe = WriteProbe { address: regs.kb_30_sec_auto, values: [regs.kb_30_auto_off, regs.kb_30_auto_on] };
+        let ec = EcWriter::new(probe, locked).map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
         let tdp_cfg = TdpConfig::load_or_default();
+        let rgb_cfg = RgbConfig::load().unwrap_or_default();
 
         Ok(Self {
+            model,
+            kernel_lockdown,
             ec,
             regs,
-            cpu_ctl: CpuController::new(cpu_type),
+            caps,
+            cpu_ctl: CpuController::new(cpu.cpu),
+            cpu_detail: cpu.detail,
             tdp_mw: tdp_cfg.tdp_mw,
             power_profile: tdp_cfg.profile,
+            safety: SafetyConfig::load_or_default(),
+            fan_override: None,
+            critical_since: None,
+            shutdown_requested: false,
+            undo_stack: VecDeque::with_capacity(UNDO_DEPTH),
+            nitro_cfg: NitroConfig::load_or_default(),
+            nitro_cfg_dirty_since: None,
+            app_rules: AppRuleState {
+                rules: parse_app_rules(&AppRulesConfig::load_or_default().rules).unwrap_or_else(|e| {
+                    eprintln!("Ignoring app rules: {}", e);
+                    Vec::new()
+                }),
+                ..AppRuleState::default()
+            },
+            ec_cache,
+            fan_ramps: Vec::new(),
+            passive_fans: Vec::new(),
+            bogus_temps: RefCell::new(HashSet::new()),
+            rpm_filters: RefCell::new([RpmFilter::default(); 2]),
+            nvidia_fan_percent: None,
+            fans_polled_at: Cell::new(None),
+            events: VecDeque::new(),
+            event_seq: 0,
+            current_client: 0,
+            client_names: HashMap::new(),
+            // Software effects live only in the daemon, so pick them back up.
+            effect_started: rgb_cfg.software_breathing().then(Instant::now),
+            rgb_cfg,
+            mode_tint: None,
+            mode_readback: None,
         })
     }
 
-    fn get_fan_mode(&self, val: u8, auto: u8, turbo: u8, manual: u8) -> FanMode {
-        if val == auto { FanMode::Auto }
-        else if val == turbo { FanMode::Turbo }
-        else if val == manual { FanMode::Manual }
+    /// Current value of one register, read straight from the EC rather
+    /// than refreshing all 256 bytes.  Falls back to the last poll if the
+    /// read fails.
+    fn read_now(&mut self, address: u8) -> u8 {
+        self.ec.read_one(address).unwrap_or_else(|e| {
+            eprintln!("EC read of 0x{address:02X} failed ({e}); using the last poll");
+            self.ec.read(address)
+        })
+    }
+
+    /// Write a register, remembering its previous value for [`Request::Undo`].
+    fn write_tracked(&mut self, address: u8, value: u8) {
+        // Re-read so the saved value reflects the EC right now, not the last poll.
+        let previous = self.read_now(address);
+        if self.undo_stack.len() == UNDO_DEPTH {
+            self.undo_stack.pop_front();
+        }
+        self.undo_stack.push_back((address, previous));
+        self.write_unlocked(address, value);
+    }
+
+    /// Write a register, first sending the unlock byte the model's map
+    /// asks for.
+    fn write_unlocked(&mut self, address: u8, value: u8) {
+        if let Some(unlock) = self.regs.unlock_for(address) {
+            self.ec.write(unlock.reg, unlock.value);
+        }
+        self.ec.write(address, value);
+    }
+
+    /// Change the persisted settings.  The file is written later by
+    /// [`flush_nitro_config`](Self::flush_nitro_config), so bursts of
+    /// clicks cost one save.
+    fn update_nitro_config(&mut self, f: impl FnOnce(&mut NitroConfig)) {
+        f(&mut self.nitro_cfg);
+        self.nitro_cfg_dirty_since.get_or_insert_with(Instant::now);
+    }
+
+    /// Save pending settings once they have settled for [`CONFIG_SAVE_DELAY`],
+    /// or right away with `force` (shutdown).
+    fn flush_nitro_config(&mut self, force: bool) {
+        let Some(since) = self.nitro_cfg_dirty_since else { return };
+        if force || since.elapsed() >= CONFIG_SAVE_DELAY {
+            self.nitro_cfg.save();
+            self.nitro_cfg_dirty_since = None;
+        }
+    }
+
+    /// Revert the most recent tracked write and keep the saved config in step.
+    fn undo_last_write(&mut self) -> Response {
+        let Some((address, value)) = self.undo_stack.pop_back() else {
+            return Response::Error("Nothing to undo".into());
+        };
+        self.fan_ramps.clear();
+        self.write_unlocked(address, value);
+
+        let r = self.regs.clone();
+        self.update_nitro_config(|cfg| {
+            if address == r.cpu_fan_mode_control {
+                cfg.cpu_mode = value;
+            } else if address == r.gpu_fan_mode_control {
+                cfg.gpu_mode = value;
+            } else if address == r.nitro_mode {
+                cfg.nitro_mode = value;
+            } else if address == r.kb_30_sec_auto {
+                cfg.kb_timeout = value;
+            } else if address == r.usb_charging_reg {
+                cfg.usb_charging = value;
+            } else if address == r.battery_charge_limit {
+                cfg.battery_charge_limit = value;
+            }
+        });
+
+        println!("Undo: restored EC 0x{address:02X} to 0x{value:02X}");
+        Response::Ok
+    }
+
+    /// Force manual fans to Turbo while either sensor is above the critical
+    /// temperature, and hand control back once both have cooled down.
+    /// Expects a freshly refreshed EC buffer.
+    fn check_fan_safety(&mut self) {
+        if !self.safety.override_enabled {
+            return;
+        }
+
+        let hottest = self.read_temp(self.regs.cpu_temp).max(self.read_temp(self.regs.gpu_temp));
+        let critical = self.safety.critical_temp;
+
+        match &self.fan_override {
+            None if hottest >= critical => {
+                let cpu_manual = self.ec.read(self.regs.cpu_fan_mode_control) == self.regs.cpu_manual_mode;
+                let gpu_manual = self.ec.read(self.regs.gpu_fan_mode_control) == self.regs.gpu_manual_mode;
+                if !cpu_manual && !gpu_manual {
+                    return;
+                }
+
+                let saved = FanOverride {
+                    cpu_level: cpu_manual.then(|| self.ec.read(self.regs.cpu_manual_speed_control)),
+                    gpu_level: gpu_manual.then(|| self.ec.read(self.regs.gpu_manual_speed_control)),
+                };
+                // A ramp would keep writing levels under the override.
+                self.fan_ramps.clear();
+                if cpu_manual {
+                    self.ec.write(self.regs.cpu_fan_mode_control, self.regs.cpu_turbo_mode);
+                }
+                if gpu_manual {
+                    self.ec.write(self.regs.gpu_fan_mode_control, self.regs.gpu_turbo_mode);
+                }
+                println!("Thermal safety: {hottest}°C >= {critical}°C, forcing manual fans to Turbo.");
+                self.fan_override = Some(saved);
+                self.record_event(None, format!("Manual fans → Turbo by thermal safety at {hottest}°C"));
+            }
+            Some(saved) if hottest < critical.saturating_sub(SAFETY_HYSTERESIS) => {
+                // Only restore fans the user has not switched away from Turbo since.
+                if let Some(level) = saved.cpu_level {
+                    if self.ec.read(self.regs.cpu_fan_mode_control) == self.regs.cpu_turbo_mode {
+                        self.ec.write(self.regs.cpu_fan_mode_control, self.regs.cpu_manual_mode);
+                        self.ec.write(self.regs.cpu_manual_speed_control, level);
+                    }
+                }
+                if let Some(level) = saved.gpu_level {
+                    if self.ec.read(self.regs.gpu_fan_mode_control) == self.regs.gpu_turbo_mode {
+                        self.ec.write(self.regs.gpu_fan_mode_control, self.regs.gpu_manual_mode);
+                        self.ec.write(self.regs.gpu_manual_speed_control, level);
+                    }
+                }
+                println!("Thermal safety: cooled to {hottest}°C, restoring manual fan control.");
+                self.fan_override = None;
+                self.record_event(None, "Manual fan control restored by thermal safety".into());
+            }
+            _ => {}
+        }
+    }
+
+    /// Power off if CPU or GPU has stayed above the opt-in shutdown threshold
+    /// for the configured duration.  Expects a freshly refreshed EC buffer.
+    fn check_critical_shutdown(&mut self) {
+        let Some(limit) = self.safety.critical_shutdown_temp else {
+            return;
+        };
+        if self.shutdown_requested {
+            return;
+        }
+
+        let hottest = self.read_temp(self.regs.cpu_temp).max(self.read_temp(self.regs.gpu_temp));
+        if hottest < limit {
+            if self.critical_since.take().is_some() {
+                println!("Thermal safety: back below {limit}°C, shutdown cancelled.");
+            }
+            return;
+        }
+
+        let since = *self.critical_since.get_or_insert_with(|| {
+            eprintln!(
+                "WARNING: {hottest}°C >= {limit}°C – powering off in {} s unless it cools down!",
+                self.safety.critical_shutdown_seconds
+            );
+            Instant::now()
+        });
+        if since.elapsed() < Duration::from_secs(self.safety.critical_shutdown_seconds as u64) {
+            return;
+        }
+
+        eprintln!(
+            "CRITICAL: temperature has stayed at or above {limit}°C for {} s ({hottest}°C now). Shutting down!",
+            self.safety.critical_shutdown_seconds
+        );
+        self.shutdown_requested = true;
+        self.flush_nitro_config(true);
+        if let Err(e) = Command::new("systemctl").arg("poweroff").status() {
+            eprintln!("Failed to run systemctl poweroff: {}", e);
+            self.shutdown_requested = false;
+        }
+    }
+
+    fn get_fan_mode(&self, fan: FanRegs) -> FanMode {
+        let val = self.ec.read(fan.mode_ctrl);
+        let emulated_passive = || self.passive_fans.contains(&fan.mode_ctrl) && self.ec.read(fan.speed_ctrl) == 0;
+        if val == fan.auto_mode { FanMode::Auto }
+        else if val == fan.turbo_mode { FanMode::Turbo }
+        else if (self.caps.fan_stop && val == fan.passive_mode) || (val == fan.manual_mode && emulated_passive()) {
+            FanMode::Passive
+        }
+        else if val == fan.manual_mode { FanMode::Manual }
         else { FanMode::Unknown(val) }
     }
 
@@ -48,9 +529,500 @@ impl DaemonState {
          if val == self.regs.quiet_mode { NitroMode::Quiet }
          else if val == self.regs.default_mode { NitroMode::Default }
          else if val == self.regs.extreme_mode { NitroMode::Extreme }
+         else if self.caps.turbo_mode && val == self.regs.turbo_mode { NitroMode::Turbo }
          else { NitroMode::Unknown(val) }
     }
 
+    /// Register value for `mode`, if this model has it.
+    fn nitro_mode_value(&self, mode: NitroMode) -> Result<u8, String> {
+        match mode {
+            NitroMode::Quiet => Ok(self.regs.quiet_mode),
+            NitroMode::Default => Ok(self.regs.default_mode),
+            NitroMode::Extreme => Ok(self.regs.extreme_mode),
+            NitroMode::Turbo if self.caps.turbo_mode => Ok(self.regs.turbo_mode),
+            NitroMode::Turbo => Err("Turbo mode not supported on this model".into()),
+            _ => Err("Invalid mode".into()),
+        }
+    }
+
+    fn fan_regs(&self, gpu: bool) -> FanRegs {
+        let r = &self.regs;
+        if gpu {
+            FanRegs {
+                mode_ctrl: r.gpu_fan_mode_control,
+                auto_mode: r.gpu_auto_mode,
+                turbo_mode: r.gpu_turbo_mode,
+                manual_mode: r.gpu_manual_mode,
+                passive_mode: r.gpu_passive_mode,
+                speed_ctrl: r.gpu_manual_speed_control,
+                duty: r.gpu_fan_duty,
+            }
+        } else {
+            FanRegs {
+                mode_ctrl: r.cpu_fan_mode_control,
+                auto_mode: r.cpu_auto_mode,
+                turbo_mode: r.cpu_turbo_mode,
+                manual_mode: r.cpu_manual_mode,
+                passive_mode: r.cpu_passive_mode,
+                speed_ctrl: r.cpu_manual_speed_control,
+                duty: r.cpu_fan_duty,
+            }
+        }
+    }
+
+    /// Finish a fan mode change once [`MODE_READBACK_DELAY`] has passed.
+    /// Some firmwares ignore a mode they won't run right now (Turbo on
+    /// battery, Manual during a thermal event) without any error, so the
+    /// register is read back first.  A failed read counts as accepted.
+    fn finish_fan_mode(&mut self, readback: ModeReadback) -> Response {
+        let ModeReadback { gpu, fan, value, emulated_passive } = readback;
+        if !self.ec.read_one(fan.mode_ctrl).map_or(true, |v| v == value) {
+            let name = if gpu { "GPU" } else { "CPU" };
+            return Response::Error(format!("Firmware rejected the {name} fan mode change"));
+        }
+        if emulated_passive {
+            self.passive_fans.push(fan.mode_ctrl);
+        }
+        self.update_nitro_config(|c| if gpu { c.gpu_mode = value } else { c.cpu_mode = value });
+        Response::Ok
+    }
+
+    /// Switch one fan's mode.  Passive uses the firmware fan-stop where the
+    /// model has one; elsewhere it is emulated with Manual 0%, which is only
+    /// allowed while the thermal override can spin the fan back up.  A
+    /// direct write is finished by the caller with [`finish_fan_mode`].
+    fn set_fan_mode(&mut self, gpu: bool, mode: FanMode) -> Response {
+        let mode = match self.interlock_fan_mode(mode) {
+            Ok(m) => m,
+            Err(e) => return Response::Error(e),
+        };
+        let emulate_passive = mode == FanMode::Passive && !self.caps.fan_stop;
+        if emulate_passive && !self.safety.override_enabled {
+            return Response::Error(
+                "Passive mode needs the thermal safety override on models without firmware fan-stop".into(),
+            );
+        }
+        let fan = self.fan_regs(gpu);
+        let val = match mode {
+            FanMode::Auto => fan.auto_mode,
+            FanMode::Turbo => fan.turbo_mode,
+            FanMode::Manual => fan.manual_mode,
+            FanMode::Passive if emulate_passive => fan.manual_mode,
+            FanMode::Passive => fan.passive_mode,
+            _ => return Response::Error("Invalid mode".into()),
+        };
+        self.cancel_fan_ramp(fan);
+        self.passive_fans.retain(|&a| a != fan.mode_ctrl);
+        let readback = ModeReadback { gpu, fan, value: val, emulated_passive: emulate_passive };
+        // A ramp writes the mode only once it gets there.
+        if mode == FanMode::Turbo && self.start_fan_ramp(fan, self.caps.fan_max_percent, Some(val)) {
+            return self.finish_fan_mode(readback);
+        }
+        if emulate_passive {
+            self.write_tracked(fan.speed_ctrl, 0);
+        } else if mode == FanMode::Manual {
+            self.raise_to_floor(fan);
+        }
+        self.write_tracked(fan.mode_ctrl, val);
+        self.mode_readback = Some(readback);
+        Response::Ok
+    }
+
+    /// Manual level for one fan, in percent.  Refused outside Manual: the
+    /// firmware ignores the level in Auto and Turbo, so accepting it would
+    /// silently do nothing.
+    fn set_fan_speed(&mut self, gpu: bool, percent: u8) -> Response {
+        let fan = self.fan_regs(gpu);
+        if self.read_now(fan.mode_ctrl) != fan.manual_mode {
+            let name = if gpu { "GPU" } else { "CPU" };
+            return Response::Error(format!("The {name} fan is not in Manual mode; switch it to Manual to set a speed"));
+        }
+        self.cancel_fan_ramp(fan);
+        self.passive_fans.retain(|&a| a != fan.mode_ctrl);
+        let percent = percent.max(self.nitro_cfg.manual_fan_floor).min(self.caps.fan_max_percent);
+        if !self.start_fan_ramp(fan, percent, None) {
+            self.write_tracked(fan.speed_ctrl, self.caps.fan_percent_to_raw(percent));
+        }
+        Response::Ok
+    }
+
+    /// Raise `fan`'s manual level to the configured floor if it sits below.
+    /// The level is kept outside Manual too, so this works before the mode
+    /// switch.
+    fn raise_to_floor(&mut self, fan: FanRegs) {
+        let floor = self.nitro_cfg.manual_fan_floor.min(self.caps.fan_max_percent);
+        let level = self.read_now(fan.speed_ctrl);
+        if self.caps.fan_raw_to_percent(level) < floor {
+            self.write_tracked(fan.speed_ctrl, self.caps.fan_percent_to_raw(floor));
+        }
+    }
+
+    /// Drop any ramp on `fan`; a newer request supersedes it.
+    fn cancel_fan_ramp(&mut self, fan: FanRegs) {
+        self.fan_ramps.retain(|r| r.fan.mode_ctrl != fan.mode_ctrl);
+    }
+
+    /// Step `fan` up to `target` percent instead of jumping there, when
+    /// ramps are enabled and the increase is large.  `finish` is the mode
+    /// to switch to at the end; without one, only a fan already in Manual
+    /// is ramped.  Returns false if the caller should apply the change
+    /// directly.
+    fn start_fan_ramp(&mut self, fan: FanRegs, target: u8, finish: Option<u8>) -> bool {
+        if !self.nitro_cfg.fan_ramp {
+            return false;
+        }
+        let manual = self.read_now(fan.mode_ctrl) == fan.manual_mode;
+        if finish.is_none() && !manual {
+            return false;
+        }
+        let start = if self.caps.fan_duty_readback {
+            let duty = self.read_now(fan.duty);
+            self.caps.fan_raw_to_percent(duty)
+        } else if manual {
+            let level = self.read_now(fan.speed_ctrl);
+            self.caps.fan_raw_to_percent(level)
+        } else {
+            RAMP_AUTO_START
+        };
+        if target.saturating_sub(start) < RAMP_MIN_DELTA {
+            return false;
+        }
+
+        let level = start.saturating_add(RAMP_STEP_PERCENT).min(target);
+        match finish {
+            // Undo returns the fan to the mode it was in before the ramp.
+            Some(_) => {
+                self.ec.write(fan.speed_ctrl, self.caps.fan_percent_to_raw(level));
+                self.write_tracked(fan.mode_ctrl, fan.manual_mode);
+            }
+            None => self.write_tracked(fan.speed_ctrl, self.caps.fan_percent_to_raw(level)),
+        }
+        self.fan_ramps.push(FanRamp { fan, level, target, finish });
+        true
+    }
+
+    /// Advance every in-progress ramp by one step.
+    fn step_fan_ramps(&mut self) {
+        for mut ramp in std::mem::take(&mut self.fan_ramps) {
+            ramp.level = ramp.level.saturating_add(RAMP_STEP_PERCENT).min(ramp.target);
+            self.ec.write(ramp.fan.speed_ctrl, self.caps.fan_percent_to_raw(ramp.level));
+            if ramp.level < ramp.target {
+                self.fan_ramps.push(ramp);
+            } else if let Some(mode) = ramp.finish {
+                self.ec.write(ramp.fan.mode_ctrl, mode);
+            }
+        }
+    }
+
+    /// Battery interlock for a high-power request; `what` names it in the
+    /// refusal.  `Ok(true)` means the capped setting should be applied.
+    fn battery_interlock(&mut self, what: &str) -> Result<bool, String> {
+        if self.safety.battery_turbo == BatteryTurboPolicy::Allow {
+            return Ok(false);
+        }
+        self.ec.refresh_if_older_than(self.ec_cache);
+        if self.ec.read(self.regs.power_status) == self.regs.power_plugged_in {
+            return Ok(false);
+        }
+        match self.safety.battery_turbo {
+            BatteryTurboPolicy::Refuse => Err(format!("{what} needs AC power; plug in the charger first")),
+            _ => Ok(true),
+        }
+    }
+
+    /// Turbo fans fall back to Auto under a capping interlock.
+    fn interlock_fan_mode(&mut self, mode: FanMode) -> Result<FanMode, String> {
+        if mode != FanMode::Turbo || !self.battery_interlock("Turbo fan mode")? {
+            return Ok(mode);
+        }
+        println!("Battery interlock: running fans on Auto instead of Turbo.");
+        Ok(FanMode::Auto)
+    }
+
+    /// Extreme and Turbo fall back to Default under a capping interlock.
+    fn interlock_nitro_mode(&mut self, mode: NitroMode) -> Result<NitroMode, String> {
+        if !matches!(mode, NitroMode::Extreme | NitroMode::Turbo)
+            || !self.battery_interlock(&format!("{mode:?} mode"))?
+        {
+            return Ok(mode);
+        }
+        println!("Battery interlock: using Default mode instead of {mode:?}.");
+        Ok(NitroMode::Default)
+    }
+
+    /// Switch the performance mode when the set of running programs calls
+    /// for a different one.  Automatic switches are neither undoable nor
+    /// saved, so the user's own choice is what comes back after a reboot.
+    fn apply_app_rules(&mut self, running: &HashSet<String>) {
+        let wanted = self
+            .app_rules
+            .rules
+            .iter()
+            .find(|(exe, _)| running.contains(exe))
+            .map(|&(_, mode)| mode);
+
+        let current = self.read_now(self.regs.nitro_mode);
+        if let Some((applied, _)) = self.app_rules.active {
+            if current != applied {
+                // The user picked a mode by hand; leave it alone.
+                self.app_rules.active = None;
+            }
+        }
+
+        let active_mode = self.app_rules.active.map(|(applied, _)| self.get_nitro_mode(applied));
+        if wanted == active_mode {
+            self.app_rules.pending = None;
+            return;
+        }
+        match self.app_rules.pending {
+            Some((mode, since)) if mode == wanted => {
+                if since.elapsed() < APP_RULE_DEBOUNCE {
+                    return;
+                }
+            }
+            _ => {
+                self.app_rules.pending = Some((wanted, Instant::now()));
+                return;
+            }
+        }
+        self.app_rules.pending = None;
+
+        match wanted {
+            Some(mode) => {
+                let Ok(mode) = self.interlock_nitro_mode(mode) else { return };
+                let Ok(val) = self.nitro_mode_value(mode) else { return };
+                let previous = self.app_rules.active.map_or(current, |(_, prev)| prev);
+                println!("App rule: switching to {:?}", mode);
+                self.ec.write(self.regs.nitro_mode, val);
+                self.app_rules.active = Some((val, previous));
+                self.apply_mode_lighting(mode);
+                self.record_event(None, format!("Nitro mode → {mode:?} by app rule"));
+            }
+            None => {
+                if let Some((_, previous)) = self.app_rules.active.take() {
+                    let mode = self.get_nitro_mode(previous);
+                    println!("App rule: restoring {:?}", mode);
+                    self.ec.write(self.regs.nitro_mode, previous);
+                    self.apply_mode_lighting(mode);
+                    self.record_event(None, format!("Nitro mode → {mode:?}, restored after app rule"));
+                }
+            }
+        }
+    }
+
+    // Status groups, read from the last EC snapshot.
+
+    /// Record a change for the other clients' notices.
+    fn record_event(&mut self, source: Option<u64>, text: String) {
+        self.event_seq += 1;
+        if self.events.len() == MAX_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(ChangeEvent { seq: self.event_seq, source, text });
+    }
+
+    /// Record a change made by the connection being served.
+    fn record_client_event(&mut self, change: String) {
+        let who = self.client_names.get(&self.current_client).map_or("another client", String::as_str);
+        let text = format!("{change} by {who}");
+        self.record_event(Some(self.current_client), text);
+    }
+
+    /// Tint the keyboard for `mode` when mode lighting is on, at the user's
+    /// brightness.  Per-key keyboards aren't driven yet.
+    fn apply_mode_lighting(&mut self, mode: NitroMode) {
+        if !self.nitro_cfg.mode_lighting || self.caps.keyboard == KeyboardLayout::PerKey {
+            return;
+        }
+        let Some(color) = mode_color(mode) else { return };
+        self.effect_started = None;
+        keyboard::set_mode(0, 0, 0, self.rgb_cfg.brightness, Direction::default(), color);
+        self.mode_tint = Some(color);
+    }
+
+    /// Whether there is lighting to put back: a saved keyboard config, or
+    /// mode lighting.  Otherwise the firmware's own lighting is left alone.
+    fn has_saved_lighting(&self) -> bool {
+        RgbConfig::load().is_some() || self.nitro_cfg.mode_lighting
+    }
+
+    /// Put the lighting back after a restart or resume; the keyboard comes
+    /// up in its firmware default.
+    fn restore_lighting(&mut self) {
+        if self.caps.keyboard == KeyboardLayout::PerKey {
+            return;
+        }
+        if self.nitro_cfg.mode_lighting {
+            self.ec.refresh_if_older_than(self.ec_cache);
+            let mode = self.get_nitro_mode(self.ec.read(self.regs.nitro_mode));
+            self.apply_mode_lighting(mode);
+        } else {
+            self.show_rgb_config();
+        }
+        println!("Restored keyboard lighting");
+    }
+
+    /// Show the saved lighting, animating effects the firmware can't run
+    /// itself from the effects thread.
+    fn show_rgb_config(&mut self) {
+        self.mode_tint = None;
+        self.effect_started = self.rgb_cfg.software_breathing().then(Instant::now);
+        if self.effect_started.is_none() {
+            write_rgb(&self.rgb_cfg);
+        }
+        // The logo has its own zone bit, so effects on the keys leave it be.
+        if let Some(color) = self.rgb_cfg.logo_color.filter(|_| self.caps.logo_zone) {
+            keyboard::set_logo_color(color);
+        }
+    }
+
+    /// Write the next frame of the running software effect.
+    fn step_software_effect(&mut self) {
+        let Some(started) = self.effect_started else { return };
+        let cfg = &self.rgb_cfg;
+        let color = keyboard::breathing_frame(&cfg.breathing_colors, cfg.speed, started.elapsed().as_secs_f64());
+        keyboard::set_mode(0, 0, 0, cfg.brightness, Direction::default(), color);
+    }
+
+    /// Read every mapped register straight from the EC and flag values that
+    /// look wrong for this model.  Never writes.
+    fn self_test(&mut self) -> Vec<RegisterCheck> {
+        self.regs
+            .named_addresses(&self.caps)
+            .into_iter()
+            .map(|(name, address)| {
+                let (value, problem) = match self.ec.read_one(address) {
+                    Ok(v) => (Some(v), self.register_problem(address, v)),
+                    Err(e) => (None, Some(format!("read failed: {e}"))),
+                };
+                RegisterCheck { name: name.to_string(), address, value, problem }
+            })
+            .collect()
+    }
+
+    /// Why `value` is implausible for the register at `address`, if it is.
+    fn register_problem(&self, address: u8, value: u8) -> Option<String> {
+        let r = &self.regs;
+        if value == 0xFF {
+            return Some("reads 0xFF, usually an unmapped address or a failed read".into());
+        }
+        if [r.cpu_temp, r.gpu_temp, r.sys_temp].contains(&address) {
+            return r.decode_temp(value).err().map(|c| format!("decodes to {c}°C, outside the plausible range"));
+        }
+        if address == r.nitro_mode {
+            return matches!(self.get_nitro_mode(value), NitroMode::Unknown(_))
+                .then(|| "not one of the map's Nitro mode values".into());
+        }
+        for fan in [self.fan_regs(false), self.fan_regs(true)] {
+            if address == fan.mode_ctrl {
+                let mut known = vec![fan.auto_mode, fan.turbo_mode, fan.manual_mode];
+                if self.caps.fan_stop {
+                    known.push(fan.passive_mode);
+                }
+                return (!known.contains(&value)).then(|| "not one of the map's fan mode values".into());
+            }
+            if address == fan.speed_ctrl && value > self.caps.fan_raw_max {
+                return Some(format!("above the full-speed value 0x{:02X}", self.caps.fan_raw_max));
+            }
+        }
+        None
+    }
+
+    /// Temperature register decoded for this model and clamped to
+    /// [`TEMP_RANGE`].
+    fn read_temp(&self, address: u8) -> u8 {
+        let raw = self.ec.read(address);
+        match self.regs.decode_temp(raw) {
+            Ok(celsius) => celsius,
+            Err(celsius) => {
+                if self.bogus_temps.borrow_mut().insert(address) {
+                    eprintln!(
+                        "Implausible temperature at EC 0x{address:02X}: raw 0x{raw:02X} decodes to {celsius}°C; \
+                         check temp_offset/temp_is_signed for this model"
+                    );
+                }
+                celsius.clamp(*TEMP_RANGE.start(), *TEMP_RANGE.end()) as u8
+            }
+        }
+    }
+
+    fn temp_status(&self) -> TempStatus {
+        TempStatus {
+            cpu_temp: self.read_temp(self.regs.cpu_temp),
+            gpu_temp: self.read_temp(self.regs.gpu_temp),
+            sys_temp: self.read_temp(self.regs.sys_temp),
+        }
+    }
+
+    fn fan_status(&self) -> FanStatus {
+        let now = Instant::now();
+        // Keeps the NVIDIA sampler running while someone is watching.
+        self.fans_polled_at.set(Some(now));
+        let rpm = |fan: usize, high: u8, low: u8| {
+            let raw = ((self.ec.read(low) as u16) << 8) | self.ec.read(high) as u16;
+            let shown = self.rpm_filters.borrow_mut()[fan].filter(raw, &self.caps, now);
+            if shown != raw {
+                eprintln!("Ignoring implausible {} fan reading of {raw} RPM (torn EC read?)", ["CPU", "GPU"][fan]);
+            }
+            shown
+        };
+        let duty = |reg: u8| self.caps.fan_duty_readback.then(|| self.caps.fan_raw_to_percent(self.ec.read(reg)));
+        let level = |reg: u8| self.caps.fan_raw_to_percent(self.ec.read(reg));
+        FanStatus {
+            cpu_fan_speed: rpm(0, self.regs.cpu_fan_speed_high, self.regs.cpu_fan_speed_low),
+            gpu_fan_speed: rpm(1, self.regs.gpu_fan_speed_high, self.regs.gpu_fan_speed_low),
+            cpu_fan_duty: duty(self.regs.cpu_fan_duty),
+            gpu_fan_duty: duty(self.regs.gpu_fan_duty),
+            gpu_fan_percent: self.nvidia_fan_percent,
+            cpu_manual_level: level(self.regs.cpu_manual_speed_control),
+            gpu_manual_level: level(self.regs.gpu_manual_speed_control),
+            manual_fan_floor: self.nitro_cfg.manual_fan_floor,
+        }
+    }
+
+    fn mode_status(&self) -> ModeStatus {
+        let r = &self.regs;
+        ModeStatus {
+            cpu_mode: self.get_fan_mode(self.fan_regs(false)),
+            gpu_mode: self.get_fan_mode(self.fan_regs(true)),
+            nitro_mode: self.get_nitro_mode(self.ec.read(r.nitro_mode)),
+            coolboost: self.caps.coolboost && self.ec.read(r.coolboost_reg) == r.coolboost_on,
+            tdp_value: self.tdp_mw,
+            power_profile: self.power_profile,
+        }
+    }
+
+    /// Also reads the adapter and battery from sysfs.
+    fn power_status(&self) -> PowerStatus {
+        let r = &self.regs;
+        let adapter_watts = power_supply::adapter_watts();
+        let flow = power_supply::battery_flow();
+        PowerStatus {
+            power_plugged_in: self.ec.read(r.power_status) == r.power_plugged_in,
+            battery_status: self.get_battery_status(self.ec.read(r.battery_status)),
+            battery_charge_limit: self.ec.read(r.battery_charge_limit) == r.battery_limit_on,
+            usb_charging: self.ec.read(r.usb_charging_reg) == r.usb_charging_on,
+            adapter_watts,
+            adapter_underpowered: adapter_watts.is_some_and(|w| w < self.caps.rated_adapter_watts),
+            battery_cycles: power_supply::battery_cycles(),
+            battery_health_pct: power_supply::battery_health_pct(),
+            battery_power_w: flow.map(|f| f.watts),
+            battery_time_remaining_min: flow.and_then(|f| f.minutes_left),
+        }
+    }
+
+    fn keyboard_status(&self) -> KeyboardStatus {
+        let r = &self.regs;
+        KeyboardStatus {
+            kb_timeout: self.ec.read(r.kb_30_sec_auto) == r.kb_30_auto_on,
+            kb_brightness: self.caps.kb_brightness_readback.then(|| self.ec.read(r.kb_brightness).min(100)),
+            win_key_lock: self.caps.win_key_lock && self.ec.read(r.win_key_lock) == r.win_key_lock_on,
+            touchpad_lock: self.caps.touchpad_lock && self.ec.read(r.touchpad_lock) == r.touchpad_lock_on,
+            mode_lighting: self.nitro_cfg.mode_lighting,
+            mode_tint: self.mode_tint,
+        }
+    }
+
     fn get_battery_status(&self, val: u8) -> BatteryStatus {
          if val == self.regs.battery_charging { BatteryStatus::Charging }
          else if val == self.regs.battery_draining { BatteryStatus::Discharging }
@@ -59,132 +1031,228 @@ impl DaemonState {
     }
 
     fn handle_request(&mut self, req: Request) -> Response {
+        let needs_ec = !matches!(
+            req,
+            Request::HardwareReset
+                | Request::ReloadEc
+                | Request::GetSystemInfo
+                | Request::SetClientName(_)
+                | Request::GetEvents(_)
+        );
+        if needs_ec && self.ec.is_offline() {
+            return Response::Error(EC_OFFLINE.into());
+        }
+        // Refused before anything is written, saved or pushed for undo.
+        if self.ec.is_locked() && writes_ec(&req) {
+            return Response::Error(EC_LOCKED.into());
+        }
         match req {
-            Request::GetStatus => {
-                self.ec.refresh();
-                
-                // Refresh voltage info (this might be slow)
-                self.cpu_ctl.refresh_voltage();
-                
-                let cpu_mode_val = self.ec.read(self.regs.cpu_fan_mode_control);
-                let gpu_mode_val = self.ec.read(self.regs.gpu_fan_mode_control);
-                let nitro_mode_val = self.ec.read(self.regs.nitro_mode);
-                let battery_status_val = self.ec.read(self.regs.battery_status);
+            Request::GetStatus { force } => {
+                if force {
+                    self.ec.refresh();
+                } else {
+                    self.ec.refresh_if_older_than(self.ec_cache);
+                }
+
+                let TempStatus { cpu_temp, gpu_temp, sys_temp } = self.temp_status();
+                let FanStatus {
+                    cpu_fan_speed,
+                    gpu_fan_speed,
+                    cpu_fan_duty,
+                    gpu_fan_duty,
+                    gpu_fan_percent,
+                    cpu_manual_level,
+                    gpu_manual_level,
+                    manual_fan_floor,
+                } = self.fan_status();
+                let ModeStatus { cpu_mode, gpu_mode, nitro_mode, coolboost, tdp_value, power_profile } = self.mode_status();
+                let PowerStatus {
+                    power_plugged_in,
+                    battery_status,
+                    battery_charge_limit,
+                    usb_charging,
+                    adapter_watts,
+                    adapter_underpowered,
+                    battery_cycles,
+                    battery_health_pct,
+                    battery_power_w,
+                    battery_time_remaining_min,
+                } = self.power_status();
+                let KeyboardStatus { kb_timeout, kb_brightness, win_key_lock, touchpad_lock, mode_lighting, mode_tint } =
+                    self.keyboard_status();
 
                 let data = EcData {
-                    cpu_temp: self.ec.read(self.regs.cpu_temp),
-                    gpu_temp: self.ec.read(self.regs.gpu_temp),
-                    sys_temp: self.ec.read(self.regs.sys_temp),
-                    cpu_fan_speed: {
-                        let hi = self.ec.read(self.regs.cpu_fan_speed_high) as u16;
-                        let lo = self.ec.read(self.regs.cpu_fan_speed_low) as u16;
-                        (lo << 8) | hi
-                    },
-                    gpu_fan_speed: {
-                        let hi = self.ec.read(self.regs.gpu_fan_speed_high) as u16;
-                        let lo = self.ec.read(self.regs.gpu_fan_speed_low) as u16;
-                        (lo << 8) | hi
-                    },
-                    power_plugged_in: self.ec.read(self.regs.power_status) == self.regs.power_plugged_in,
-                    battery_status: self.get_battery_status(battery_status_val),
-                    cpu_mode: self.get_fan_mode(cpu_mode_val, self.regs.cpu_auto_mode, self.regs.cpu_turbo_mode, self.regs.cpu_manual_mode),
-                    gpu_mode: self.get_fan_mode(gpu_mode_val, self.regs.gpu_auto_mode, self.regs.gpu_turbo_mode, self.regs.gpu_manual_mode),
-                    nitro_mode: self.get_nitro_mode(nitro_mode_val),
-                    kb_timeout: self.ec.read(self.regs.kb_30_sec_auto) == self.regs.kb_30_auto_on,
-                    usb_charging: self.ec.read(self.regs.usb_charging_reg) == self.regs.usb_charging_on,
-                    battery_charge_limit: self.ec.read(self.regs.battery_charge_limit) == self.regs.battery_limit_on,
+                    model: self.model.clone(),
+                    cpu_temp,
+                    gpu_temp,
+                    sys_temp,
+                    cpu_fan_speed,
+                    gpu_fan_speed,
+                    cpu_fan_duty,
+                    gpu_fan_duty,
+                    gpu_fan_percent,
+                    power_plugged_in,
+                    battery_status,
+                    cpu_mode,
+                    gpu_mode,
+                    nitro_mode,
+                    kb_timeout,
+                    kb_brightness,
+                    usb_charging,
+                    battery_charge_limit,
                     voltage_info: self.cpu_ctl.voltage_info.clone(),
                     undervolt_status: self.cpu_ctl.undervolt_status.clone(),
-                    cpu_manual_level: self.ec.read(self.regs.cpu_manual_speed_control),
-                    gpu_manual_level: self.ec.read(self.regs.gpu_manual_speed_control),
-                    tdp_value: self.tdp_mw,
-                    power_profile: self.power_profile,
+                    undervolt_supported: self.cpu_ctl.is_supported(),
+                    cpu_manual_level,
+                    gpu_manual_level,
+                    manual_fan_floor,
+                    tdp_value,
+                    power_profile,
+                    coolboost,
+                    win_key_lock,
+                    touchpad_lock,
+                    mode_lighting,
+                    mode_tint,
+                    adapter_watts,
+                    adapter_underpowered,
+                    battery_cycles,
+                    battery_health_pct,
+                    battery_power_w,
+                    battery_time_remaining_min,
+                    caps: self.caps.clone(),
                 };
                 Response::Status(data)
             }
-            Request::SetCpuFanMode(mode) => {
-                let val = match mode {
-                    FanMode::Auto => self.regs.cpu_auto_mode,
-                    FanMode::Turbo => self.regs.cpu_turbo_mode,
-                    FanMode::Manual => self.regs.cpu_manual_mode,
-                    _ => return Response::Error("Invalid mode".into()),
-                };
-                self.ec.write(self.regs.cpu_fan_mode_control, val);
-                let mut cfg = NitroConfig::load_or_default();
-                cfg.cpu_mode = val;
-                cfg.save();
-                Response::Ok
+            Request::GetStatusFields(fields) => {
+                let wants = |f: StatusField| fields.contains(&f);
+                // Everything but voltage comes from the EC snapshot.
+                if fields.iter().any(|&f| f != StatusField::Voltage) {
+                    self.ec.refresh_if_older_than(self.ec_cache);
+                }
+                Response::StatusFields(StatusFields {
+                    temps: wants(StatusField::Temps).then(|| self.temp_status()),
+                    fans: wants(StatusField::Fans).then(|| self.fan_status()),
+                    modes: wants(StatusField::Modes).then(|| self.mode_status()),
+                    power: wants(StatusField::Power).then(|| self.power_status()),
+                    voltage: wants(StatusField::Voltage).then(|| self.cpu_ctl.voltage_info.clone()),
+                    keyboard: wants(StatusField::Keyboard).then(|| self.keyboard_status()),
+                })
             }
-            Request::SetGpuFanMode(mode) => {
-                let val = match mode {
-                    FanMode::Auto => self.regs.gpu_auto_mode,
-                    FanMode::Turbo => self.regs.gpu_turbo_mode,
-                    FanMode::Manual => self.regs.gpu_manual_mode,
-                    _ => return Response::Error("Invalid mode".into()),
-                };
-                self.ec.write(self.regs.gpu_fan_mode_control, val);
-                let mut cfg = NitroConfig::load_or_default();
-                cfg.gpu_mode = val;
-                cfg.save();
-                Response::Ok
+            Request::SetCpuFanMode(mode) => self.set_fan_mode(false, mode),
+            // Single-fan models: the CPU controller is the only one that works.
+            Request::SetGpuFanMode(mode) if !self.caps.independent_gpu_fan => {
+                self.handle_request(Request::SetCpuFanMode(mode))
             }
-            Request::SetCpuFanSpeed(val) => {
-                self.ec.write(self.regs.cpu_manual_speed_control, val);
-                Response::Ok
+            Request::SetGpuFanSpeed(val) if !self.caps.independent_gpu_fan => {
+                self.handle_request(Request::SetCpuFanSpeed(val))
             }
-            Request::SetGpuFanSpeed(val) => {
-                self.ec.write(self.regs.gpu_manual_speed_control, val);
+            Request::SetGpuFanMode(mode) => self.set_fan_mode(true, mode),
+            Request::SetCpuFanSpeed(percent) => self.set_fan_speed(false, percent),
+            Request::SetGpuFanSpeed(percent) => self.set_fan_speed(true, percent),
+            Request::SetManualFanFloor(percent) if percent > self.caps.fan_max_percent => Response::Error(format!(
+                "Fan floor {percent}% is above this model's {}% maximum",
+                self.caps.fan_max_percent
+            )),
+            Request::SetManualFanFloor(percent) => {
+                self.update_nitro_config(|c| c.manual_fan_floor = percent);
+                // Lift fans already running in Manual below the new floor,
+                // leaving emulated Passive (Manual 0%) and climbing ramps alone.
+                let gpu_fans: &[bool] = if self.caps.independent_gpu_fan { &[false, true] } else { &[false] };
+                for &gpu in gpu_fans {
+                    let fan = self.fan_regs(gpu);
+                    let manual = self.read_now(fan.mode_ctrl) == fan.manual_mode;
+                    let ramping = self.fan_ramps.iter().any(|r| r.fan.mode_ctrl == fan.mode_ctrl);
+                    if manual && !ramping && !self.passive_fans.contains(&fan.mode_ctrl) {
+                        self.raise_to_floor(fan);
+                    }
+                }
                 Response::Ok
             }
             Request::SetNitroMode(mode) => {
-                let val = match mode {
-                    NitroMode::Quiet => self.regs.quiet_mode,
-                    NitroMode::Default => self.regs.default_mode,
-                    NitroMode::Extreme => self.regs.extreme_mode,
-                     _ => return Response::Error("Invalid mode".into()),
+                let val = match self.interlock_nitro_mode(mode).and_then(|m| self.nitro_mode_value(m)) {
+                    Ok(v) => v,
+                    Err(e) => return Response::Error(e),
                 };
                
-                self.ec.write(self.regs.nitro_mode, val);
-                let mut cfg = NitroConfig::load_or_default();
-                cfg.nitro_mode = val;
-                cfg.save();
+                self.write_tracked(self.regs.nitro_mode, val);
+                self.update_nitro_config(|c| c.nitro_mode = val);
+                self.apply_mode_lighting(self.get_nitro_mode(val));
                 Response::Ok
             }
             Request::SetKbTimeout(val) => {
                 let reg_val = if val { self.regs.kb_30_auto_on } else { self.regs.kb_30_auto_off };
-                self.ec.write(self.regs.kb_30_sec_auto, reg_val);
+                self.write_tracked(self.regs.kb_30_sec_auto, reg_val);
                 
-                let mut cfg = NitroConfig::load_or_default();
-                cfg.kb_timeout = reg_val;
-                cfg.save();
+                self.update_nitro_config(|c| c.kb_timeout = reg_val);
                 Response::Ok
             }
             Request::SetUsbCharging(val) => {
                 let v = if val { self.regs.usb_charging_on } else { self.regs.usb_charging_off };
-                self.ec.write(self.regs.usb_charging_reg, v);
-                let mut cfg = NitroConfig::load_or_default();
-                cfg.usb_charging = v;
-                cfg.save();
+                self.write_tracked(self.regs.usb_charging_reg, v);
+                self.update_nitro_config(|c| c.usb_charging = v);
                 Response::Ok
             }
             Request::SetBatteryLimit(val) => {
                 let v = if val { self.regs.battery_limit_on } else { self.regs.battery_limit_off };
-                self.ec.write(self.regs.battery_charge_limit, v);
-                let mut cfg = NitroConfig::load_or_default();
-                cfg.battery_charge_limit = v;
-                cfg.save();
+                self.write_tracked(self.regs.battery_charge_limit, v);
+                self.update_nitro_config(|c| c.battery_charge_limit = v);
                 Response::Ok
             }
+            Request::SetKeyboardColor(..) | Request::SetRgbConfig(_) | Request::ResetKeyboard
+                if self.caps.keyboard == KeyboardLayout::PerKey =>
+            {
+                Response::Error("Per-key RGB keyboards are not supported yet".into())
+            }
+            Request::SetKeyboardColor(zone, _, _, _) if zone > self.caps.keyboard.zone_count() => {
+                Response::Error(format!(
+                    "Invalid keyboard zone {zone} (expected 0-{})",
+                    self.caps.keyboard.zone_count()
+                ))
+            }
             Request::SetKeyboardColor(zone, r, g, b) => {
                 let color = Rgb { r, g, b };
-                keyboard::set_mode(0, zone, 0, 0, 0, color);
-                
-                let mut rgb_cfg = RgbConfig::load().unwrap_or_default();
-                rgb_cfg.mode = 0;
-                rgb_cfg.zone = zone;
-                rgb_cfg.color = color;
-                rgb_cfg.save();
-                
+                keyboard::set_mode(0, zone, 0, 0, Direction::default(), color);
+
+                self.rgb_cfg.mode = 0;
+                self.rgb_cfg.zone = zone;
+                self.rgb_cfg.color = color;
+                self.rgb_cfg.save();
+                self.mode_tint = None;
+                self.effect_started = None;
+                Response::Ok
+            }
+            Request::SetRgbConfig(cfg) if cfg.zone > self.caps.keyboard.zone_count() => Response::Error(format!(
+                "Invalid keyboard zone {} (expected 0-{})",
+                cfg.zone,
+                self.caps.keyboard.zone_count()
+            )),
+            Request::SetRgbConfig(cfg) if cfg.breathing_colors.len() > keyboard::MAX_BREATHING_COLORS => {
+                Response::Error(format!("Breathing takes at most {} colours", keyboard::MAX_BREATHING_COLORS))
+            }
+            Request::SetRgbConfig(cfg) => {
+                cfg.save();
+                self.rgb_cfg = cfg;
+                self.show_rgb_config();
+                Response::Ok
+            }
+            Request::GetRgbConfig => Response::RgbConfig(self.rgb_cfg.clone()),
+            Request::ResetKeyboard => {
+                // Static zone 0 rewrites every zone, so no custom colour survives.
+                let cfg = RgbConfig::factory();
+                cfg.save();
+                self.rgb_cfg = cfg;
+                self.show_rgb_config();
+                Response::Ok
+            }
+            Request::SetModeLighting(on) => {
+                self.update_nitro_config(|c| c.mode_lighting = on);
+                if on {
+                    self.ec.refresh_if_older_than(self.ec_cache);
+                    let mode = self.get_nitro_mode(self.ec.read(self.regs.nitro_mode));
+                    self.apply_mode_lighting(mode);
+                } else if self.mode_tint.is_some() {
+                    self.show_rgb_config();
+                }
                 Response::Ok
             }
             Request::ApplyUndervolt(idx) => {
@@ -219,37 +1287,349 @@ impl DaemonState {
                     Err(e) => Response::Error(e),
                 }
             }
+            Request::HardwareReset => self.hardware_reset(),
+            Request::ReloadEc => self.reload_ec(),
+            Request::Undo => self.undo_last_write(),
+            // Sampled by the voltage thread; never read on request.
+            Request::GetVoltage => Response::Voltage(self.cpu_ctl.voltage_info.clone()),
+            Request::GetThermalZones => Response::ThermalZones(thermal::read_all()),
+            Request::GetSystemInfo => Response::SystemInfo(SystemInfo {
+                model: self.model.clone(),
+                kernel_lockdown: self.kernel_lockdown.clone(),
+                keyboard_device: keyboard::device_present(),
+                cpu_tooling: cpu_ctl::tooling_available(self.cpu_ctl.cpu_type()),
+                cpu: self.cpu_ctl.cpu_type(),
+                cpu_detail: self.cpu_detail.clone(),
+                config_dir: if config::persistence_enabled() {
+                    config::config_dir().display().to_string()
+                } else {
+                    "none (--no-persist)".into()
+                },
+                caps: self.caps.clone(),
+            }),
+            // Checked when the EC was opened; asking never writes.
+            Request::GetEcInfo => Response::EcInfo(EcInfo {
+                interface: self.ec.path().to_string(),
+                writes_verified: self.ec.writes_verified(),
+                buffer_len: self.ec.buffer_len(),
+                probed: self.ec.probed().iter().map(|&(path, ok)| (path.to_string(), ok)).collect(),
+            }),
+            Request::GetRegisterMap => Response::RegisterMap(ModelMap {
+                model: self.model.clone(),
+                registers: self.regs.clone(),
+                caps: self.caps.clone(),
+            }),
+            Request::SetClientName(name) => {
+                self.client_names.insert(self.current_client, name);
+                Response::Ok
+            }
+            Request::GetEvents(after) => {
+                let events = self
+                    .events
+                    .iter()
+                    .filter(|e| e.seq > after && e.source != Some(self.current_client))
+                    .map(|e| e.text.clone())
+                    .collect();
+                Response::Events(self.event_seq, events)
+            }
+            Request::DumpEc => {
+                self.ec.refresh_if_older_than(self.ec_cache);
+                Response::EcDump(self.ec.snapshot())
+            }
+            Request::SelfTest => Response::SelfTest(self.self_test()),
+            Request::ResetVoltageStats => {
+                self.cpu_ctl.voltage_info.reset_stats();
+                Response::Ok
+            }
+            Request::SetCoolBoost(val) => {
+                if !self.caps.coolboost {
+                    return Response::Error("CoolBoost not supported on this model".into());
+                }
+                let v = if val { self.regs.coolboost_on } else { self.regs.coolboost_off };
+                self.write_tracked(self.regs.coolboost_reg, v);
+                Response::Ok
+            }
+            Request::SetAppRules(rules) => {
+                let parsed = match parse_app_rules(&rules) {
+                    Ok(p) => p,
+                    Err(e) => return Response::Error(e),
+                };
+                AppRulesConfig { rules }.save();
+                // Any active rule is reverted on the next pass if it no
+                // longer matches.
+                self.app_rules.rules = parsed;
+                self.app_rules.pending = None;
+                Response::Ok
+            }
+            Request::SetWinKeyLock(val) => {
+                if !self.caps.win_key_lock {
+                    return Response::Error("Windows key lock not supported on this model".into());
+                }
+                let v = if val { self.regs.win_key_lock_on } else { self.regs.win_key_lock_off };
+                self.write_tracked(self.regs.win_key_lock, v);
+                Response::Ok
+            }
+            Request::SetTouchpadLock(val) => {
+                if !self.caps.touchpad_lock {
+                    return Response::Error("Touchpad lock not supported on this model".into());
+                }
+                let v = if val { self.regs.touchpad_lock_on } else { self.regs.touchpad_lock_off };
+                self.write_tracked(self.regs.touchpad_lock, v);
+                Response::Ok
+            }
+        }
+    }
+
+    /// Reopen the EC driver with the fans on Auto.  They are handed to the
+    /// firmware through the old handle first, so they stay under its
+    /// control if the EC does not come back.
+    fn reinit_fans_on_auto(&mut self) -> Result<(), EcError> {
+        if !self.ec.is_offline() {
+            self.ec.write(self.regs.cpu_fan_mode_control, self.regs.cpu_auto_mode);
+            self.ec.write(self.regs.gpu_fan_mode_control, self.regs.gpu_auto_mode);
         }
+        self.ec.reinit().inspect_err(|e| {
+            eprintln!("EC reinit failed: {e}; EC control disabled until it is reopened");
+        })
+    }
+
+    /// Reopen the EC driver, e.g. to pick up write support, and leave every
+    /// setting as it was.
+    fn reload_ec(&mut self) -> Response {
+        let modes = [self.regs.cpu_fan_mode_control, self.regs.gpu_fan_mode_control];
+        let saved = (!self.ec.is_offline()).then(|| modes.map(|address| (address, self.read_now(address))));
+        if let Err(e) = self.reinit_fans_on_auto() {
+            return Response::Error(format!("EC reload failed: {e}; fans were left on Auto"));
+        }
+        // Manual levels survive a mode change, so the modes are enough.
+        for (address, value) in saved.into_iter().flatten() {
+            self.ec.write(address, value);
+        }
+        println!("EC driver reloaded.");
+        Response::Ok
+    }
+
+    /// Reopen the EC from scratch and put every control back to a safe state.
+    fn hardware_reset(&mut self) -> Response {
+        // Earlier values may not make sense after a full reset.
+        self.undo_stack.clear();
+        self.fan_ramps.clear();
+        self.passive_fans.clear();
+
+        if let Err(e) = self.reinit_fans_on_auto() {
+            return Response::Error(format!("EC reinit failed: {e}; fans were left on Auto"));
+        }
+
+        self.ec.write(self.regs.cpu_fan_mode_control, self.regs.cpu_auto_mode);
+        self.ec.write(self.regs.gpu_fan_mode_control, self.regs.gpu_auto_mode);
+        self.ec.write(self.regs.nitro_mode, self.regs.default_mode);
+
+        let r = &self.regs;
+        let (cpu_mode, gpu_mode, nitro_mode) = (r.cpu_auto_mode, r.gpu_auto_mode, r.default_mode);
+        self.update_nitro_config(|cfg| {
+            cfg.cpu_mode = cpu_mode;
+            cfg.gpu_mode = gpu_mode;
+            cfg.nitro_mode = nitro_mode;
+        });
+
+        // Factory lighting, not the all-zero default, which is brightness 0.
+        let rgb_cfg = RgbConfig::factory();
+        rgb_cfg.save();
+        self.rgb_cfg = rgb_cfg;
+        self.show_rgb_config();
+
+        println!("Hardware reset complete.");
+        Response::Ok
     }
 }
 
-pub fn run_daemon() {
-    println!("Starting NitroSense daemon...");
-    
-    // Always force remove socket if it exists.
-    if Path::new(SOCKET_PATH).exists() {
-        if let Err(e) = fs::remove_file(SOCKET_PATH) {
-            eprintln!("Error removing existing socket {}: {}. Is another instance running?", SOCKET_PATH, e);
-            // If we can't remove it, we probably can't bind.
-            // But let's try anyway, or exit.
-        } else {
-             println!("Removed stale socket file.");
+/// Bind the daemon socket, clearing a stale socket file left by a crashed
+/// instance.  A socket that still accepts connections belongs to a live
+/// daemon – possibly one that is shutting down during a restart – so that
+/// case is retried with backoff instead of stealing the path.
+fn bind_socket(path: &str) -> io::Result<UnixListener> {
+    let mut delay = BIND_BACKOFF;
+    for attempt in 1..=BIND_ATTEMPTS {
+        match UnixListener::bind(path) {
+            Ok(l) => return Ok(l),
+            Err(e) if e.kind() == io::ErrorKind::AddrInUse => {
+                if UnixStream::connect(path).is_err() {
+                    println!("Removing stale socket file.");
+                    fs::remove_file(path)?;
+                    continue;
+                }
+                eprintln!("Socket {path} is still in use (attempt {attempt}/{BIND_ATTEMPTS}); retrying in {delay:?}");
+            }
+            Err(e) => return Err(e),
+        }
+        thread::sleep(delay);
+        delay *= 2;
+    }
+    Err(io::Error::new(io::ErrorKind::AddrInUse, "another NitroSense daemon is running"))
+}
+
+extern "C" {
+    // From libc, which std already links on Linux.
+    fn geteuid() -> u32;
+    fn fork() -> i32;
+    fn setsid() -> i32;
+    fn dup2(oldfd: i32, newfd: i32) -> i32;
+    fn kill(pid: i32, sig: i32) -> i32;
+}
+
+const SIGTERM: i32 = 15;
+
+/// Pidfile used by `--background` and `--stop` unless `--pidfile` is given.
+pub const DEFAULT_PIDFILE: &str = "/run/nitrosense.pid";
+
+/// Process-management options for `--daemon` (and `--stop`), for inits
+/// other than systemd.
+#[derive(Debug)]
+pub struct DaemonOptions {
+    /// Detach from the terminal instead of running in the foreground.
+    pub background: bool,
+    /// Where to record the daemon's PID; removed again on shutdown.
+    pub pidfile: Option<PathBuf>,
+    /// How long status requests may reuse the last EC read.
+    pub ec_cache: Duration,
+    /// Model name to use instead of the DMI strings.
+    pub model: Option<String>,
+    /// Load and save settings; off with `--no-persist`, for live systems
+    /// and setups where something else owns the EC state at boot.
+    pub persist: bool,
+    /// Write to the EC even when the model's register map is unconfirmed.
+    pub allow_experimental: bool,
+}
+
+impl Default for DaemonOptions {
+    fn default() -> Self {
+        Self { background: false, pidfile: None, ec_cache: EC_CACHE_MAX_AGE, model: None, persist: true, allow_experimental: false }
+    }
+}
+
+impl DaemonOptions {
+    /// Parse `[--background] [--pidfile <path>] [--ec-cache-ms <ms>] [--model <name>] [--no-persist]
+    /// [--allow-experimental]`.
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let mut opts = Self::default();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--background" => opts.background = true,
+                "--pidfile" => match iter.next() {
+                    Some(path) => opts.pidfile = Some(PathBuf::from(path)),
+                    None => return Err("--pidfile requires a path".into()),
+                },
+                "--ec-cache-ms" => match iter.next().map(|v| v.parse::<u64>()) {
+                    Some(Ok(ms)) => opts.ec_cache = Duration::from_millis(ms),
+                    _ => return Err("--ec-cache-ms requires a number of milliseconds".into()),
+                },
+                "--model" => match iter.next() {
+                    Some(name) => opts.model = Some(name.clone()),
+                    None => return Err("--model requires a model name".into()),
+                },
+                "--no-persist" => opts.persist = false,
+                "--allow-experimental" => opts.allow_experimental = true,
+                other => return Err(format!("Unknown daemon option: {other}")),
+            }
+        }
+        // A detached daemon is unreachable without one.
+        if opts.background && opts.pidfile.is_none() {
+            opts.pidfile = Some(PathBuf::from(DEFAULT_PIDFILE));
+        }
+        Ok(opts)
+    }
+}
+
+/// Classic double fork: the caller's process exits, and the survivor has
+/// no controlling terminal and stdio pointing at `/dev/null`.
+fn daemonize() -> io::Result<()> {
+    // SAFETY: called before any threads are spawned, so the children
+    // inherit a consistent single-threaded process.
+    unsafe {
+        match fork() {
+            -1 => return Err(io::Error::last_os_error()),
+            0 => {}
+            _ => std::process::exit(0),
+        }
+        if setsid() == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        match fork() {
+            -1 => return Err(io::Error::last_os_error()),
+            0 => {}
+            _ => std::process::exit(0),
+        }
+    }
+    std::env::set_current_dir("/")?;
+    let null = fs::OpenOptions::new().read(true).write(true).open("/dev/null")?;
+    for fd in 0..=2 {
+        // SAFETY: both descriptors are valid for the duration of the call.
+        if unsafe { dup2(null.as_raw_fd(), fd) } == -1 {
+            return Err(io::Error::last_os_error());
         }
     }
+    Ok(())
+}
 
-    // Set up Ctrl+C handler
-    if let Err(e) = ctrlc::set_handler(move || {
-        println!("\nReceived shutdown signal. Cleaning up...");
-        if Path::new(SOCKET_PATH).exists() {
-            let _ = fs::remove_file(SOCKET_PATH);
-            println!("Socket removed.");
+/// Send SIGTERM to the daemon recorded in `pidfile`.  Returns an exit code.
+pub fn stop_daemon(pidfile: &Path) -> i32 {
+    let pid = match fs::read_to_string(pidfile) {
+        Ok(s) => match s.trim().parse::<i32>() {
+            Ok(pid) if pid > 0 => pid,
+            _ => {
+                eprintln!("Malformed pidfile {}", pidfile.display());
+                return 1;
+            }
+        },
+        Err(e) => {
+            eprintln!("Cannot read {}: {} (is the daemon running?)", pidfile.display(), e);
+            return 1;
         }
-        std::process::exit(0);
-    }) {
-        eprintln!("Error setting Ctrl-C handler: {}", e);
+    };
+    // SAFETY: kill has no memory-safety preconditions.
+    if unsafe { kill(pid, SIGTERM) } == -1 {
+        eprintln!("Failed to stop daemon (pid {}): {}", pid, io::Error::last_os_error());
+        return 1;
     }
+    println!("Sent SIGTERM to daemon (pid {}).", pid);
+    0
+}
+
+/// Remove the files a running daemon owns.
+fn remove_runtime_files(pidfile: Option<&Path>) {
+    if Path::new(SOCKET_PATH).exists() {
+        let _ = fs::remove_file(SOCKET_PATH);
+        println!("Socket removed.");
+    }
+    if let Some(path) = pidfile {
+        let _ = fs::remove_file(path);
+    }
+}
+
+/// Time since boot including time spent suspended, from `/proc/uptime`.
+fn boot_time() -> Option<Duration> {
+    let uptime = fs::read_to_string("/proc/uptime").ok()?;
+    let secs: f64 = uptime.split_whitespace().next()?.parse().ok()?;
+    Some(Duration::from_secs_f64(secs))
+}
 
-    let listener = match UnixListener::bind(SOCKET_PATH) {
+fn running_as_root() -> bool {
+    // SAFETY: geteuid takes no arguments and cannot fail.
+    unsafe { geteuid() == 0 }
+}
+
+pub fn run_daemon(opts: DaemonOptions) {
+    // EC, MSR and module loading all need root; bail out before touching
+    // the socket so a non-root attempt leaves nothing half-started.
+    if !running_as_root() {
+        eprintln!("nitrosense daemon must run as root");
+        std::process::exit(1);
+    }
+
+    println!("Starting NitroSense daemon...");
+
+    let listener = match bind_socket(SOCKET_PATH) {
         Ok(l) => l,
         Err(e) => {
              eprintln!("Failed to bind to socket: {}", e);
@@ -262,16 +1642,35 @@ pub fn run_daemon() {
          eprintln!("Failed to set socket permissions: {}", e);
     }
 
+    // Detach only after binding, so a bind failure still reaches the terminal.
+    if opts.background {
+        if let Err(e) = daemonize() {
+            eprintln!("Failed to daemonize: {}", e);
+            remove_runtime_files(None);
+            std::process::exit(1);
+        }
+    }
+    if let Some(path) = &opts.pidfile {
+        if let Err(e) = fs::write(path, format!("{}\n", std::process::id())) {
+            eprintln!("Failed to write pidfile {}: {}", path.display(), e);
+        }
+    }
+
     println!("NitroSense Daemon started.");
-    
+    if opts.persist {
+        config::enable_dir_fallback();
+    } else {
+        config::disable_persistence();
+    }
+
     // Simple restore
-    if let Ok(mut state) = DaemonState::new() {
-        if let Some(cfg) = NitroConfig::load() {
-             let _ = state.ec.write(state.regs.nitro_mode, cfg.nitro_mode);
+    if let Ok(mut state) = DaemonState::new(opts.ec_cache, opts.model.as_deref(), opts.allow_experimental) {
+        if NitroConfig::load().is_some() {
+             let _ = state.ec.write(state.regs.nitro_mode, state.nitro_cfg.nitro_mode);
         }
 
         // Restore TDP settings
-        if tdp_ctl::is_available() {
+        if config::persistence_enabled() && tdp_ctl::is_available() {
             let tdp_cfg = TdpConfig::load_or_default();
             if let Err(e) = tdp_ctl::apply_tdp_and_profile(tdp_cfg.tdp_mw, tdp_cfg.profile) {
                 eprintln!("Failed to restore TDP settings: {}", e);
@@ -280,40 +1679,363 @@ pub fn run_daemon() {
             }
         }
 
+        let restore_kb = state.has_saved_lighting();
+        let state = Arc::new(Mutex::new(state));
+
+        // Saved keyboard lighting, once the driver's nodes are there.
+        if restore_kb {
+            let state = Arc::clone(&state);
+            thread::spawn(move || {
+                let started = Instant::now();
+                while !keyboard::device_present() {
+                    if started.elapsed() >= KB_RESTORE_WAIT {
+                        eprintln!("Keyboard device did not appear; saved lighting not restored");
+                        return;
+                    }
+                    thread::sleep(KB_RESTORE_POLL);
+                }
+                if let Ok(mut s) = state.lock() {
+                    s.restore_lighting();
+                }
+            });
+        }
+
+        // Saved keyboard lighting again after suspend.  The boot clock keeps
+        // counting while suspended and `Instant` does not, so a gap between
+        // the two means the system slept.
+        {
+            let state = Arc::clone(&state);
+            thread::spawn(move || {
+                let mut last = (Instant::now(), boot_time());
+                loop {
+                    thread::sleep(RESUME_POLL_INTERVAL);
+                    let now = (Instant::now(), boot_time());
+                    let slept = match (last.1, now.1) {
+                        (Some(before), Some(after)) => {
+                            after.saturating_sub(before) > now.0.duration_since(last.0) + RESUME_MIN_GAP
+                        }
+                        _ => false,
+                    };
+                    last = now;
+                    if !slept {
+                        continue;
+                    }
+                    let Ok(mut s) = state.lock() else { break };
+                    if s.has_saved_lighting() {
+                        println!("Resumed from suspend");
+                        s.restore_lighting();
+                    }
+                }
+            });
+        }
+
+        // SIGINT/SIGTERM/SIGHUP handler; flushes any settings still waiting
+        // on the save debounce before exiting.
+        {
+            let state = Arc::clone(&state);
+            let pidfile = opts.pidfile.clone();
+            if let Err(e) = ctrlc::set_handler(move || {
+                println!("\nReceived shutdown signal. Cleaning up...");
+                if let Ok(mut s) = state.lock() {
+                    s.flush_nitro_config(true);
+                }
+                remove_runtime_files(pidfile.as_deref());
+                std::process::exit(0);
+            }) {
+                eprintln!("Error setting Ctrl-C handler: {}", e);
+            }
+        }
+
+        // Thermal safety watchdog
+        {
+            let state = Arc::clone(&state);
+            thread::spawn(move || {
+                let mut last_retry = Instant::now();
+                loop {
+                    thread::sleep(SAFETY_POLL_INTERVAL);
+                    let Ok(mut s) = state.lock() else { break };
+                    s.flush_nitro_config(false);
+                    // No temperatures to check: keep retrying the reinit
+                    // that failed, with the fans left on Auto meanwhile.
+                    if s.ec.is_offline() {
+                        if last_retry.elapsed() >= EC_RETRY_INTERVAL {
+                            last_retry = Instant::now();
+                            if s.ec.reinit().is_ok() {
+                                println!("EC reopened; fans stay on Auto.");
+                                s.record_event(None, "EC reopened, fans on Auto".into());
+                            }
+                        }
+                        continue;
+                    }
+                    s.ec.refresh();
+                    s.check_fan_safety();
+                    s.check_critical_shutdown();
+                }
+            });
+        }
+
+        // Fan ramps; ticks with nothing to do only take the lock.
+        {
+            let state = Arc::clone(&state);
+            thread::spawn(move || loop {
+                thread::sleep(RAMP_STEP_INTERVAL);
+                let Ok(mut s) = state.lock() else { break };
+                if !s.fan_ramps.is_empty() {
+                    s.step_fan_ramps();
+                }
+            });
+        }
+
+        // Software lighting effects; idle ticks only take the lock.
+        {
+            let state = Arc::clone(&state);
+            thread::spawn(move || loop {
+                thread::sleep(EFFECT_FRAME_INTERVAL);
+                let Ok(mut s) = state.lock() else { break };
+                s.step_software_effect();
+            });
+        }
+
+        // Per-application mode rules; the /proc scan runs unlocked.
+        {
+            let state = Arc::clone(&state);
+            thread::spawn(move || loop {
+                thread::sleep(APP_RULE_POLL_INTERVAL);
+                let has_rules = match state.lock() {
+                    Ok(s) => !s.app_rules.rules.is_empty() || s.app_rules.active.is_some(),
+                    Err(_) => break,
+                };
+                if !has_rules {
+                    continue;
+                }
+                let running = app_watch::running_exes();
+                let Ok(mut s) = state.lock() else { break };
+                if !s.ec.is_offline() {
+                    s.apply_app_rules(&running);
+                }
+            });
+        }
+
+        // Voltage sampler; the read itself runs unlocked.
+        {
+            let state = Arc::clone(&state);
+            let cpu_type = state.lock().map(|s| s.cpu_ctl.cpu_type()).unwrap_or(CpuType::Unknown);
+            if cpu_type != CpuType::Unknown {
+                thread::spawn(move || loop {
+                    if let Some(v) = cpu_ctl::read_voltage(cpu_type) {
+                        let Ok(mut s) = state.lock() else { break };
+                        s.cpu_ctl.voltage_info.update(v);
+                    }
+                    thread::sleep(VOLTAGE_POLL_INTERVAL);
+                });
+            }
+        }
+
+        // NVIDIA GPU fan sampler, unlocked like the voltage one.
+        {
+            let state = Arc::clone(&state);
+            let wanted = state.lock().is_ok_and(|s| s.caps.gpu_fan_from_nvidia);
+            if wanted {
+                thread::spawn(move || loop {
+                    // Only while clients read fan speeds: each run can wake
+                    // the dGPU.
+                    let polled = match state.lock() {
+                        Ok(s) => s.fans_polled_at.get().is_some_and(|t| t.elapsed() < NVIDIA_IDLE_AFTER),
+                        Err(_) => break,
+                    };
+                    let percent = if polled { nvidia::read_fan_percent() } else { None };
+                    let Ok(mut s) = state.lock() else { break };
+                    s.nvidia_fan_percent = percent;
+                    drop(s);
+                    thread::sleep(NVIDIA_POLL_INTERVAL);
+                });
+            }
+        }
+
+        let mut next_conn = 0u64;
         for stream in listener.incoming() {
             match stream {
-                Ok(stream) => handle_client(stream, &mut state),
+                // One thread per connection, so an open GUI doesn't keep
+                // the CLI waiting.
+                Ok(stream) => {
+                    next_conn += 1;
+                    let (state, conn) = (Arc::clone(&state), next_conn);
+                    thread::spawn(move || handle_client(stream, &state, conn));
+                }
                 Err(e) => eprintln!("Connection failed: {}", e),
             }
         }
     } else {
         eprintln!("Failed to initialize daemon hardware interface (are you root?)");
+        remove_runtime_files(opts.pidfile.as_deref());
     }
 }
 
-fn handle_client(mut stream: UnixStream, state: &mut DaemonState) {
+/// Keyboard colour for a performance mode under mode lighting.
+fn mode_color(mode: NitroMode) -> Option<Rgb> {
+    match mode {
+        NitroMode::Quiet => Some(Rgb { r: 0, g: 64, b: 255 }),
+        NitroMode::Default => Some(Rgb { r: 255, g: 255, b: 255 }),
+        NitroMode::Extreme => Some(Rgb { r: 255, g: 0, b: 0 }),
+        NitroMode::Turbo => Some(Rgb { r: 255, g: 96, b: 0 }),
+        NitroMode::Unknown(_) => None,
+    }
+}
+
+/// Write a keyboard config to the device.
+fn write_rgb(cfg: &RgbConfig) {
+    match cfg.gradient_end {
+        Some(end) if cfg.mode == 0 => keyboard::set_gradient(cfg.color, end, cfg.brightness),
+        _ => keyboard::set_mode(cfg.mode, cfg.zone, cfg.speed, cfg.brightness, cfg.direction, cfg.color),
+    }
+}
+
+/// Short description of what a request changes, for other clients'
+/// notices.  `None` for requests that only read.
+/// Whether `req` writes the EC, now or later (app rules, the manual fan
+/// floor).
+fn writes_ec(req: &Request) -> bool {
+    matches!(
+        req,
+        Request::SetCpuFanMode(_)
+            | Request::SetGpuFanMode(_)
+            | Request::SetCpuFanSpeed(_)
+            | Request::SetGpuFanSpeed(_)
+            | Request::SetManualFanFloor(_)
+            | Request::SetNitroMode(_)
+            | Request::SetKbTimeout(_)
+            | Request::SetUsbCharging(_)
+            | Request::SetBatteryLimit(_)
+            | Request::SetCoolBoost(_)
+            | Request::SetWinKeyLock(_)
+            | Request::SetTouchpadLock(_)
+            | Request::SetAppRules(_)
+            | Request::HardwareReset
+            | Request::ReloadEc
+            | Request::Undo
+    )
+}
+
+fn change_summary(req: &Request) -> Option<String> {
+    let on_off = |on: bool| if on { "on" } else { "off" };
+    Some(match req {
+        Request::SetNitroMode(mode) => format!("Nitro mode → {mode:?}"),
+        Request::SetCpuFanMode(mode) => format!("CPU fan → {mode:?}"),
+        Request::SetGpuFanMode(mode) => format!("GPU fan → {mode:?}"),
+        Request::SetCpuFanSpeed(percent) => format!("CPU fan speed → {percent}%"),
+        Request::SetGpuFanSpeed(percent) => format!("GPU fan speed → {percent}%"),
+        Request::SetManualFanFloor(percent) => format!("Manual fan floor → {percent}%"),
+        Request::SetKbTimeout(on) => format!("Keyboard timeout → {}", on_off(*on)),
+        Request::SetUsbCharging(on) => format!("USB charging → {}", on_off(*on)),
+        Request::SetBatteryLimit(on) => format!("Battery charge limit → {}", on_off(*on)),
+        Request::SetCoolBoost(on) => format!("CoolBoost → {}", on_off(*on)),
+        Request::SetWinKeyLock(on) => format!("Windows key lock → {}", on_off(*on)),
+        Request::SetTouchpadLock(on) => format!("Touchpad lock → {}", on_off(*on)),
+        Request::SetKeyboardColor(..) | Request::SetRgbConfig(_) => "Keyboard lighting changed".into(),
+        Request::ResetKeyboard => "Keyboard lighting reset to factory default".into(),
+        Request::SetModeLighting(on) => format!("Mode lighting → {}", on_off(*on)),
+        Request::ApplyUndervolt(preset) => format!("Undervolt preset {preset} applied"),
+        Request::SetTdp(mw) => format!("TDP → {} W", mw / 1000),
+        Request::SetPowerProfile(profile) => format!("Power profile → {profile:?}"),
+        Request::SetAppRules(_) => "App rules updated".into(),
+        Request::HardwareReset => "Hardware reset".into(),
+        Request::ReloadEc => "EC driver reloaded".into(),
+        Request::Undo => "Last EC change undone".into(),
+        _ => return None,
+    })
+}
+
+fn handle_client(mut stream: UnixStream, state: &Mutex<DaemonState>, conn: u64) {
     let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let (mut writes, mut reads) = (RateLimit::new(WRITE_RATE_LIMIT), RateLimit::new(READ_RATE_LIMIT));
+    // A client that opens with the handshake byte gets binary frames for
+    // the rest of the connection; anything else is JSON.
+    let encoding = match reader.fill_buf() {
+        Ok([BINARY_HANDSHAKE, ..]) => {
+            reader.consume(1);
+            Encoding::Binary
+        }
+        _ => Encoding::Json,
+    };
+    if encoding == Encoding::Binary && stream.write_all(&[BINARY_HANDSHAKE]).is_err() {
+        return;
+    }
     loop {
-        let mut line = String::new();
-        match reader.read_line(&mut line) {
-            Ok(0) => break, // EOF connection closed
-            Ok(_) => {
+        let (id, req) = match encoding {
+            Encoding::Binary => match protocol::read_frame(&mut reader) {
+                Ok(Some(frame)) => frame,
+                // EOF, or a frame we cannot parse and so cannot skip
+                Ok(None) | Err(_) => break,
+            },
+            Encoding::Json => {
+                let mut line = String::new();
+                match reader.read_line(&mut line) {
+                    Ok(0) | Err(_) => break, // EOF connection closed
+                    Ok(_) => {}
+                }
                 if line.trim().is_empty() { continue; }
-                let req: Request = match serde_json::from_str(&line) {
-                     Ok(r) => r,
-                     Err(e) => {
-                         let _ = writeln!(stream, "{}", serde_json::to_string(&Response::Error(e.to_string())).unwrap());
-                         continue;
-                     }
-                };
-                let resp = state.handle_request(req);
-                if let Ok(resp_str) = serde_json::to_string(&resp) {
-                    if let Err(_) = writeln!(stream, "{}", resp_str) {
-                        break;
+                // Tagged requests carry an id to echo back; bare ones are
+                // accepted as id 0 for older clients.
+                match serde_json::from_str::<Envelope<Request>>(&line) {
+                    Ok(env) => (env.id, env.body),
+                    Err(_) => match protocol::parse_bare_request(&line) {
+                        Ok(r) => (0, r),
+                        Err(e) => {
+                            let _ = writeln!(stream, "{}", serde_json::to_string(&Response::Error(e.to_string())).unwrap());
+                            continue;
+                        }
+                    },
+                }
+            }
+        };
+        let change = change_summary(&req);
+        let allowed = if change.is_some() { writes.allow() } else { reads.allow() };
+        // Refused before locking, so a flooding client can't hold up others.
+        let resp = if !allowed {
+            Response::Error("rate limited".into())
+        } else {
+            match state.lock() {
+                Ok(mut s) => {
+                    s.current_client = conn;
+                    let mut resp = s.handle_request(req);
+                    // Give the firmware time to apply a fan mode without
+                    // holding up other clients and the safety watchdog.
+                    if let Some(readback) = s.mode_readback.take() {
+                        drop(s);
+                        thread::sleep(MODE_READBACK_DELAY);
+                        s = match state.lock() {
+                            Ok(s) => s,
+                            Err(_) => break,
+                        };
+                        resp = s.finish_fan_mode(readback);
+                    }
+                    if let (Some(change), Response::Ok) = (change, &resp) {
+                        s.record_client_event(change);
                     }
+                    resp
+                }
+                Err(_) => Response::Error("Daemon state poisoned".into()),
+            }
+        };
+        let sent = match encoding {
+            Encoding::Binary => protocol::write_frame(&mut stream, id, &resp),
+            Encoding::Json => {
+                let encoded = if id == 0 {
+                    serde_json::to_string(&resp)
+                } else {
+                    serde_json::to_string(&Envelope { id, body: resp })
+                };
+                match encoded {
+                    Ok(resp_str) => writeln!(stream, "{}", resp_str),
+                    Err(_) => Ok(()),
                 }
             }
-            Err(_) => break,
+        };
+        if sent.is_err() {
+            break;
         }
     }
+    if let Ok(mut s) = state.lock() {
+        s.client_names.remove(&conn);
+    }
 }
@@ -1,7 +1,9 @@
+mod cli;
 mod client;
 mod config;
 mod core;
 mod daemon;
+mod metrics;
 mod protocol;
 mod ui;
 mod utils;
@@ -17,12 +19,50 @@ use crate::ui::gui::{build_ui, AppState};
 
 fn main() {
     let args: Vec<String> = env::args().collect();
-    if args.len() > 1 && args[1] == "--daemon" {
-        daemon::run_daemon();
+    if args.len() > 1 && (args[1] == "--daemon" || args[1] == "--stop") {
+        let opts = match daemon::DaemonOptions::from_args(&args[2..]) {
+            Ok(opts) => opts,
+            Err(e) => {
+                eprintln!("{e}");
+                process::exit(2);
+            }
+        };
+        if args[1] == "--stop" {
+            let pidfile = opts.pidfile.unwrap_or_else(|| daemon::DEFAULT_PIDFILE.into());
+            process::exit(daemon::stop_daemon(&pidfile));
+        }
+        daemon::run_daemon(opts);
         return;
     }
+    if args.len() > 1 && args[1] == "--cli" {
+        process::exit(cli::run_cli(&args[2..]));
+    }
+    if args.len() > 1 && args[1] == "--metrics" {
+        let addr = args.get(2).map(String::as_str).unwrap_or(metrics::DEFAULT_ADDR);
+        process::exit(metrics::run_metrics(addr));
+    }
 
     // Client/UI mode
+    // `--socket <path>` is ours; everything else is passed through to GTK.
+    let mut socket_path = config::ClientConfig::load_or_default()
+        .socket_path
+        .unwrap_or_else(|| protocol::SOCKET_PATH.to_string());
+    let mut gtk_args = Vec::with_capacity(args.len());
+    let mut iter = args.into_iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--socket" {
+            match iter.next() {
+                Some(path) => socket_path = path,
+                None => {
+                    eprintln!("--socket requires a path");
+                    process::exit(2);
+                }
+            }
+        } else {
+            gtk_args.push(arg);
+        }
+    }
+
     let app = gtk4::Application::builder()
         .application_id("com.nitrosense.linux")
         .build();
@@ -34,10 +74,19 @@ fn main() {
         // Ideally we catch it.
         // But AppState::new() returns Self, not Result.
         // Let's rely on its panic or change it later if user complains.
-        let state = Rc::new(RefCell::new(AppState::new()));
-        let window = build_ui(app, Rc::clone(&state));
-        window.present();
+        let path = socket_path.clone();
+        let launch = move |app: &gtk4::Application| {
+            let state = Rc::new(RefCell::new(AppState::new(&path)));
+            let window = build_ui(app, Rc::clone(&state));
+            window.present();
+        };
+        // No client config yet means this is the first launch.
+        if config::ClientConfig::load().is_none() {
+            ui::wizard::show(app, &socket_path, launch);
+        } else {
+            launch(app);
+        }
     });
 
-    app.run();
+    app.run_with_args(&gtk_args);
 }
@@ -0,0 +1,127 @@
+//! Prometheus exporter (`linux-nitrosense --metrics [addr]`).
+//!
+//! Serves `GET /metrics` in the Prometheus text exposition format.  Every
+//! scrape sends one [`Request::GetStatusFields`] for just the groups it
+//! exports, so the exporter needs no privileges beyond access to the socket.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+
+use crate::client::Client;
+use crate::protocol::{Request, Response, StatusField, StatusFields};
+
+pub const DEFAULT_ADDR: &str = "127.0.0.1:9124";
+
+/// Groups needed for the exported gauges.
+const FIELDS: [StatusField; 4] = [StatusField::Temps, StatusField::Fans, StatusField::Power, StatusField::Voltage];
+
+/// Render a status snapshot as Prometheus gauges labelled with the model.
+/// Groups missing from the reply are skipped.
+fn render(model: &str, data: &StatusFields) -> String {
+    let model = model.replace('\\', "\\\\").replace('"', "\\\"");
+    let mut gauges: Vec<(&str, &str, f64)> = Vec::new();
+    if let Some(t) = &data.temps {
+        gauges.push(("nitrosense_cpu_temp_celsius", "CPU temperature reported by the EC.", t.cpu_temp as f64));
+        gauges.push(("nitrosense_gpu_temp_celsius", "GPU temperature reported by the EC.", t.gpu_temp as f64));
+        gauges.push(("nitrosense_sys_temp_celsius", "System temperature reported by the EC.", t.sys_temp as f64));
+    }
+    if let Some(f) = &data.fans {
+        gauges.push(("nitrosense_cpu_fan_rpm", "CPU fan speed.", f.cpu_fan_speed as f64));
+        gauges.push(("nitrosense_gpu_fan_rpm", "GPU fan speed.", f.gpu_fan_speed as f64));
+        if let Some(percent) = f.gpu_fan_percent {
+            gauges.push(("nitrosense_gpu_fan_percent", "Discrete GPU fan speed reported by the NVIDIA driver.", percent as f64));
+        }
+    }
+    if let Some(p) = &data.power {
+        gauges.push(("nitrosense_power_plugged", "1 when running on AC power.", p.power_plugged_in as u8 as f64));
+    }
+    if let Some(v) = &data.voltage {
+        gauges.push(("nitrosense_cpu_voltage_volts", "Current CPU core voltage.", v.voltage));
+    }
+
+    let mut out = String::new();
+    for (name, help, value) in gauges {
+        out.push_str(&format!("# HELP {name} {help}\n# TYPE {name} gauge\n"));
+        out.push_str(&format!("{name}{{model=\"{model}\"}} {value}\n"));
+    }
+    out
+}
+
+fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &str) {
+    let _ = write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+}
+
+fn handle_connection(mut stream: TcpStream, client: &mut Client, model: &str) {
+    let mut request_line = String::new();
+    {
+        let mut reader = BufReader::new(&stream);
+        if reader.read_line(&mut request_line).is_err() {
+            return;
+        }
+        // Drain the headers; we don't need any of them.
+        let mut header = String::new();
+        while reader.read_line(&mut header).map(|n| n > 0).unwrap_or(false) && header != "\r\n" {
+            header.clear();
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => {}
+        (Some("GET"), _) => return respond(&mut stream, "404 Not Found", "text/plain", "Not found\n"),
+        _ => return respond(&mut stream, "405 Method Not Allowed", "text/plain", "Method not allowed\n"),
+    }
+
+    match client.send(Request::GetStatusFields(FIELDS.to_vec())) {
+        Ok(Response::StatusFields(data)) => {
+            respond(&mut stream, "200 OK", "text/plain; version=0.0.4", &render(model, &data))
+        }
+        Ok(other) => {
+            eprintln!("Unexpected response to GetStatusFields: {:?}", other);
+            respond(&mut stream, "502 Bad Gateway", "text/plain", "Unexpected daemon response\n")
+        }
+        Err(e) => {
+            eprintln!("IPC error: {}", e);
+            respond(&mut stream, "503 Service Unavailable", "text/plain", "Daemon unavailable\n")
+        }
+    }
+}
+
+/// Serve metrics on `addr` until the process is killed.  Returns the exit
+/// code when startup fails.
+pub fn run_metrics(addr: &str) -> i32 {
+    let mut client = match Client::new() {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("Failed to connect to daemon: {}", e);
+            return 1;
+        }
+    };
+
+    // The model never changes, so it is fetched once instead of per scrape.
+    let model = match client.send(Request::GetSystemInfo) {
+        Ok(Response::SystemInfo(info)) => info.model,
+        _ => String::from("Unknown"),
+    };
+
+    let listener = match TcpListener::bind(addr) {
+        Ok(l) => l,
+        Err(e) => {
+            eprintln!("Failed to bind {}: {}", addr, e);
+            return 1;
+        }
+    };
+    println!("Serving metrics on http://{addr}/metrics");
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => handle_connection(stream, &mut client, &model),
+            Err(e) => eprintln!("Connection failed: {}", e),
+        }
+    }
+    0
+}
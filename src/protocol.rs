@@ -1,30 +1,160 @@
-use serde::{Deserialize, Serialize};
+use std::io::{self, Read, Write};
 
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::config::RgbConfig;
 use crate::core::cpu_ctl::VoltageInfo;
+use crate::core::device_regs::{CpuType, ModelCaps, ModelMap};
+use crate::core::thermal::ThermalZone;
+use crate::utils::keyboard::Rgb;
 
 pub const SOCKET_PATH: &str = "/tmp/nitrosense.sock";
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct EcData {
+    /// DMI product name of the machine the daemon runs on.
+    pub model: String,
     pub cpu_temp: u8,
     pub gpu_temp: u8,
     pub sys_temp: u8,
     pub cpu_fan_speed: u16,
     pub gpu_fan_speed: u16,
+    /// Duty cycle (0-100) the EC is driving each fan at, when readable.
+    pub cpu_fan_duty: Option<u8>,
+    pub gpu_fan_duty: Option<u8>,
+    /// dGPU fan speed in percent from the NVIDIA driver, on models whose
+    /// `gpu_fan_speed` is really a shared chassis fan.
+    pub gpu_fan_percent: Option<u8>,
     pub power_plugged_in: bool,
     pub battery_status: BatteryStatus,
     pub cpu_mode: FanMode,
     pub gpu_mode: FanMode,
     pub nitro_mode: NitroMode,
     pub kb_timeout: bool,
+    /// Live backlight brightness (0-100), when the model exposes it.
+    pub kb_brightness: Option<u8>,
     pub usb_charging: bool,
     pub battery_charge_limit: bool,
     pub voltage_info: VoltageInfo,
     pub undervolt_status: String,
+    /// `false` when the CPU vendor is unknown and voltage control is off.
+    pub undervolt_supported: bool,
+    /// Manual fan speeds in percent.
     pub cpu_manual_level: u8,
     pub gpu_manual_level: u8,
+    /// Lowest manual fan speed the daemon allows, in percent.
+    pub manual_fan_floor: u8,
     pub tdp_value: u32,
     pub power_profile: PowerProfile,
+    /// CoolBoost state (always `false` unless `caps.coolboost`).
+    pub coolboost: bool,
+    /// Super key disabled (always `false` unless `caps.win_key_lock`).
+    pub win_key_lock: bool,
+    /// Touchpad disabled (always `false` unless `caps.touchpad_lock`).
+    pub touchpad_lock: bool,
+    /// Keyboard follows the performance mode's colour (opt-in).
+    pub mode_lighting: bool,
+    /// Colour the keyboard is tinted for the current mode; `None` while
+    /// mode lighting is off or other lighting was applied since.
+    pub mode_tint: Option<Rgb>,
+    /// Advertised wattage of the connected adapter, when the kernel exposes it.
+    pub adapter_watts: Option<u16>,
+    /// Adapter is weaker than the one the model ships with.
+    pub adapter_underpowered: bool,
+    /// Battery charge cycles, when the firmware reports them.
+    pub battery_cycles: Option<u32>,
+    /// Full capacity relative to design capacity.
+    pub battery_health_pct: Option<u8>,
+    /// Watts into or out of the battery, when the kernel reports it.
+    pub battery_power_w: Option<f64>,
+    /// Minutes until empty (discharging) or full (charging).
+    pub battery_time_remaining_min: Option<u32>,
+    /// Optional features of the detected model.
+    pub caps: ModelCaps,
+}
+
+/// Groups of [`EcData`] fields that can be fetched on their own with
+/// [`Request::GetStatusFields`].
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+pub enum StatusField {
+    Temps,
+    Fans,
+    Modes,
+    Power,
+    Voltage,
+    Keyboard,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TempStatus {
+    pub cpu_temp: u8,
+    pub gpu_temp: u8,
+    pub sys_temp: u8,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FanStatus {
+    pub cpu_fan_speed: u16,
+    pub gpu_fan_speed: u16,
+    pub cpu_fan_duty: Option<u8>,
+    pub gpu_fan_duty: Option<u8>,
+    /// See [`EcData::gpu_fan_percent`].
+    pub gpu_fan_percent: Option<u8>,
+    /// Manual fan speeds in percent.
+    pub cpu_manual_level: u8,
+    pub gpu_manual_level: u8,
+    /// Lowest manual fan speed the daemon allows, in percent.
+    pub manual_fan_floor: u8,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ModeStatus {
+    pub cpu_mode: FanMode,
+    pub gpu_mode: FanMode,
+    pub nitro_mode: NitroMode,
+    pub coolboost: bool,
+    pub tdp_value: u32,
+    pub power_profile: PowerProfile,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PowerStatus {
+    pub power_plugged_in: bool,
+    pub battery_status: BatteryStatus,
+    pub battery_charge_limit: bool,
+    pub usb_charging: bool,
+    pub adapter_watts: Option<u16>,
+    pub adapter_underpowered: bool,
+    pub battery_cycles: Option<u32>,
+    pub battery_health_pct: Option<u8>,
+    pub battery_power_w: Option<f64>,
+    pub battery_time_remaining_min: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct KeyboardStatus {
+    pub kb_timeout: bool,
+    pub kb_brightness: Option<u8>,
+    pub win_key_lock: bool,
+    pub touchpad_lock: bool,
+    /// Keyboard follows the performance mode's colour (opt-in).
+    pub mode_lighting: bool,
+    /// Colour the keyboard is tinted for the current mode; `None` while
+    /// mode lighting is off or other lighting was applied since.
+    pub mode_tint: Option<Rgb>,
+}
+
+/// Reply to [`Request::GetStatusFields`]; groups that were not asked for
+/// are `None`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct StatusFields {
+    pub temps: Option<TempStatus>,
+    pub fans: Option<FanStatus>,
+    pub modes: Option<ModeStatus>,
+    pub power: Option<PowerStatus>,
+    pub voltage: Option<VoltageInfo>,
+    pub keyboard: Option<KeyboardStatus>,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
@@ -32,6 +162,9 @@ pub enum FanMode {
     Auto,
     Turbo,
     Manual,
+    /// Fan stops while cool (firmware fan-stop, or Manual 0% with the
+    /// thermal override as the backstop).
+    Passive,
     Unknown(u8),
 }
 
@@ -40,9 +173,23 @@ pub enum NitroMode {
     Quiet,
     Default,
     Extreme,
+    Turbo,
     Unknown(u8),
 }
 
+impl NitroMode {
+    /// Parse a user-facing mode name, case-insensitively.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "quiet" => Some(NitroMode::Quiet),
+            "default" => Some(NitroMode::Default),
+            "extreme" => Some(NitroMode::Extreme),
+            "turbo" => Some(NitroMode::Turbo),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
 pub enum BatteryStatus {
     Charging,
@@ -77,26 +224,484 @@ impl PowerProfile {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub enum Request {
-    GetStatus,
+    // `force` bypasses the daemon's EC read cache
+    #[serde(serialize_with = "status_args::serialize", deserialize_with = "status_args::deserialize")]
+    GetStatus { force: bool },
+    GetStatusFields(Vec<StatusField>), // Only the listed groups; cheaper than GetStatus
     SetCpuFanMode(FanMode),
     SetGpuFanMode(FanMode),
-    SetCpuFanSpeed(u8),
+    SetCpuFanSpeed(u8),                // Percent, 0-100, Manual mode only; the daemon scales it for the EC
     SetGpuFanSpeed(u8),
+    SetManualFanFloor(u8),             // Percent; manual speeds below it are raised
     SetNitroMode(NitroMode),
     SetKbTimeout(bool),
     SetUsbCharging(bool),
     SetBatteryLimit(bool),
     SetKeyboardColor(u8, u8, u8, u8), // zone, r, g, b
+    SetRgbConfig(RgbConfig),           // Whole keyboard state; the daemon saves it
+    GetRgbConfig,
+    ResetKeyboard,                     // Factory lighting; clears per-zone colours
+    SetModeLighting(bool),             // Tint the keyboard by performance mode
     ApplyUndervolt(usize),
     SetTdp(u32),                       // TDP in milliwatts
     SetPowerProfile(PowerProfile),     // Preset profile (also sets TDP)
+    HardwareReset,                     // Reload EC driver, restore safe defaults
+    ReloadEc,                          // Reload EC driver only; settings untouched
+    SetCoolBoost(bool),
+    SetWinKeyLock(bool),
+    SetTouchpadLock(bool),
+    SetAppRules(Vec<(String, String)>), // (executable name, Nitro mode name)
+    GetVoltage,
+    ResetVoltageStats,                 // Restart min/max voltage tracking
+    Undo,                              // Revert the most recent EC write
+    GetThermalZones,                   // Extra sysfs temperature sensors
+    GetSystemInfo,
+    GetEcInfo,                         // Active EC interface and its startup write check
+    GetRegisterMap,                    // Active register map and capabilities
+    DumpEc,                            // Raw EC contents, for reverse-engineering
+    SetClientName(String),             // Names this connection in change notices
+    GetEvents(u64),                    // Other clients' changes after this sequence number
+    SelfTest,                          // Read (never write) every mapped register
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub enum Response {
     Status(EcData),
+    StatusFields(StatusFields),
+    Voltage(VoltageInfo),
+    ThermalZones(Vec<ThermalZone>),
+    SystemInfo(SystemInfo),
+    EcInfo(EcInfo),
+    RegisterMap(ModelMap),
+    EcDump(Vec<u8>),
+    Events(u64, Vec<String>),          // Latest sequence number, change descriptions
+    SelfTest(Vec<RegisterCheck>),
+    RgbConfig(RgbConfig),
     Ok,
     Error(String),
 }
+
+/// Static facts about the machine the daemon runs on.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SystemInfo {
+    pub model: String,
+    /// Active kernel lockdown mode; EC writes are blocked when set.
+    pub kernel_lockdown: Option<String>,
+    /// The RGB keyboard driver's device node exists.
+    pub keyboard_device: bool,
+    /// `amdctl` (AMD) or `rdmsr` plus the `msr` module (Intel) is available.
+    pub cpu_tooling: bool,
+    pub cpu: CpuType,
+    /// Where `cpu` was detected from, or why detection failed (e.g.
+    /// `/proc/cpuinfo` unreadable versus an unrecognised vendor).
+    #[serde(default)]
+    pub cpu_detail: String,
+    /// Directory the daemon loads and saves its settings in.
+    #[serde(default)]
+    pub config_dir: String,
+    /// Optional features of the detected model, so clients can build their
+    /// controls before the first status poll.
+    #[serde(default)]
+    pub caps: ModelCaps,
+}
+
+/// One register read by [`Request::SelfTest`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RegisterCheck {
+    /// `EcRegisters` field name.
+    pub name: String,
+    pub address: u8,
+    /// `None` when the read failed.
+    pub value: Option<u8>,
+    /// Why the read failed or the value looks wrong; `None` when it's fine.
+    pub problem: Option<String>,
+}
+
+/// Low-level EC interface health.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EcInfo {
+    /// Device file in use (`/sys/kernel/debug/ec/ec0/io`, `/dev/ec` or `/dev/port`).
+    pub interface: String,
+    /// A test write through `interface` reached the EC when it was opened.
+    pub writes_verified: bool,
+    /// Bytes read by the last EC refresh.
+    pub buffer_len: usize,
+    /// Backends tried at startup, in order, with whether writes through
+    /// them reached the EC.  A later writable entry means the earlier ones
+    /// were skipped.
+    #[serde(default)]
+    pub probed: Vec<(String, bool)>,
+}
+
+/// Wraps a [`Request`] or [`Response`] with a per-connection sequence id.
+///
+/// `GetStatus` used to be a unit variant, sent as `{"GetStatus":null}`
+/// inside an envelope.  Its arguments are optional so those requests still
+/// parse, as a plain cached read.
+mod status_args {
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Default)]
+    #[serde(default)]
+    struct StatusArgs {
+        force: bool,
+    }
+
+    pub fn serialize<S: Serializer>(force: &bool, s: S) -> Result<S::Ok, S::Error> {
+        Some(StatusArgs { force: *force }).serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<bool, D::Error> {
+        Ok(Option::<StatusArgs>::deserialize(d)?.unwrap_or_default().force)
+    }
+}
+
+/// Bare requests from clients older than the envelope.  They send unit
+/// variants as plain strings, which only old shapes can match.
+#[derive(Deserialize)]
+enum LegacyRequest {
+    GetStatus,
+}
+
+/// Parse a bare (unenveloped) request, accepting the old `"GetStatus"`.
+pub fn parse_bare_request(line: &str) -> serde_json::Result<Request> {
+    serde_json::from_str(line).or_else(|e| match serde_json::from_str(line) {
+        Ok(LegacyRequest::GetStatus) => Ok(Request::GetStatus { force: false }),
+        Err(_) => Err(e),
+    })
+}
+
+/// The client numbers each request and the daemon echoes the id back, so a
+/// response that belongs to a different request is detected instead of being
+/// silently accepted.  An id of 0 means "untagged": it is omitted on the wire
+/// and peers fall back to the bare message format.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct Envelope<T> {
+    #[serde(default, skip_serializing_if = "is_untagged")]
+    pub id: u64,
+    #[serde(flatten)]
+    pub body: T,
+}
+
+fn is_untagged(id: &u64) -> bool {
+    *id == 0
+}
+
+/// First byte a client sends to switch its connection from JSON lines to
+/// binary frames.  No JSON message starts with it; the daemon echoes it
+/// back to accept.
+pub const BINARY_HANDSHAKE: u8 = 0xB1;
+
+/// Largest binary frame accepted.  A bigger length prefix means the
+/// stream is out of step.
+const MAX_FRAME_LEN: usize = 1 << 20;
+
+/// Wire format of a connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+    /// Newline-delimited JSON [`Envelope`]s, easy to poke at with `socat`.
+    #[default]
+    Json,
+    /// Bincode `(id, message)` pairs, each behind a little-endian `u32`
+    /// length.  A tuple rather than an `Envelope`, whose flattening only
+    /// works in self-describing formats.
+    Binary,
+}
+
+fn invalid_data(e: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+/// Write one binary frame.
+pub fn write_frame<W: Write, T: Serialize>(w: &mut W, id: u64, body: &T) -> io::Result<()> {
+    let payload = bincode::serialize(&(id, body)).map_err(invalid_data)?;
+    if payload.len() > MAX_FRAME_LEN {
+        return Err(invalid_data(format!("{}-byte frame exceeds the limit", payload.len())));
+    }
+    w.write_all(&(payload.len() as u32).to_le_bytes())?;
+    w.write_all(&payload)?;
+    w.flush()
+}
+
+/// Read one binary frame; `Ok(None)` when the peer hung up between frames.
+pub fn read_frame<R: Read, T: DeserializeOwned>(r: &mut R) -> io::Result<Option<(u64, T)>> {
+    let mut len = [0u8; 4];
+    match r.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let len = u32::from_le_bytes(len) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(invalid_data(format!("{len}-byte frame exceeds the limit")));
+    }
+    let mut payload = vec![0u8; len];
+    r.read_exact(&mut payload)?;
+    bincode::deserialize(&payload).map(Some).map_err(invalid_data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::device_regs::{CAPS_AN515_44, CAPS_AN515_46, CAPS_PH315_54, ECS_PH315_54};
+    use crate::utils::keyboard::{KeyboardLayout, Rgb};
+
+    fn sample_status() -> EcData {
+        EcData {
+            model: "Nitro AN515-46".into(),
+            cpu_temp: 55,
+            gpu_temp: 48,
+            sys_temp: 40,
+            cpu_fan_speed: 2400,
+            gpu_fan_speed: 0,
+            cpu_fan_duty: Some(45),
+            gpu_fan_duty: None,
+            gpu_fan_percent: Some(38),
+            power_plugged_in: true,
+            battery_status: BatteryStatus::Unknown(0x7F),
+            cpu_mode: FanMode::Manual,
+            gpu_mode: FanMode::Unknown(0x42),
+            nitro_mode: NitroMode::Unknown(0x09),
+            kb_timeout: true,
+            kb_brightness: Some(60),
+            usb_charging: false,
+            battery_charge_limit: true,
+            voltage_info: VoltageInfo { voltage: 1.1, min_recorded: 0.8, max_recorded: 1.3 },
+            undervolt_status: "P0\t1.2V\nP1\t1.0V\nP2\t0.9V".into(),
+            undervolt_supported: true,
+            cpu_manual_level: 50,
+            gpu_manual_level: 100,
+            manual_fan_floor: 20,
+            tdp_value: 25_000,
+            power_profile: PowerProfile::Balanced,
+            coolboost: false,
+            win_key_lock: true,
+            touchpad_lock: false,
+            mode_lighting: true,
+            mode_tint: Some(Rgb { r: 255, g: 0, b: 0 }),
+            adapter_watts: Some(180),
+            adapter_underpowered: false,
+            battery_cycles: None,
+            battery_health_pct: Some(87),
+            battery_power_w: Some(42.5),
+            battery_time_remaining_min: None,
+            caps: ModelCaps {
+                independent_gpu_fan: false,
+                turbo_mode: true,
+                fan_stop: false,
+                coolboost: false,
+                win_key_lock: true,
+                touchpad_lock: true,
+                rated_adapter_watts: 135,
+                tdp_watts: 45,
+                max_temp: 105,
+                keyboard: KeyboardLayout::SingleZone,
+                logo_zone: false,
+                kb_brightness_readback: true,
+                fan_duty_readback: true,
+                fan_step_percent: 10,
+                fan_max_percent: 100,
+                fan_raw_max: 100,
+                max_fan_rpm: 5000,
+                gpu_fan_from_nvidia: true,
+                experimental: true,
+            },
+        }
+    }
+
+    // Keep in sync with `Request` – one entry per variant.
+    fn all_requests() -> Vec<Request> {
+        vec![
+            Request::GetStatus { force: true },
+            Request::GetStatusFields(vec![StatusField::Temps, StatusField::Voltage]),
+            Request::SetCpuFanMode(FanMode::Auto),
+            Request::SetGpuFanMode(FanMode::Unknown(3)),
+            Request::SetGpuFanMode(FanMode::Passive),
+            Request::SetCpuFanSpeed(40),
+            Request::SetGpuFanSpeed(100),
+            Request::SetManualFanFloor(20),
+            Request::SetNitroMode(NitroMode::Extreme),
+            Request::SetNitroMode(NitroMode::Turbo),
+            Request::SetKbTimeout(true),
+            Request::SetUsbCharging(false),
+            Request::SetBatteryLimit(true),
+            Request::SetKeyboardColor(2, 255, 0, 128),
+            Request::SetRgbConfig(RgbConfig {
+                mode: 3,
+                speed: 5,
+                gradient_end: Some(Rgb { r: 0, g: 0, b: 255 }),
+                ..RgbConfig::default()
+            }),
+            Request::SetRgbConfig(RgbConfig {
+                mode: 1,
+                breathing_colors: vec![Rgb { r: 255, g: 0, b: 0 }, Rgb { r: 0, g: 255, b: 0 }],
+                software_effects: true,
+                ..RgbConfig::default()
+            }),
+            Request::GetRgbConfig,
+            Request::ResetKeyboard,
+            Request::SetModeLighting(true),
+            Request::ApplyUndervolt(1),
+            Request::SetTdp(35_000),
+            Request::SetPowerProfile(PowerProfile::MaxPerformance),
+            Request::HardwareReset,
+            Request::ReloadEc,
+            Request::SetCoolBoost(true),
+            Request::SetWinKeyLock(false),
+            Request::SetTouchpadLock(true),
+            Request::SetAppRules(vec![("steam".into(), "Extreme".into())]),
+            Request::GetVoltage,
+            Request::ResetVoltageStats,
+            Request::Undo,
+            Request::GetThermalZones,
+            Request::GetSystemInfo,
+            Request::GetEcInfo,
+            Request::GetRegisterMap,
+            Request::DumpEc,
+            Request::SetClientName("CLI".into()),
+            Request::GetEvents(7),
+            Request::SelfTest,
+        ]
+    }
+
+    // Keep in sync with `Response` – one entry per variant.
+    fn all_responses() -> Vec<Response> {
+        vec![
+            Response::Status(sample_status()),
+            Response::StatusFields(StatusFields {
+                temps: Some(TempStatus { cpu_temp: 55, gpu_temp: 48, sys_temp: 40 }),
+                voltage: Some(VoltageInfo::default()),
+                ..StatusFields::default()
+            }),
+            Response::Voltage(VoltageInfo::default()),
+            Response::ThermalZones(vec![ThermalZone { name: "nvme Composite".into(), temp_c: 41.85 }]),
+            Response::SystemInfo(SystemInfo {
+                model: "Nitro AN515-46".into(),
+                kernel_lockdown: Some("integrity".into()),
+                keyboard_device: true,
+                cpu_tooling: false,
+                cpu: CpuType::Amd,
+                cpu_detail: "from /proc/cpuinfo".into(),
+                config_dir: "/var/lib/nitrosense".into(),
+                caps: CAPS_AN515_46,
+            }),
+            Response::EcInfo(EcInfo {
+                interface: "/dev/ec".into(),
+                writes_verified: true,
+                buffer_len: 256,
+                probed: vec![
+                    ("/sys/kernel/debug/ec/ec0/io".into(), false),
+                    ("/dev/ec".into(), true),
+                ],
+            }),
+            Response::RegisterMap(ModelMap {
+                model: "Predator PH315-54".into(),
+                registers: ECS_PH315_54,
+                caps: sample_status().caps,
+            }),
+            Response::EcDump((0..=255).collect()),
+            Response::SelfTest(vec![
+                RegisterCheck { name: "cpu_temp".into(), address: 0xB0, value: Some(52), problem: None },
+                RegisterCheck {
+                    name: "nitro_mode".into(),
+                    address: 0x2C,
+                    value: None,
+                    problem: Some("read failed: Input/output error".into()),
+                },
+            ]),
+            Response::Events(9, vec!["Nitro mode → Extreme by CLI".into()]),
+            Response::RgbConfig(RgbConfig::default()),
+            Response::Ok,
+            Response::Error("line one\nline two".into()),
+        ]
+    }
+
+    fn assert_round_trip<T>(value: &T)
+    where
+        T: Serialize + for<'de> Deserialize<'de> + PartialEq + std::fmt::Debug,
+    {
+        let encoded = serde_json::to_string(value).unwrap();
+        assert!(!encoded.contains('\n'), "newline breaks framing: {encoded}");
+        let decoded: T = serde_json::from_str(&encoded).unwrap();
+        assert_eq!(&decoded, value);
+    }
+
+    #[test]
+    fn requests_round_trip() {
+        for req in all_requests() {
+            assert_round_trip(&req);
+        }
+    }
+
+    #[test]
+    fn responses_round_trip() {
+        for resp in all_responses() {
+            assert_round_trip(&resp);
+        }
+    }
+
+    #[test]
+    fn envelopes_round_trip() {
+        for (id, body) in all_requests().into_iter().enumerate() {
+            assert_round_trip(&Envelope { id: id as u64 + 1, body });
+        }
+        for (id, body) in all_responses().into_iter().enumerate() {
+            assert_round_trip(&Envelope { id: id as u64 + 1, body });
+        }
+    }
+
+    #[test]
+    fn binary_frames_round_trip() {
+        let mut wire = Vec::new();
+        for (id, req) in all_requests().iter().enumerate() {
+            write_frame(&mut wire, id as u64 + 1, req).unwrap();
+        }
+        for resp in &all_responses() {
+            write_frame(&mut wire, 7, resp).unwrap();
+        }
+        let mut r = wire.as_slice();
+        for (id, req) in all_requests().into_iter().enumerate() {
+            assert_eq!(read_frame::<_, Request>(&mut r).unwrap(), Some((id as u64 + 1, req)));
+        }
+        for resp in all_responses() {
+            assert_eq!(read_frame::<_, Response>(&mut r).unwrap(), Some((7, resp)));
+        }
+        assert_eq!(read_frame::<_, Response>(&mut r).unwrap(), None);
+    }
+
+    #[test]
+    fn old_get_status_still_parses() {
+        let env: Envelope<Request> = serde_json::from_str(r#"{"id":3,"GetStatus":null}"#).unwrap();
+        assert_eq!(env, Envelope { id: 3, body: Request::GetStatus { force: false } });
+        assert_eq!(parse_bare_request(r#""GetStatus""#).unwrap(), Request::GetStatus { force: false });
+        assert_eq!(parse_bare_request(r#"{"GetStatus":{}}"#).unwrap(), Request::GetStatus { force: false });
+        assert!(parse_bare_request(r#""GetSomething""#).is_err());
+    }
+
+    #[test]
+    fn untagged_request_parses_as_id_zero() {
+        let env: Envelope<Request> = serde_json::from_str(r#"{"SetTdp":15000}"#).unwrap();
+        assert_eq!(env, Envelope { id: 0, body: Request::SetTdp(15_000) });
+    }
+
+    // Slider percent -> request -> register -> readback -> displayed percent.
+    #[test]
+    fn fan_percent_survives_register_round_trip() {
+        let byte_scale = ModelCaps { fan_raw_max: 255, ..CAPS_AN515_46 };
+        for caps in [CAPS_AN515_44, CAPS_AN515_46, CAPS_PH315_54, byte_scale] {
+            for percent in (0..=caps.fan_max_percent).step_by(caps.fan_step_percent.max(1) as usize) {
+                let json = serde_json::to_string(&Request::SetCpuFanSpeed(percent)).unwrap();
+                let Request::SetCpuFanSpeed(sent) = serde_json::from_str(&json).unwrap() else {
+                    panic!("SetCpuFanSpeed did not survive encoding");
+                };
+                let raw = caps.fan_percent_to_raw(sent);
+                assert_eq!(caps.fan_raw_to_percent(raw), percent, "{percent}% went through raw {raw}");
+            }
+            // Off-step values land on a step and then stay there.
+            let raw = caps.fan_percent_to_raw(33);
+            assert_eq!(caps.fan_percent_to_raw(caps.fan_raw_to_percent(raw)), raw);
+        }
+    }
+}
@@ -0,0 +1,117 @@
+//! Developer panel for reverse-engineering registers (`NITROSENSE_DEV=1`).
+//!
+//! Shows the raw 256-byte EC space from [`Request::DumpEc`] as a hex grid
+//! and highlights every byte that changed since the previous poll, so
+//! flipping a setting shows which register moved.
+
+use std::cell::RefCell;
+use std::env;
+use std::rc::Rc;
+use std::time::Duration;
+
+use gtk4::prelude::*;
+use gtk4::{glib, Align, Box as GtkBox, Grid, Label, Orientation};
+
+use crate::protocol::{Request, Response};
+use crate::ui::gui::AppState;
+
+/// Set to anything but `0` to add the panel as an extra tab.
+const ENV_VAR: &str = "NITROSENSE_DEV";
+
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+const EC_SIZE: usize = 256;
+
+pub fn enabled() -> bool {
+    env::var_os(ENV_VAR).is_some_and(|v| !v.is_empty() && v != "0")
+}
+
+pub fn build(state: &Rc<RefCell<AppState>>) -> GtkBox {
+    let container = GtkBox::new(Orientation::Vertical, 8);
+
+    let summary = Label::new(Some("Waiting for the first dump…"));
+    summary.add_css_class("label-secondary");
+    summary.set_halign(Align::Start);
+    container.append(&summary);
+
+    let grid = Grid::new();
+    grid.set_column_spacing(6);
+    grid.set_row_spacing(2);
+    grid.add_css_class("ec-grid");
+    for col in 0..16 {
+        let head = Label::new(Some(&format!("{col:X}")));
+        head.add_css_class("label-secondary");
+        grid.attach(&head, col as i32 + 1, 0, 1, 1);
+    }
+    let mut cells = Vec::with_capacity(EC_SIZE);
+    for row in 0..16 {
+        let head = Label::new(Some(&format!("{:02X}", row * 16)));
+        head.add_css_class("label-secondary");
+        grid.attach(&head, 0, row as i32 + 1, 1, 1);
+        for col in 0..16 {
+            let cell = Label::new(Some("--"));
+            cell.set_tooltip_text(Some(&format!("0x{:02X}", row * 16 + col)));
+            grid.attach(&cell, col as i32 + 1, row as i32 + 1, 1, 1);
+            cells.push(cell);
+        }
+    }
+    container.append(&grid);
+
+    let previous: RefCell<Option<Vec<u8>>> = RefCell::new(None);
+    let st = Rc::clone(state);
+    let weak = container.downgrade();
+    glib::timeout_add_local(POLL_INTERVAL, move || {
+        let Some(container) = weak.upgrade() else {
+            return glib::ControlFlow::Break;
+        };
+        // Only poll while the tab is on screen; coming back starts a fresh
+        // comparison instead of flagging everything that moved meanwhile.
+        if !container.is_mapped() {
+            previous.borrow_mut().take();
+            return glib::ControlFlow::Continue;
+        }
+        // Skip a tick if a handler is busy with the state.
+        let Ok(mut s) = st.try_borrow_mut() else {
+            return glib::ControlFlow::Continue;
+        };
+        let bytes = match s.client.send(Request::DumpEc) {
+            Ok(Response::EcDump(bytes)) => bytes,
+            Ok(Response::Error(e)) => {
+                summary.set_label(&format!("Dump failed: {e}"));
+                return glib::ControlFlow::Continue;
+            }
+            Ok(other) => {
+                summary.set_label(&format!("Unexpected response: {other:?}"));
+                return glib::ControlFlow::Continue;
+            }
+            Err(e) => {
+                summary.set_label(&format!("IPC error: {e}"));
+                return glib::ControlFlow::Continue;
+            }
+        };
+        drop(s);
+
+        let mut prev = previous.borrow_mut();
+        let mut changed = Vec::new();
+        for (addr, cell) in cells.iter().enumerate() {
+            let value = bytes.get(addr).copied();
+            let old = prev.as_ref().and_then(|p| p.get(addr).copied());
+            cell.set_label(&value.map_or_else(|| String::from("--"), |v| format!("{v:02X}")));
+            if prev.is_some() && value != old {
+                cell.add_css_class("ec-changed");
+                changed.push(format!("0x{addr:02X}"));
+            } else {
+                cell.remove_css_class("ec-changed");
+            }
+        }
+        summary.set_label(&if changed.is_empty() {
+            format!("{} bytes, no changes since the last poll", bytes.len())
+        } else {
+            format!("{} bytes, changed: {}", bytes.len(), changed.join(" "))
+        });
+        *prev = Some(bytes);
+        glib::ControlFlow::Continue
+    });
+
+    container
+}
@@ -0,0 +1,231 @@
+//! Fan response check: switches the CPU fan to Turbo or Auto, samples its
+//! RPM until the reading settles, then plots the transient and reports how
+//! long the fan took.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use gtk4::prelude::*;
+use gtk4::{glib, Align, Box as GtkBox, Button, DrawingArea, DropDown, Label, Orientation, StringList};
+
+use crate::protocol::{FanMode, Request, Response, StatusField};
+use crate::ui::gui::AppState;
+
+/// The daemon's default EC cache age; sampling faster only repeats readings.
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Give up on a fan that never settles.
+const MAX_DURATION: Duration = Duration::from_secs(20);
+
+/// The reading counts as settled once every sample in the last
+/// `SETTLE_WINDOW` stays within `SETTLE_BAND` of their mean, or within
+/// `SETTLE_MIN_RPM` for a fan that is (nearly) stopped.
+const SETTLE_WINDOW: Duration = Duration::from_secs(3);
+const SETTLE_BAND: f64 = 0.05;
+const SETTLE_MIN_RPM: f64 = 100.0;
+
+/// Steps on offer and the CPU fan mode each one switches to.
+const STEPS: [(&str, FanMode); 2] = [("Spin up (Turbo)", FanMode::Turbo), ("Spin down (Auto)", FanMode::Auto)];
+
+fn band(level: f64) -> f64 {
+    (level * SETTLE_BAND).max(SETTLE_MIN_RPM)
+}
+
+/// RPM samples, in seconds since the change was sent.
+#[derive(Default)]
+struct Trace {
+    samples: Vec<(f64, u16)>,
+    /// Settling time, once known.
+    settled_at: Option<f64>,
+}
+
+impl Trace {
+    /// Mean RPM of the trailing window, once the trace covers a whole
+    /// window and every sample in it is inside the band.
+    fn settled_level(&self) -> Option<f64> {
+        let &(last, _) = self.samples.last()?;
+        let window = SETTLE_WINDOW.as_secs_f64();
+        if last < window {
+            return None;
+        }
+        let recent: Vec<f64> =
+            self.samples.iter().filter(|(t, _)| *t >= last - window).map(|&(_, rpm)| rpm as f64).collect();
+        let mean = recent.iter().sum::<f64>() / recent.len() as f64;
+        recent.iter().all(|rpm| (rpm - mean).abs() <= band(mean)).then_some(mean)
+    }
+
+    /// When the RPM entered the band around `level` for good.
+    fn settling_time(&self, level: f64) -> f64 {
+        self.samples
+            .iter()
+            .rev()
+            .take_while(|(_, rpm)| (*rpm as f64 - level).abs() <= band(level))
+            .last()
+            .map_or(0.0, |&(t, _)| t)
+    }
+
+    /// When the RPM first left the band around where it started.
+    fn response_time(&self) -> Option<f64> {
+        let &(_, start) = self.samples.first()?;
+        self.samples.iter().find(|(_, rpm)| (*rpm as f64 - start as f64).abs() > band(start as f64)).map(|&(t, _)| t)
+    }
+
+    fn summary(&self, level: Option<f64>) -> String {
+        let start = self.samples.first().map_or(0, |s| s.1);
+        let mut text = format!("Started at {start} RPM");
+        match self.response_time() {
+            Some(t) => text.push_str(&format!(", first moved after {t:.1} s")),
+            None => text.push_str(", never moved"),
+        }
+        match (level, self.settled_at) {
+            (Some(level), Some(t)) => text.push_str(&format!(", settled at {level:.0} RPM after {t:.1} s")),
+            _ => text.push_str(&format!(", not settled within {} s", MAX_DURATION.as_secs())),
+        }
+        text
+    }
+}
+
+/// Requests that put the CPU fan back the way it was.
+fn restore_requests(s: &AppState) -> Vec<Request> {
+    match s.cpu_mode {
+        FanMode::Manual => vec![Request::SetCpuFanMode(FanMode::Manual), Request::SetCpuFanSpeed(s.cpu_manual_level)],
+        FanMode::Unknown(_) => vec![Request::SetCpuFanMode(FanMode::Auto)],
+        mode => vec![Request::SetCpuFanMode(mode)],
+    }
+}
+
+fn restore(s: &mut AppState, requests: Vec<Request>) {
+    for req in requests {
+        if let Err(e) = s.client.send(req) {
+            eprintln!("IPC error: {}", e);
+        }
+    }
+}
+
+/// A measurement in progress.
+struct Run {
+    started: Instant,
+    restore: Vec<Request>,
+}
+
+pub fn build(state: &Rc<RefCell<AppState>>) -> GtkBox {
+    let card = GtkBox::new(Orientation::Vertical, 12);
+    card.add_css_class("card");
+
+    let title = Label::new(Some("FAN RESPONSE"));
+    title.add_css_class("section-title");
+    title.set_halign(Align::Start);
+    card.append(&title);
+
+    let step_dd = DropDown::new(Some(StringList::new(&STEPS.map(|(name, _)| name))), gtk4::Expression::NONE);
+    let button = Button::with_label("Measure CPU fan");
+    let controls = GtkBox::new(Orientation::Horizontal, 12);
+    controls.append(&step_dd);
+    controls.append(&button);
+    card.append(&controls);
+
+    let trace = Rc::new(RefCell::new(Trace::default()));
+    let plot = DrawingArea::new();
+    plot.set_content_height(80);
+    plot.set_hexpand(true);
+    {
+        let trace = Rc::clone(&trace);
+        plot.set_draw_func(move |_, cr, w, h| {
+            let trace = trace.borrow();
+            let Some(&(end, _)) = trace.samples.last() else { return };
+            let top = trace.samples.iter().map(|s| s.1).max().unwrap_or(0).max(1) as f64;
+            let (w, h) = (w as f64, h as f64);
+            let x = |t: f64| t / end.max(1.0) * w;
+            let y = |rpm: u16| h - 2.0 - rpm as f64 / top * (h - 4.0);
+            cr.set_line_width(2.0);
+            cr.set_source_rgb(0.35, 0.65, 1.0);
+            for (i, &(t, rpm)) in trace.samples.iter().enumerate() {
+                if i == 0 {
+                    cr.move_to(x(t), y(rpm));
+                } else {
+                    cr.line_to(x(t), y(rpm));
+                }
+            }
+            let _ = cr.stroke();
+            if let Some(t) = trace.settled_at {
+                cr.set_line_width(1.0);
+                cr.set_source_rgb(0.5, 0.5, 0.5);
+                cr.move_to(x(t), 0.0);
+                cr.line_to(x(t), h);
+                let _ = cr.stroke();
+            }
+        });
+    }
+    card.append(&plot);
+
+    let result = Label::new(Some("Switches the CPU fan, records its RPM until it settles, then puts it back."));
+    result.add_css_class("label-secondary");
+    result.set_halign(Align::Start);
+    result.set_xalign(0.0);
+    result.set_wrap(true);
+    card.append(&result);
+
+    let st = Rc::clone(state);
+    let weak = card.downgrade();
+    let (label, plot, dd) = (result.clone(), plot.clone(), step_dd.clone());
+    button.connect_clicked(move |btn| {
+        let Ok(mut s) = st.try_borrow_mut() else { return };
+        let (name, target) = STEPS[dd.selected() as usize % STEPS.len()];
+        if s.cpu_mode == target {
+            label.set_label(&format!("The CPU fan is already on {target:?}; pick the other step."));
+            return;
+        }
+        let mut run = Run { started: Instant::now(), restore: restore_requests(&s) };
+        match s.client.send(Request::SetCpuFanMode(target)) {
+            Ok(Response::Error(e)) => {
+                label.set_label(&format!("Fan change refused: {e}"));
+                return;
+            }
+            Err(e) => {
+                label.set_label(&format!("IPC error: {e}"));
+                return;
+            }
+            Ok(_) => {}
+        }
+        drop(s);
+        *trace.borrow_mut() = Trace::default();
+        btn.set_sensitive(false);
+        label.set_label(&format!("{name}: sampling…"));
+
+        let (st, weak, label, plot, btn, trace) =
+            (Rc::clone(&st), weak.clone(), label.clone(), plot.clone(), btn.clone(), Rc::clone(&trace));
+        glib::timeout_add_local(SAMPLE_INTERVAL, move || {
+            // Skip a tick if a handler is busy with the state.
+            let Ok(mut s) = st.try_borrow_mut() else {
+                return glib::ControlFlow::Continue;
+            };
+            // Window closed mid-run: still hand the fan back.
+            if weak.upgrade().is_none() {
+                restore(&mut s, std::mem::take(&mut run.restore));
+                return glib::ControlFlow::Break;
+            }
+            let elapsed = run.started.elapsed();
+            if let Ok(Response::StatusFields(fields)) = s.client.send(Request::GetStatusFields(vec![StatusField::Fans])) {
+                if let Some(fans) = fields.fans {
+                    trace.borrow_mut().samples.push((elapsed.as_secs_f64(), fans.cpu_fan_speed));
+                }
+            }
+            let level = trace.borrow().settled_level();
+            if level.is_none() && elapsed < MAX_DURATION {
+                plot.queue_draw();
+                return glib::ControlFlow::Continue;
+            }
+            restore(&mut s, std::mem::take(&mut run.restore));
+            let mut t = trace.borrow_mut();
+            let settled_at = level.map(|l| t.settling_time(l));
+            t.settled_at = settled_at;
+            label.set_label(&t.summary(level));
+            plot.queue_draw();
+            btn.set_sensitive(true);
+            glib::ControlFlow::Break
+        });
+    });
+
+    card
+}
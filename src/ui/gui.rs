@@ -7,19 +7,24 @@ use gtk4::gdk;
 use gtk4::glib;
 use gtk4::prelude::*;
 use gtk4::{
-    Align, Box as GtkBox, Button, CheckButton, ColorButton, CssProvider, DropDown,
-    Entry, Frame, Grid, Label, LevelBar, Orientation, Scale, Stack, StackSwitcher,
-    StringList, StyleContext, TextView, Window, Adjustment,
+    Align, Box as GtkBox, Button, CallbackAction, CheckButton, ColorButton, CssProvider, DrawingArea,
+    DropDown, Entry, Expander, Frame, Grid, Label, LevelBar, Orientation, Scale, Shortcut,
+    ShortcutController, ShortcutTrigger, SpinButton, Stack, StackSwitcher, StringList, StyleContext,
+    TextView, Window, Adjustment,
 };
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
+use std::time::{Duration, Instant};
 
-use crate::client::Client;
-use crate::config::{NitroConfig, RgbConfig, TdpConfig};
+use crate::client::{self, Client};
+use crate::config::{ClientConfig, NitroConfig, RgbConfig, TdpConfig, TempUnit, Theme};
 use crate::core::cpu_ctl::VoltageInfo;
-use crate::protocol::{BatteryStatus, EcData, FanMode, NitroMode, PowerProfile, Request, Response};
-use crate::utils::keyboard::{self, Rgb};
+use crate::core::device_regs::{CpuType, ModelCaps};
+use crate::core::thermal::ThermalZone;
+use crate::protocol::{BatteryStatus, EcData, Encoding, FanMode, NitroMode, PowerProfile, Request, Response};
+use crate::ui::{ec_watch, fan_response, stress};
+use crate::utils::keyboard::{self, Direction, KeyboardLayout, Rgb};
 
 // Shared application state
 
@@ -27,6 +32,8 @@ pub struct AppState {
     pub client: Client,
 
     // Runtime state (mirrored from Daemon)
+    /// Fans were switched to Turbo by picking Turbo mode, so the next mode
+    /// change should put them back on Auto.
     pub turbo_enabled: bool,
     
     // Values read from Daemon
@@ -35,6 +42,13 @@ pub struct AppState {
     pub sys_temp: u8,
     pub cpu_fan_speed: u16,
     pub gpu_fan_speed: u16,
+    pub cpu_fan_duty: Option<u8>,
+    pub gpu_fan_duty: Option<u8>,
+    /// dGPU fan in percent, on models where the EC's GPU fan is shared.
+    pub gpu_fan_percent: Option<u8>,
+    // Displayed RPM (moving average of the raw readings above)
+    pub cpu_fan_display: Option<f64>,
+    pub gpu_fan_display: Option<f64>,
     
     pub cpu_mode: FanMode,
     pub gpu_mode: FanMode,
@@ -48,29 +62,92 @@ pub struct AppState {
     
     pub cpu_manual_level: u8,
     pub gpu_manual_level: u8,
+    pub manual_fan_floor: u8,
     
     pub voltage_info: VoltageInfo,
     pub undervolt_status: String,
+    pub undervolt_supported: bool,
 
     // TDP / Power Profile
     pub tdp_value: u32,
     pub power_profile: PowerProfile,
 
+    pub coolboost: bool,
+    pub win_key_lock: bool,
+    pub touchpad_lock: bool,
+    pub mode_lighting: bool,
+    pub mode_tint: Option<Rgb>,
+
+    pub adapter_watts: Option<u16>,
+    pub battery_cycles: Option<u32>,
+    pub battery_health_pct: Option<u8>,
+    pub battery_power_w: Option<f64>,
+    pub battery_time_remaining_min: Option<u32>,
+    pub adapter_underpowered: bool,
+
+    // Optional features of the connected model; decides which controls show
+    pub caps: ModelCaps,
+
+    // Extra sysfs sensors, only polled while their section is expanded
+    pub thermal_zones: Vec<ThermalZone>,
+    pub show_thermal_zones: bool,
+
+    // Client-only preferences
+    pub client_config: ClientConfig,
+
     // Keyboard RGB (Client side state for UI)
     pub rgb_config: RgbConfig,
     pub selected_color: Rgb,
+    /// `rgb_config` has changes not yet written to the device.
+    pub rgb_dirty: bool,
+    /// Why the daemon refused the last change, shown in the status bar
+    /// until a later change goes through.
+    pub refusal: Option<String>,
+    /// Last change notice seen; `None` until the first poll catches up.
+    event_seq: Option<u64>,
+}
+
+/// Outcome of a status poll, shown in the window's status bar.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PollStatus {
+    Ok,
+    DaemonError(String),
+    IpcError(String),
+    /// The daemon hung up, e.g. because it restarted.
+    Disconnected,
+    Unexpected,
+}
+
+impl PollStatus {
+    /// Text for the status bar, or `None` when everything is fine.
+    pub fn message(&self) -> Option<String> {
+        match self {
+            PollStatus::Ok => None,
+            PollStatus::DaemonError(e) => Some(format!("Daemon error: {e}")),
+            PollStatus::IpcError(e) => Some(format!("IPC error: {e} – reconnecting…")),
+            PollStatus::Disconnected => Some("Daemon closed the connection – reconnecting…".into()),
+            PollStatus::Unexpected => Some("Unexpected response from daemon".into()),
+        }
+    }
 }
 
 impl AppState {
-    pub fn new() -> Self {
-        // Try to connect
-        let client = match Client::new() {
+    pub fn new(socket_path: &str) -> Self {
+        // Try to connect.  The window polls several times a second, so it
+        // asks for the compact encoding.
+        let mut client = match Client::new_with_encoding(socket_path, Encoding::Binary) {
             Ok(c) => c,
             Err(e) => {
                 eprintln!("Failed to connect to daemon: {}", e);
                 panic!("Daemon connection failed. Check if service is running.");
             }
         };
+        let _ = client.send(Request::SetClientName("GUI".into()));
+        // The daemon owns the keyboard config; this is only a working copy.
+        let rgb_config = match client.send(Request::GetRgbConfig) {
+            Ok(Response::RgbConfig(cfg)) => cfg,
+            _ => RgbConfig::default(),
+        };
 
         Self {
             client,
@@ -83,6 +160,11 @@ impl AppState {
             sys_temp: 0,
             cpu_fan_speed: 0,
             gpu_fan_speed: 0,
+            cpu_fan_duty: None,
+            gpu_fan_duty: None,
+            gpu_fan_percent: None,
+            cpu_fan_display: None,
+            gpu_fan_display: None,
             power_plugged_in: false,
             battery_status: BatteryStatus::Unknown(0),
             kb_timeout: false,
@@ -90,18 +172,38 @@ impl AppState {
             battery_charge_limit: false,
             cpu_manual_level: 0,
             gpu_manual_level: 0,
-            rgb_config: RgbConfig::load().unwrap_or_default(),
+            manual_fan_floor: 0,
+            client_config: ClientConfig::load_or_default(),
+            rgb_config,
             selected_color: Rgb::default(),
+            rgb_dirty: false,
             voltage_info: VoltageInfo { voltage: 0.0, min_recorded: 0.0, max_recorded: 0.0 },
             undervolt_status: String::new(),
+            undervolt_supported: true,
             tdp_value: TdpConfig::load_or_default().tdp_mw,
             power_profile: TdpConfig::load_or_default().profile,
+            coolboost: false,
+            win_key_lock: false,
+            touchpad_lock: false,
+            mode_lighting: false,
+            mode_tint: None,
+            adapter_watts: None,
+            battery_cycles: None,
+            battery_health_pct: None,
+            battery_power_w: None,
+            battery_time_remaining_min: None,
+            adapter_underpowered: false,
+            caps: ModelCaps { independent_gpu_fan: true, ..ModelCaps::default() },
+            thermal_zones: Vec::new(),
+            show_thermal_zones: false,
+            refusal: None,
+            event_seq: None,
         }
     }
 
     /// Refresh EC buffer and read all sensor / status registers via Daemon.
-    pub fn poll_ec(&mut self) {
-        match self.client.send(Request::GetStatus) {
+    pub fn poll_ec(&mut self) -> PollStatus {
+        let status = match self.client.send(Request::GetStatus { force: false }) {
             Ok(Response::Status(data)) => {
                 self.cpu_temp = data.cpu_temp;
                 self.gpu_temp = data.gpu_temp;
@@ -109,6 +211,11 @@ impl AppState {
                 
                 self.cpu_fan_speed = data.cpu_fan_speed;
                 self.gpu_fan_speed = data.gpu_fan_speed;
+                self.cpu_fan_duty = data.cpu_fan_duty;
+                self.gpu_fan_duty = data.gpu_fan_duty;
+                self.gpu_fan_percent = data.gpu_fan_percent;
+                self.cpu_fan_display = self.smooth_rpm(self.cpu_fan_display, data.cpu_fan_speed);
+                self.gpu_fan_display = self.smooth_rpm(self.gpu_fan_display, data.gpu_fan_speed);
                 
                 self.cpu_mode = data.cpu_mode;
                 self.gpu_mode = data.gpu_mode;
@@ -117,80 +224,165 @@ impl AppState {
                 self.power_plugged_in = data.power_plugged_in;
                 self.battery_status = data.battery_status;
                 self.kb_timeout = data.kb_timeout;
+                // Follow Fn-key brightness changes unless the user has
+                // unapplied edits of their own.
+                if let Some(b) = data.kb_brightness {
+                    if !self.rgb_dirty {
+                        self.rgb_config.brightness = b;
+                    }
+                }
                 self.usb_charging = data.usb_charging;
                 self.battery_charge_limit = data.battery_charge_limit;
                 
                 self.cpu_manual_level = data.cpu_manual_level;
                 self.gpu_manual_level = data.gpu_manual_level;
+                self.manual_fan_floor = data.manual_fan_floor;
                 
                 self.voltage_info = data.voltage_info;
                 self.undervolt_status = data.undervolt_status;
+                self.undervolt_supported = data.undervolt_supported;
                 self.tdp_value = data.tdp_value;
                 self.power_profile = data.power_profile;
+                self.coolboost = data.coolboost;
+                self.win_key_lock = data.win_key_lock;
+                self.touchpad_lock = data.touchpad_lock;
+                self.mode_lighting = data.mode_lighting;
+                self.mode_tint = data.mode_tint;
+                self.adapter_watts = data.adapter_watts;
+                self.battery_cycles = data.battery_cycles;
+                self.battery_health_pct = data.battery_health_pct;
+                self.battery_power_w = data.battery_power_w;
+                self.battery_time_remaining_min = data.battery_time_remaining_min;
+                self.adapter_underpowered = data.adapter_underpowered;
+                self.caps = data.caps;
+                PollStatus::Ok
             }
+            Ok(Response::Error(e)) => PollStatus::DaemonError(e),
+            Ok(_) => PollStatus::Unexpected,
+            // The client has already reconnected for the next poll.
+            Err(e) if client::is_disconnect(&e) => PollStatus::Disconnected,
+            Err(e) => PollStatus::IpcError(e.to_string()),
+        };
+        if self.show_thermal_zones {
+            self.refresh_thermal_zones();
+        }
+        status
+    }
+
+    pub fn refresh_thermal_zones(&mut self) {
+        match self.client.send(Request::GetThermalZones) {
+            Ok(Response::ThermalZones(zones)) => self.thermal_zones = zones,
             Ok(Response::Error(e)) => eprintln!("Daemon error: {}", e),
             Ok(_) => eprintln!("Unexpected response"),
             Err(e) => eprintln!("IPC error: {}", e),
         }
     }
 
+    /// Blend a new RPM reading into the displayed value.  The first reading,
+    /// and every reading with smoothing off, is shown as-is.
+    fn smooth_rpm(&self, shown: Option<f64>, raw: u16) -> Option<f64> {
+        let raw = raw as f64;
+        let alpha = self.client_config.rpm_smoothing_alpha.clamp(0.0, 1.0);
+        Some(match shown {
+            Some(prev) if self.client_config.smooth_rpm => prev + alpha * (raw - prev),
+            _ => raw,
+        })
+    }
+
+    /// RPM values to display as `(cpu, gpu)`.
+    pub fn displayed_rpm(&self) -> (u16, u16) {
+        let shown = |v: Option<f64>, raw: u16| v.map_or(raw, |v| v.round() as u16);
+        (shown(self.cpu_fan_display, self.cpu_fan_speed), shown(self.gpu_fan_display, self.gpu_fan_speed))
+    }
+
+    /// Send a change and remember whether the daemon refused it.
+    fn send_checked(&mut self, req: Request) {
+        match self.client.send(req) {
+            Ok(Response::Error(e)) => self.refusal = Some(e),
+            Ok(_) => self.refusal = None,
+            Err(e) => eprintln!("IPC error: {}", e),
+        }
+    }
+
     // Fan Mode
 
     pub fn set_cpu_auto(&mut self) {
-        let _ = self.client.send(Request::SetCpuFanMode(FanMode::Auto));
+        self.send_checked(Request::SetCpuFanMode(FanMode::Auto));
     }
 
     pub fn set_cpu_turbo(&mut self) {
-        let _ = self.client.send(Request::SetCpuFanMode(FanMode::Turbo));
+        self.send_checked(Request::SetCpuFanMode(FanMode::Turbo));
     }
 
     pub fn set_cpu_manual(&mut self) {
-        let _ = self.client.send(Request::SetCpuFanMode(FanMode::Manual));
+        self.send_checked(Request::SetCpuFanMode(FanMode::Manual));
     }
 
-    pub fn set_cpu_speed(&mut self, level: u8) {
-        // Range 0-20. Register expects 0-100.
-        let val = level * 5;
-        let _ = self.client.send(Request::SetCpuFanSpeed(val));
+    pub fn set_cpu_passive(&mut self) {
+        self.send_checked(Request::SetCpuFanMode(FanMode::Passive));
+    }
+
+    /// Manual CPU fan speed in percent (0-100); the daemon scales it for the EC.
+    pub fn set_cpu_speed(&mut self, percent: u8) {
+        self.send_checked(Request::SetCpuFanSpeed(percent));
     }
 
     pub fn set_gpu_auto(&mut self) {
-        let _ = self.client.send(Request::SetGpuFanMode(FanMode::Auto));
+        self.send_checked(Request::SetGpuFanMode(FanMode::Auto));
     }
 
     pub fn set_gpu_turbo(&mut self) {
-        let _ = self.client.send(Request::SetGpuFanMode(FanMode::Turbo));
+        self.send_checked(Request::SetGpuFanMode(FanMode::Turbo));
     }
 
     pub fn set_gpu_manual(&mut self) {
-        let _ = self.client.send(Request::SetGpuFanMode(FanMode::Manual));
+        self.send_checked(Request::SetGpuFanMode(FanMode::Manual));
+    }
+
+    pub fn set_gpu_passive(&mut self) {
+        self.send_checked(Request::SetGpuFanMode(FanMode::Passive));
+    }
+
+    pub fn set_gpu_speed(&mut self, percent: u8) {
+        self.send_checked(Request::SetGpuFanSpeed(percent));
+    }
+
+    pub fn set_manual_fan_floor(&mut self, percent: u8) {
+        self.send_checked(Request::SetManualFanFloor(percent));
     }
 
-    pub fn set_gpu_speed(&mut self, level: u8) {
-        let val = level * 5;
-        let _ = self.client.send(Request::SetGpuFanSpeed(val));
+    /// Fan slider step: the user's preference rounded up to a multiple of
+    /// the model's hardware granularity.
+    pub fn fan_step(&self) -> u8 {
+        let hw = self.caps.fan_step_percent.max(1);
+        let user = self.client_config.fan_step_percent.max(1);
+        user.div_ceil(hw).saturating_mul(hw)
     }
 
     // Nitro Mode
 
     pub fn set_quiet_mode(&mut self) {
-        let _ = self.client.send(Request::SetNitroMode(NitroMode::Quiet));
+        self.send_checked(Request::SetNitroMode(NitroMode::Quiet));
         self.global_auto();
     }
 
     pub fn set_default_mode(&mut self) {
-        let _ = self.client.send(Request::SetNitroMode(NitroMode::Default));
+        self.send_checked(Request::SetNitroMode(NitroMode::Default));
         self.global_auto();
     }
 
     pub fn set_extreme_mode(&mut self) {
-        let _ = self.client.send(Request::SetNitroMode(NitroMode::Extreme));
+        self.send_checked(Request::SetNitroMode(NitroMode::Extreme));
         self.global_auto();
     }
 
+    /// Turbo is its own EC level (only on models with `caps.turbo_mode`).
+    /// Maxing the fans alongside it is a separate client preference.
     pub fn set_turbo_mode(&mut self) {
-        let _ = self.client.send(Request::SetNitroMode(NitroMode::Extreme));
-        self.global_turbo();
+        self.send_checked(Request::SetNitroMode(NitroMode::Turbo));
+        if self.client_config.turbo_max_fans {
+            self.global_turbo();
+        }
     }
 
     fn global_auto(&mut self) {
@@ -223,11 +415,38 @@ impl AppState {
         let _ = self.client.send(Request::SetBatteryLimit(on));
     }
 
+    pub fn toggle_coolboost(&mut self, on: bool) {
+        let _ = self.client.send(Request::SetCoolBoost(on));
+    }
+
+    pub fn toggle_win_key_lock(&mut self, on: bool) {
+        let _ = self.client.send(Request::SetWinKeyLock(on));
+    }
+
+    pub fn toggle_touchpad_lock(&mut self, on: bool) {
+        let _ = self.client.send(Request::SetTouchpadLock(on));
+    }
+
+    pub fn toggle_mode_lighting(&mut self, on: bool) {
+        self.send_checked(Request::SetModeLighting(on));
+    }
+
     pub fn apply_undervolt(&mut self, idx: usize) {
         let _ = self.client.send(Request::ApplyUndervolt(idx));
     }
     
     pub fn refresh_voltage(&mut self) {
+        match self.client.send(Request::GetVoltage) {
+            Ok(Response::Voltage(info)) => self.voltage_info = info,
+            Ok(Response::Error(e)) => eprintln!("Daemon error: {}", e),
+            Ok(_) => eprintln!("Unexpected response"),
+            Err(e) => eprintln!("IPC error: {}", e),
+        }
+    }
+
+    pub fn reset_voltage_stats(&mut self) {
+        let _ = self.client.send(Request::ResetVoltageStats);
+        self.refresh_voltage();
     }
 
     // TDP Control
@@ -245,9 +464,66 @@ impl AppState {
         }
     }
 
+    /// Revert the daemon's most recent EC write and refresh the mirror.
+    pub fn undo(&mut self) -> Result<(), String> {
+        let result = match self.client.send(Request::Undo) {
+            Ok(Response::Ok) => Ok(()),
+            Ok(Response::Error(e)) => Err(e),
+            Ok(_) => Err("Unexpected response".into()),
+            Err(e) => Err(e.to_string()),
+        };
+        self.poll_ec();
+        result
+    }
+
+    /// Changes other clients or the daemon made since the last call.  The
+    /// first call only catches up, so nothing from before launch shows.
+    pub fn take_events(&mut self) -> Vec<String> {
+        let after = self.event_seq.unwrap_or(u64::MAX);
+        let Ok(Response::Events(latest, events)) = self.client.send(Request::GetEvents(after)) else {
+            return Vec::new();
+        };
+        match self.event_seq.replace(latest) {
+            // A restarted daemon numbers from zero again.
+            Some(seen) if latest >= seen => events,
+            _ => Vec::new(),
+        }
+    }
+
+    // Model Limits
+
+    /// Alarm threshold in °C: the user's own, or a margin under the
+    /// model's rated maximum.
+    pub fn temp_alarm(&self) -> u8 {
+        self.client_config.temp_alarm.unwrap_or(match self.caps.max_temp {
+            0 => DEFAULT_TEMP_ALARM,
+            max => max.saturating_sub(TEMP_ALARM_MARGIN),
+        })
+    }
+
+    /// Highest custom TDP accepted, in watts.
+    pub fn max_tdp_watts(&self) -> u32 {
+        match self.caps.tdp_watts {
+            0 => 200,
+            w => w as u32 * 2,
+        }
+    }
+
+    // Client Preferences
+
+    /// Change client preferences and persist them immediately.
+    pub fn update_client_config(&mut self, f: impl FnOnce(&mut ClientConfig)) {
+        f(&mut self.client_config);
+        self.client_config.save();
+    }
+
     // Config Persistence
 
     pub fn load_config(&mut self) {
+        // Caps first, so a failed first poll still leaves the right controls.
+        if let Ok(Response::SystemInfo(info)) = self.client.send(Request::GetSystemInfo) {
+            self.caps = info.caps;
+        }
         self.poll_ec();
     }
 
@@ -262,11 +538,28 @@ impl AppState {
         }
     }
 
+    /// Status with the power flow, e.g. "Discharging at 45 W — 1h 20m
+    /// left", as far as the kernel reports it.
+    pub fn battery_flow_text(&self) -> String {
+        let mut text = self.battery_status_text().to_string();
+        let moving = matches!(self.battery_status, BatteryStatus::Charging | BatteryStatus::Discharging);
+        if let (true, Some(w)) = (moving, self.battery_power_w) {
+            text.push_str(&format!(" at {w:.0} W"));
+        }
+        if let (true, Some(min)) = (moving, self.battery_time_remaining_min) {
+            let time = if min >= 60 { format!("{}h {}m", min / 60, min % 60) } else { format!("{min}m") };
+            let until = if self.battery_status == BatteryStatus::Charging { "to full" } else { "left" };
+            text.push_str(&format!(" — {time} {until}"));
+        }
+        text
+    }
+
     pub fn nitro_mode_text(&self) -> &str {
         match self.nitro_mode {
             NitroMode::Quiet => "Quiet",
             NitroMode::Default => "Default",
             NitroMode::Extreme => "Extreme",
+            NitroMode::Turbo => "Turbo",
             NitroMode::Unknown(_) => "Unknown",
         }
     }
@@ -277,44 +570,98 @@ impl AppState {
 
     // Keyboard
 
+    // Keyboard setters only stage the change; it reaches the device when
+    // `commit_rgb` runs (immediately in live-apply mode, else on "Apply").
+
     pub fn set_rgb_mode(&mut self, mode: u8) {
         self.rgb_config.mode = mode;
-        self.apply_rgb();
+        self.stage_rgb(true);
     }
 
     pub fn set_rgb_zone(&mut self, zone: u8) {
         self.rgb_config.zone = zone;
-        self.apply_rgb();
+        self.stage_rgb(true);
     }
 
+    /// Slider setter: never writes by itself, the caller debounces the commit.
     pub fn set_rgb_speed(&mut self, speed: u8) {
         self.rgb_config.speed = speed;
-        self.apply_rgb();
+        self.stage_rgb(false);
     }
 
+    /// Slider setter: never writes by itself, the caller debounces the commit.
     pub fn set_rgb_brightness(&mut self, brightness: u8) {
         self.rgb_config.brightness = brightness;
-        self.apply_rgb();
+        self.stage_rgb(false);
     }
 
-    pub fn set_rgb_direction(&mut self, direction: u8) {
+    pub fn set_rgb_direction(&mut self, direction: Direction) {
         self.rgb_config.direction = direction;
-        self.apply_rgb();
+        self.stage_rgb(true);
+    }
+
+    /// Replace the whole keyboard config and apply it in a single write.
+    /// Presets only cover the keys, so the logo keeps its colour.
+    pub fn apply_rgb_preset(&mut self, cfg: RgbConfig) {
+        self.rgb_config = RgbConfig { logo_color: self.rgb_config.logo_color, ..cfg };
+        self.rgb_dirty = true;
+        self.commit_rgb();
     }
 
     pub fn set_rgb_color(&mut self, r: u8, g: u8, b: u8) {
         self.rgb_config.color.r = r;
         self.rgb_config.color.g = g;
         self.rgb_config.color.b = b;
-        self.apply_rgb();
+        // The breathing cycle starts with the main colour.
+        if let Some(first) = self.rgb_config.breathing_colors.first_mut() {
+            *first = self.rgb_config.color;
+        }
+        self.stage_rgb(true);
+    }
+
+    /// Breathing colour cycle, animated by the daemon; fewer than two
+    /// colours turns it off.
+    pub fn set_rgb_breathing(&mut self, colors: Vec<Rgb>) {
+        self.rgb_config.software_effects = colors.len() > 1;
+        self.rgb_config.breathing_colors = colors;
+        self.stage_rgb(true);
     }
 
-    fn apply_rgb(&self) {
-        let c = &self.rgb_config;
-        keyboard::set_mode(
-            c.mode, c.zone, c.speed, c.brightness, c.direction, c.color
-        );
-        c.save();
+    /// Colour of the logo / light-bar zone.
+    pub fn set_rgb_logo(&mut self, color: Rgb) {
+        self.rgb_config.logo_color = Some(color);
+        self.stage_rgb(true);
+    }
+
+    /// `Some(end)` turns static mode into a zone gradient ending in `end`.
+    pub fn set_rgb_gradient(&mut self, end: Option<Rgb>) {
+        self.rgb_config.gradient_end = end;
+        self.stage_rgb(true);
+    }
+
+    fn stage_rgb(&mut self, commit_if_live: bool) {
+        self.rgb_dirty = true;
+        if commit_if_live && self.client_config.kb_live_apply {
+            self.commit_rgb();
+        }
+    }
+
+    /// Back to the factory lighting, dropping any unapplied edits.
+    pub fn reset_rgb(&mut self) {
+        self.send_checked(Request::ResetKeyboard);
+        if self.refusal.is_none() {
+            self.rgb_config = RgbConfig::factory();
+            self.rgb_dirty = false;
+        }
+    }
+
+    /// Send staged keyboard changes to the daemon, which writes the device
+    /// and saves them.
+    pub fn commit_rgb(&mut self) {
+        if self.rgb_dirty {
+            self.send_checked(Request::SetRgbConfig(self.rgb_config.clone()));
+            self.rgb_dirty = false;
+        }
     }
 
     pub fn shutdown(&mut self) {
@@ -322,6 +669,19 @@ impl AppState {
     }
 }
 
+// Keyboard presets
+
+const KEYBOARD_PRESETS: &[(&str, RgbConfig)] = &[
+    ("Rainbow Wave", RgbConfig { mode: 3, zone: 0, speed: 5, brightness: 100, direction: Direction::Right, color: Rgb { r: 255, g: 255, b: 255 }, gradient_end: None, breathing_colors: Vec::new(), software_effects: false, logo_color: None }),
+    ("Breathing White", RgbConfig { mode: 1, zone: 0, speed: 4, brightness: 100, direction: Direction::Right, color: Rgb { r: 255, g: 255, b: 255 }, gradient_end: None, breathing_colors: Vec::new(), software_effects: false, logo_color: None }),
+    ("Neon", RgbConfig { mode: 2, zone: 0, speed: 5, brightness: 100, direction: Direction::Right, color: Rgb { r: 255, g: 255, b: 255 }, gradient_end: None, breathing_colors: Vec::new(), software_effects: false, logo_color: None }),
+    ("Static Red", RgbConfig { mode: 0, zone: 0, speed: 0, brightness: 100, direction: Direction::Right, color: Rgb { r: 255, g: 0, b: 0 }, gradient_end: None, breathing_colors: Vec::new(), software_effects: false, logo_color: None }),
+];
+
+/// Starting picks for the extra colour-cycle colours.
+const CYCLE_DEFAULTS: [Rgb; keyboard::MAX_BREATHING_COLORS - 1] =
+    [Rgb { r: 255, g: 0, b: 0 }, Rgb { r: 0, g: 255, b: 0 }, Rgb { r: 0, g: 0, b: 255 }];
+
 // UI builder
 
 const APP_CSS: &str = r#"
@@ -390,25 +750,101 @@ scale trough {
 scale highlight {
     background-color: #3b82f6;
 }
+
+.status-bar {
+    color: #f87171; /* red-400 */
+    font-size: 12px;
+    margin-top: 12px;
+}
+
+.toast {
+    background-color: rgba(30, 41, 59, 0.95); /* slate-800 */
+    border-radius: 8px;
+    padding: 8px 14px;
+    margin-top: 12px;
+}
+
+.temp-alarm {
+    color: #f87171; /* red-400 */
+    font-weight: bold;
+}
+
+.footer {
+    color: #9ca3af; /* gray-400 */
+    font-size: 11px;
+    margin-top: 8px;
+}
+
+.footer-error {
+    color: #f87171; /* red-400 */
+}
+
+.ec-grid label {
+    font-family: monospace;
+}
+
+.ec-changed {
+    color: #facc15; /* yellow-400 */
+    font-weight: bold;
+}
 "#;
 
+/// Granularity of the poll timer.  A poll happens on the first tick after the
+/// configured interval has elapsed, so interval changes apply immediately.
+const POLL_TICK: Duration = Duration::from_millis(100);
+
+/// How long a change notice stays up.
+const TOAST_DURATION: Duration = Duration::from_secs(4);
+/// Alarm threshold when neither the user nor the model gives one.
+const DEFAULT_TEMP_ALARM: u8 = 90;
+/// Degrees under the model's rated maximum where the default alarm sits.
+const TEMP_ALARM_MARGIN: u8 = 5;
+/// Quiet period after the last keyboard slider move before a live write.
+const KB_SLIDER_DEBOUNCE: Duration = Duration::from_millis(300);
+/// Quiet period after the last fan slider move before the speed is sent,
+/// keeping a drag well under the daemon's write rate limit.
+const FAN_SLIDER_DEBOUNCE: Duration = Duration::from_millis(150);
+
 pub fn build_ui(app: &gtk4::Application, state: Rc<RefCell<AppState>>) -> Window {
+    // Model caps feed several widget defaults, so fetch them up front.
+    state.borrow_mut().load_config();
+
+    // GTK 4 has no API for window position or keep-above (both are left to
+    // the compositor), so only the size and maximized state are restored.
+    let (width, height, maximized) = {
+        let c = &state.borrow().client_config;
+        (c.window_width, c.window_height, c.window_maximized)
+    };
     let window = Window::builder()
         .application(app)
         .title("NitroSense")
-        .default_width(780)
-        .default_height(520)
+        .default_width(width)
+        .default_height(height)
+        .maximized(maximized)
         .resizable(true)
         .build();
 
+    {
+        let st = Rc::clone(&state);
+        window.connect_close_request(move |w| {
+            // default-size tracks the unmaximized size as the user resizes.
+            let (width, height) = w.default_size();
+            let maximized = w.is_maximized();
+            if let Ok(mut s) = st.try_borrow_mut() {
+                s.update_client_config(|c| {
+                    c.window_width = width;
+                    c.window_height = height;
+                    c.window_maximized = maximized;
+                });
+            }
+            glib::Propagation::Proceed
+        });
+    }
+
     // Load CSS
     let provider = CssProvider::new();
     provider.load_from_data(APP_CSS);
-    StyleContext::add_provider_for_display(
-        &gdk::Display::default().expect("Could not connect to a display."),
-        &provider,
-        gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION,
-    );
+    apply_theme(&provider, state.borrow().client_config.theme);
 
     let main_vbox = GtkBox::new(Orientation::Vertical, 0);
     main_vbox.set_margin_top(20);
@@ -432,7 +868,23 @@ pub fn build_ui(app: &gtk4::Application, state: Rc<RefCell<AppState>>) -> Window
     spacer.set_hexpand(true);
     header.append(&spacer);
 
-    // Right: Mode Selectors (Quiet, Default, Extreme)
+    // Undo last EC change
+    let undo_btn = Button::with_label("Undo");
+    undo_btn.set_valign(Align::Center);
+    undo_btn.set_margin_end(8);
+    {
+        let st = Rc::clone(&state);
+        undo_btn.connect_clicked(move |btn| {
+            let mut s = st.borrow_mut();
+            match s.undo() {
+                Ok(()) => btn.set_tooltip_text(None),
+                Err(e) => btn.set_tooltip_text(Some(&e)),
+            }
+        });
+    }
+    header.append(&undo_btn);
+
+    // Right: Mode Selectors (Quiet, Default, Extreme, Turbo where supported)
     let mode_box = GtkBox::new(Orientation::Horizontal, 4);
     mode_box.add_css_class("card");
     
@@ -441,6 +893,9 @@ pub fn build_ui(app: &gtk4::Application, state: Rc<RefCell<AppState>>) -> Window
     let mode_extreme = CheckButton::builder().label("Extreme").css_classes(["mode-btn"]).build();
     mode_default.set_group(Some(&mode_quiet));
     mode_extreme.set_group(Some(&mode_quiet));
+    let mode_turbo = CheckButton::builder().label("Turbo").css_classes(["mode-btn"]).build();
+    mode_turbo.set_group(Some(&mode_quiet));
+    mode_turbo.set_visible(false);
     
     // Set initial active state based on current mode
     {
@@ -448,6 +903,7 @@ pub fn build_ui(app: &gtk4::Application, state: Rc<RefCell<AppState>>) -> Window
         match s.nitro_mode {
             NitroMode::Quiet => mode_quiet.set_active(true),
             NitroMode::Extreme => mode_extreme.set_active(true),
+            NitroMode::Turbo => mode_turbo.set_active(true),
             _ => mode_default.set_active(true),
         }
     }
@@ -456,10 +912,29 @@ pub fn build_ui(app: &gtk4::Application, state: Rc<RefCell<AppState>>) -> Window
     { let st = Rc::clone(&state); mode_quiet.connect_toggled(move |btn| if btn.is_active() { if let Ok(mut s) = st.try_borrow_mut() { s.set_quiet_mode(); } }); }
     { let st = Rc::clone(&state); mode_default.connect_toggled(move |btn| if btn.is_active() { if let Ok(mut s) = st.try_borrow_mut() { s.set_default_mode(); } }); }
     { let st = Rc::clone(&state); mode_extreme.connect_toggled(move |btn| if btn.is_active() { if let Ok(mut s) = st.try_borrow_mut() { s.set_extreme_mode(); } }); }
+    { let st = Rc::clone(&state); mode_turbo.connect_toggled(move |btn| if btn.is_active() { if let Ok(mut s) = st.try_borrow_mut() { s.set_turbo_mode(); } }); }
+
+    // Ctrl+1..4 pick a mode through the same toggles as a click.
+    let shortcuts = ShortcutController::new();
+    shortcuts.set_scope(gtk4::ShortcutScope::Global);
+    for (key, btn) in [("1", &mode_quiet), ("2", &mode_default), ("3", &mode_extreme), ("4", &mode_turbo)] {
+        btn.set_tooltip_text(Some(&format!("Ctrl+{key}")));
+        let btn = btn.clone();
+        let action = CallbackAction::new(move |_, _| {
+            // Turbo stays hidden, and unreachable, on models without it.
+            if btn.is_visible() {
+                btn.set_active(true);
+            }
+            glib::Propagation::Stop
+        });
+        shortcuts.add_shortcut(Shortcut::new(ShortcutTrigger::parse_string(&format!("<Control>{key}")), Some(action)));
+    }
+    window.add_controller(shortcuts);
 
     mode_box.append(&mode_quiet);
     mode_box.append(&mode_default);
     mode_box.append(&mode_extreme);
+    mode_box.append(&mode_turbo);
     header.append(&mode_box);
     main_vbox.append(&header);
 
@@ -468,23 +943,136 @@ pub fn build_ui(app: &gtk4::Application, state: Rc<RefCell<AppState>>) -> Window
     stack.add_titled(&home_tab.container, Some("home"), "Home");
 
     let kbd_tab = build_keyboard_tab(&state);
-    stack.add_titled(&kbd_tab, Some("keyboard"), "Keyboard");
+    stack.add_titled(&kbd_tab.container, Some("keyboard"), "Keyboard");
+
+    let settings_tab = build_settings_tab(&state, &provider);
+    stack.add_titled(&settings_tab, Some("settings"), "Settings");
+
+    if ec_watch::enabled() {
+        stack.add_titled(&ec_watch::build(&state), Some("ec-watch"), "EC");
+    }
 
     main_vbox.append(&stack);
+
+    // Poll problems, hidden while the daemon answers normally
+    let status_bar = Label::new(None);
+    status_bar.add_css_class("status-bar");
+    status_bar.set_halign(Align::Start);
+    status_bar.set_wrap(true);
+    status_bar.set_visible(false);
+    main_vbox.append(&status_bar);
+
+    // Changes made elsewhere (CLI, app rules, thermal safety)
+    let toast = Label::new(None);
+    toast.add_css_class("toast");
+    toast.set_halign(Align::Center);
+    toast.set_wrap(true);
+    toast.set_visible(false);
+    main_vbox.append(&toast);
+    let toast_gen = Rc::new(Cell::new(0u32));
+    main_vbox.append(&build_footer(&mut state.borrow_mut().client));
+
     window.set_child(Some(&main_vbox));
 
     // Poll timer
-    glib::timeout_add_local(std::time::Duration::from_millis(1500), move || {
+    let last_poll = Cell::new(Instant::now());
+    glib::timeout_add_local(POLL_TICK, move || {
         let mut s = state.borrow_mut();
-        s.poll_ec();
-        // Update widgets
+        let interval = Duration::from_millis(s.client_config.poll_interval_ms as u64);
+        if last_poll.get().elapsed() < interval {
+            return glib::ControlFlow::Continue;
+        }
+        last_poll.set(Instant::now());
+        let status = s.poll_ec();
+        match status.message().or_else(|| s.refusal.clone()) {
+            Some(msg) => {
+                status_bar.set_label(&msg);
+                status_bar.set_visible(true);
+            }
+            None => status_bar.set_visible(false),
+        }
+        let events = s.take_events();
+        if !events.is_empty() {
+            toast.set_label(&events.join("\n"));
+            toast.set_visible(true);
+            // Only the newest notice's timer hides the toast.
+            let generation = toast_gen.get().wrapping_add(1);
+            toast_gen.set(generation);
+            let (toast, toast_gen) = (toast.clone(), Rc::clone(&toast_gen));
+            glib::timeout_add_local_once(TOAST_DURATION, move || {
+                if toast_gen.get() == generation {
+                    toast.set_visible(false);
+                }
+            });
+        }
+        // Update widgets; the toggle handlers are no-ops while we hold the
+        // borrow, so a refused or automatic mode change just snaps back.
+        mode_turbo.set_visible(s.caps.turbo_mode);
+        match s.nitro_mode {
+            NitroMode::Quiet => mode_quiet.set_active(true),
+            NitroMode::Default => mode_default.set_active(true),
+            NitroMode::Extreme => mode_extreme.set_active(true),
+            NitroMode::Turbo => mode_turbo.set_active(true),
+            NitroMode::Unknown(_) => {}
+        }
         home_tab.update(&s);
+        (kbd_tab.update)(&s);
         glib::ControlFlow::Continue
     });
 
     window
 }
 
+/// Short name for the EC device file the daemon uses.
+fn ec_backend_name(path: &str) -> &str {
+    match path {
+        "/sys/kernel/debug/ec/ec0/io" => "ec_sys",
+        "/dev/ec" => "acpi_ec",
+        other => other,
+    }
+}
+
+/// One-line summary of the EC backend, model and CPU, fetched once at
+/// startup.  Red when EC writes are known not to work.
+fn build_footer(client: &mut Client) -> Label {
+    let info = match client.send(Request::GetSystemInfo) {
+        Ok(Response::SystemInfo(info)) => Some(info),
+        _ => None,
+    };
+    let ec = match client.send(Request::GetEcInfo) {
+        Ok(Response::EcInfo(ec)) => Some(ec),
+        _ => None,
+    };
+
+    let locked = info.as_ref().is_some_and(|i| i.kernel_lockdown.is_some());
+    let writable = ec.as_ref().is_some_and(|e| e.writes_verified) && !locked;
+    let ec_text = match &ec {
+        Some(ec) if locked => format!("{} (locked down)", ec_backend_name(&ec.interface)),
+        Some(ec) => format!("{} ({})", ec_backend_name(&ec.interface), if writable { "rw" } else { "ro" }),
+        None => "unknown".into(),
+    };
+    let text = match &info {
+        Some(i) => {
+            let cpu = match i.cpu {
+                CpuType::Amd => "AMD",
+                CpuType::Intel => "Intel",
+                CpuType::Unknown => "unknown",
+            };
+            format!("EC: {ec_text} · Model: {} · CPU: {cpu}", i.model)
+        }
+        None => format!("EC: {ec_text}"),
+    };
+
+    let footer = Label::new(Some(&text));
+    footer.add_css_class("footer");
+    if !writable {
+        footer.add_css_class("footer-error");
+    }
+    footer.set_halign(Align::Start);
+    footer.set_selectable(true);
+    footer
+}
+
 struct HomeTab {
     container: GtkBox,
     update_fn: Rc<RefCell<Box<dyn FnMut(&AppState)>>>,
@@ -528,19 +1116,49 @@ fn build_home_tab(state: &Rc<RefCell<AppState>>) -> HomeTab {
     charge_val.add_css_class("value-text");
     power_card.append(&make_row("Charge Limit", &charge_val));
 
+    let health_val = Label::new(None);
+    health_val.set_halign(Align::End);
+    health_val.add_css_class("value-text");
+    power_card.append(&make_row("Battery Health", &health_val));
+
+    let cycles_val = Label::new(None);
+    cycles_val.set_halign(Align::End);
+    cycles_val.add_css_class("value-text");
+    power_card.append(&make_row("Charge Cycles", &cycles_val));
+
+    let adapter_val = Label::new(None);
+    adapter_val.set_halign(Align::End);
+    adapter_val.add_css_class("value-text");
+    power_card.append(&make_row("Adapter", &adapter_val));
+
+    let adapter_warn = Label::new(Some("Adapter below rated wattage – performance modes may be limited"));
+    adapter_warn.add_css_class("label-secondary");
+    adapter_warn.set_wrap(true);
+    adapter_warn.set_visible(false);
+    power_card.append(&adapter_warn);
+
     // Power controls
     let switches_box = GtkBox::new(Orientation::Vertical, 6);
     let limit_sw = CheckButton::with_label("Limit 80%");
     let usb_sw = CheckButton::with_label("USB Charge");
     let kb_sw = CheckButton::with_label("KB Timeout");
+    // Only shown on models that have the register
+    let win_key_sw = CheckButton::with_label("Lock Win Key");
+    win_key_sw.set_visible(false);
+    let touchpad_sw = CheckButton::with_label("Lock Touchpad");
+    touchpad_sw.set_visible(false);
     
     { let st = Rc::clone(state); limit_sw.connect_toggled(move |btn| if let Ok(mut s) = st.try_borrow_mut() { s.toggle_charge_limit(btn.is_active()); }); }
     { let st = Rc::clone(state); usb_sw.connect_toggled(move |btn| if let Ok(mut s) = st.try_borrow_mut() { s.toggle_usb_charging(btn.is_active()); }); }
     { let st = Rc::clone(state); kb_sw.connect_toggled(move |btn| if let Ok(mut s) = st.try_borrow_mut() { s.toggle_kb_timeout(btn.is_active()); }); }
+    { let st = Rc::clone(state); win_key_sw.connect_toggled(move |btn| if let Ok(mut s) = st.try_borrow_mut() { s.toggle_win_key_lock(btn.is_active()); }); }
+    { let st = Rc::clone(state); touchpad_sw.connect_toggled(move |btn| if let Ok(mut s) = st.try_borrow_mut() { s.toggle_touchpad_lock(btn.is_active()); }); }
 
     switches_box.append(&limit_sw);
     switches_box.append(&usb_sw);
     switches_box.append(&kb_sw);
+    switches_box.append(&win_key_sw);
+    switches_box.append(&touchpad_sw);
     power_card.append(&switches_box);
 
     grid.attach(&power_card, 0, 0, 1, 1);
@@ -590,15 +1208,42 @@ fn build_home_tab(state: &Rc<RefCell<AppState>>) -> HomeTab {
     
     let gpu_rpm = Label::new(Some("0 RPM"));
     gpu_rpm.add_css_class("value-text");
+
+    // Commanded duty from the EC, on models that expose it
+    let cpu_duty = Label::new(None);
+    cpu_duty.add_css_class("label-secondary");
+    let gpu_duty = Label::new(None);
+    gpu_duty.add_css_class("label-secondary");
     
     fans_box.append(&Label::new(Some("CPU FAN")));
     fans_box.append(&cpu_rpm);
+    fans_box.append(&cpu_duty);
     fans_box.append(&Label::new(Some("GPU FAN")));
     fans_box.append(&gpu_rpm);
+    fans_box.append(&gpu_duty);
     
     stats_content.attach(&fans_box, 1, 0, 1, 1);
     
     stats_card.append(&stats_content);
+
+    // Additional sysfs sensors (collapsed by default)
+    let sensors_box = GtkBox::new(Orientation::Vertical, 6);
+    let sensors_exp = Expander::builder().label("More sensors").child(&sensors_box).build();
+    {
+        let st = Rc::clone(state);
+        let sensors_box = sensors_box.clone();
+        sensors_exp.connect_expanded_notify(move |exp| {
+            if let Ok(mut s) = st.try_borrow_mut() {
+                s.show_thermal_zones = exp.is_expanded();
+                if s.show_thermal_zones {
+                    s.refresh_thermal_zones();
+                    fill_sensor_rows(&sensors_box, &s.thermal_zones, s.client_config.temp_unit);
+                }
+            }
+        });
+    }
+    stats_card.append(&sensors_exp);
+
     grid.attach(&stats_card, 1, 0, 2, 1);
 
     // Performance Tuning
@@ -637,10 +1282,33 @@ fn build_home_tab(state: &Rc<RefCell<AppState>>) -> HomeTab {
          });
     }
 
+    // Voltage readout
+    let volt_cur = Label::new(None);
+    volt_cur.add_css_class("value-text");
+    let volt_min = Label::new(None);
+    volt_min.add_css_class("value-text");
+    let volt_max = Label::new(None);
+    volt_max.add_css_class("value-text");
+    let volt_reset = Button::with_label("Reset Min/Max");
+
+    {
+        let st = Rc::clone(state);
+        let (cur, min, max) = (volt_cur.clone(), volt_min.clone(), volt_max.clone());
+        volt_reset.connect_clicked(move |_| {
+            let mut s = st.borrow_mut();
+            s.reset_voltage_stats();
+            set_voltage_labels(&s.voltage_info, &cur, &min, &max);
+        });
+    }
+
     uv_box.append(&uv_msg);
     uv_box.append(&uv_dd);
     uv_box.append(&uv_apply);
     uv_box.append(&uv_status);
+    uv_box.append(&make_row_multi("Voltage", &volt_cur));
+    uv_box.append(&make_row_multi("Min", &volt_min));
+    uv_box.append(&make_row_multi("Max", &volt_max));
+    uv_box.append(&volt_reset);
     tune_grid.attach(&uv_box, 0, 0, 1, 1);
 
     // 2. CPU Fan Control
@@ -652,6 +1320,12 @@ fn build_home_tab(state: &Rc<RefCell<AppState>>) -> HomeTab {
     tune_grid.attach(&gpu_ctl.widget, 2, 0, 1, 1);
 
     tune_card.append(&tune_grid);
+
+    // CoolBoost toggle (only shown on models that have the register)
+    let coolboost_sw = CheckButton::with_label("CoolBoost");
+    coolboost_sw.set_visible(false);
+    { let st = Rc::clone(state); coolboost_sw.connect_toggled(move |btn| if let Ok(mut s) = st.try_borrow_mut() { s.toggle_coolboost(btn.is_active()); }); }
+    tune_card.append(&coolboost_sw);
     grid.attach(&tune_card, 0, 1, 3, 1);
 
     // -----------------------------------------------------------------------
@@ -665,6 +1339,11 @@ fn build_home_tab(state: &Rc<RefCell<AppState>>) -> HomeTab {
     tdp_title.set_halign(Align::Start);
     tdp_card.append(&tdp_title);
 
+    let rated_tdp = Label::new(None);
+    rated_tdp.add_css_class("label-secondary");
+    rated_tdp.set_halign(Align::Start);
+    tdp_card.append(&rated_tdp);
+
     let tdp_content = GtkBox::new(Orientation::Horizontal, 20);
 
     // -- Left: Power Profile radio buttons --
@@ -762,15 +1441,16 @@ fn build_home_tab(state: &Rc<RefCell<AppState>>) -> HomeTab {
         let status = tdp_status.clone();
         tdp_apply.connect_clicked(move |_| {
             let text = entry.text();
+            let mut s = st.borrow_mut();
+            let max = s.max_tdp_watts();
             match text.trim().parse::<u32>() {
-                Ok(watts) if watts > 0 && watts <= 200 => {
+                Ok(watts) if watts > 0 && watts <= max => {
                     let mw = watts * 1000;
-                    let mut s = st.borrow_mut();
                     s.set_tdp(mw);
                     status.set_text(&format!("TDP set to {} W", watts));
                 }
                 _ => {
-                    status.set_text("Invalid value (1-200 W)");
+                    status.set_text(&format!("Invalid value (1-{max} W)"));
                 }
             }
         });
@@ -782,6 +1462,8 @@ fn build_home_tab(state: &Rc<RefCell<AppState>>) -> HomeTab {
 
     tdp_card.append(&tdp_content);
     grid.attach(&tdp_card, 0, 2, 3, 1);
+    grid.attach(&stress::build(state), 0, 3, 3, 1);
+    grid.attach(&fan_response::build(state), 0, 4, 3, 1);
 
     // Wrapper for home tab
     let container = GtkBox::new(Orientation::Vertical, 0);
@@ -791,28 +1473,74 @@ fn build_home_tab(state: &Rc<RefCell<AppState>>) -> HomeTab {
     let update_fn = Rc::new(RefCell::new(Box::new(move |s: &AppState| {
         // Power Card
         power_val.set_label(if s.power_plugged_in { "ON" } else { "OFF" });
-        batt_val.set_label(s.battery_status_text());
+        batt_val.set_label(&s.battery_flow_text());
         charge_val.set_label(s.charge_limit_text());
+        health_val.set_label(&s.battery_health_pct.map_or("N/A".into(), |p| format!("{p}%")));
+        cycles_val.set_label(&s.battery_cycles.map_or("N/A".into(), |c| c.to_string()));
+        match (s.power_plugged_in, s.adapter_watts) {
+            (false, _) => adapter_val.set_label("—"),
+            (true, Some(w)) => adapter_val.set_label(&format!("{} W", w)),
+            (true, None) => adapter_val.set_label("Unknown"),
+        }
+        adapter_warn.set_visible(s.power_plugged_in && s.adapter_underpowered);
         
         limit_sw.set_active(s.battery_charge_limit);
         usb_sw.set_active(s.usb_charging);
         kb_sw.set_active(s.kb_timeout);
+        win_key_sw.set_visible(s.caps.win_key_lock);
+        win_key_sw.set_active(s.win_key_lock);
+        touchpad_sw.set_visible(s.caps.touchpad_lock);
+        touchpad_sw.set_active(s.touchpad_lock);
 
         // Stats Card
-        cpu_temp_lbl.set_label(&format!("{}°C", s.cpu_temp));
+        // Bars stay on the 0–100 °C scale; only the text follows the unit.
+        let unit = s.client_config.temp_unit;
+        cpu_temp_lbl.set_label(&unit.format(s.cpu_temp));
+        set_css_class(&cpu_temp_lbl, "temp-alarm", s.cpu_temp >= s.temp_alarm());
         cpu_bar.set_value(s.cpu_temp as f64);
-        gpu_temp_lbl.set_label(&format!("{}°C", s.gpu_temp));
+        gpu_temp_lbl.set_label(&unit.format(s.gpu_temp));
+        set_css_class(&gpu_temp_lbl, "temp-alarm", s.gpu_temp >= s.temp_alarm());
         gpu_bar.set_value(s.gpu_temp as f64);
         
-        cpu_rpm.set_markup(&format!("<span size='x-large'>{}</span> <span size='small' color='gray'>RPM</span>", s.cpu_fan_speed));
-        gpu_rpm.set_markup(&format!("<span size='x-large'>{}</span> <span size='small' color='gray'>RPM</span>", s.gpu_fan_speed));
+        let (cpu_shown, gpu_shown) = s.displayed_rpm();
+        cpu_rpm.set_markup(&format!("<span size='x-large'>{}</span> <span size='small' color='gray'>RPM</span>", cpu_shown));
+        if let Some(percent) = s.gpu_fan_percent {
+            // The NVIDIA driver reports the dGPU fan as a share of full speed.
+            gpu_rpm.set_markup(&format!("<span size='x-large'>{}</span> <span size='small' color='gray'>% (NVIDIA)</span>", percent));
+        } else if s.caps.independent_gpu_fan {
+            gpu_rpm.set_markup(&format!("<span size='x-large'>{}</span> <span size='small' color='gray'>RPM</span>", gpu_shown));
+        } else {
+            // Single-fan model: there is no GPU fan to report.
+            gpu_rpm.set_markup("<span size='x-large'>N/A</span>");
+        }
+        for (label, duty) in [(&cpu_duty, s.cpu_fan_duty), (&gpu_duty, s.gpu_fan_duty)] {
+            label.set_visible(duty.is_some());
+            if let Some(d) = duty {
+                label.set_label(&format!("Commanded {d}%"));
+            }
+        }
+        if sensors_exp.is_expanded() {
+            fill_sensor_rows(&sensors_box, &s.thermal_zones, unit);
+        }
         
         // Sync Fan Controls
         (cpu_ctl.update)(s);
         (gpu_ctl.update)(s);
+        gpu_ctl.widget.set_visible(s.caps.independent_gpu_fan);
         
         // Update UV Status
         uv_status.set_text(&s.undervolt_status);
+        // Unknown CPU vendor: keep the panel, but only the explanation is live.
+        uv_dd.set_sensitive(s.undervolt_supported);
+        uv_apply.set_sensitive(s.undervolt_supported);
+        volt_reset.set_sensitive(s.undervolt_supported);
+        set_voltage_labels(&s.voltage_info, &volt_cur, &volt_min, &volt_max);
+
+        coolboost_sw.set_visible(s.caps.coolboost);
+        coolboost_sw.set_active(s.coolboost);
+
+        rated_tdp.set_visible(s.caps.tdp_watts > 0);
+        rated_tdp.set_label(&format!("{} W rated CPU TDP", s.caps.tdp_watts));
 
         // Update TDP profile radio buttons (but don't overwrite the entry
         // text — that would prevent the user from typing a custom value).
@@ -844,25 +1572,43 @@ fn build_fan_column(title: &str, state: &Rc<RefCell<AppState>>, is_cpu: bool) ->
     manual_badge.add_css_class("mode-btn");
     manual_badge.set_halign(Align::End);
     manual_badge.set_hexpand(true);
+    manual_badge.set_visible(false);
+    header.append(&manual_badge);
     vbox.append(&header);
     
-    // Slider
-    let slider = Scale::with_range(Orientation::Horizontal, 0.0, 20.0, 1.0);
+    // Slider, in percent; range and step follow the model and preferences
+    let (step, max) = {
+        let s = state.borrow();
+        (s.fan_step(), s.caps.fan_max_percent)
+    };
+    let slider = Scale::with_range(Orientation::Horizontal, 0.0, max as f64, step as f64);
+    slider.set_draw_value(true);
+    slider.set_format_value_func(|_, v| format!("{v:.0}%"));
     
     // Mode Selection
     let modes_box = GtkBox::new(Orientation::Horizontal, 2);
     let auto_btn = CheckButton::with_label("Auto");
     let max_btn = CheckButton::with_label("Max");
     let manual_btn = CheckButton::with_label("Custom");
+    let passive_btn = CheckButton::with_label("Passive");
+    passive_btn.set_tooltip_text(Some(
+        "Stop the fan while the machine is cool. Without firmware fan-stop this \
+         is Custom 0% and needs the thermal safety override.",
+    ));
     max_btn.set_group(Some(&auto_btn));
     manual_btn.set_group(Some(&auto_btn));
+    passive_btn.set_group(Some(&auto_btn));
     
     modes_box.append(&auto_btn);
     modes_box.append(&max_btn);
     modes_box.append(&manual_btn);
+    modes_box.append(&passive_btn);
     
     vbox.append(&slider);
     vbox.append(&modes_box);
+
+    // Speed write waiting for the slider to settle.
+    let pending_speed: Rc<RefCell<Option<glib::SourceId>>> = Rc::new(RefCell::new(None));
     
     // Signal Handlers
     {
@@ -888,11 +1634,32 @@ fn build_fan_column(title: &str, state: &Rc<RefCell<AppState>>, is_cpu: bool) ->
         });
 
         let st = Rc::clone(state);
-        slider.connect_change_value(move |_, _, val| {
+        passive_btn.connect_toggled(move |btn| if btn.is_active() { 
              if let Ok(mut s) = st.try_borrow_mut() {
-                 if is_cpu { s.set_cpu_speed(val as u8); } else { s.set_gpu_speed(val as u8); }
+                 if is_cpu { s.set_cpu_passive(); } else { s.set_gpu_passive(); }
+             }
+        });
+
+        let st = Rc::clone(state);
+        let pending = Rc::clone(&pending_speed);
+        slider.connect_change_value(move |sl, _, val| {
+             // Snap to the step so the EC never sees an in-between level.
+             let adj = sl.adjustment();
+             let step = adj.step_increment().max(1.0);
+             let snapped = ((val / step).round() * step).clamp(adj.lower(), adj.upper());
+             sl.set_value(snapped);
+             // A drag fires on every step; send only where it stops.
+             if let Some(id) = pending.borrow_mut().take() {
+                 id.remove();
              }
-             glib::Propagation::Proceed
+             let (st, p) = (Rc::clone(&st), Rc::clone(&pending));
+             *pending.borrow_mut() = Some(glib::timeout_add_local_once(FAN_SLIDER_DEBOUNCE, move || {
+                 p.borrow_mut().take();
+                 if let Ok(mut s) = st.try_borrow_mut() {
+                     if is_cpu { s.set_cpu_speed(snapped as u8); } else { s.set_gpu_speed(snapped as u8); }
+                 }
+             }));
+             glib::Propagation::Stop
         });
     }
     
@@ -904,15 +1671,65 @@ fn build_fan_column(title: &str, state: &Rc<RefCell<AppState>>, is_cpu: bool) ->
             FanMode::Auto => auto_btn.set_active(true),
             FanMode::Turbo => max_btn.set_active(true),
             FanMode::Manual => manual_btn.set_active(true),
+            FanMode::Passive => passive_btn.set_active(true),
             _ => {},
         }
+
+        // Badge only while the fan is under manual control
+        let is_manual = mode == FanMode::Manual;
+        manual_badge.set_visible(is_manual);
+        if is_manual {
+            manual_badge.set_label(&format!("Manual · {}%", level));
+        }
         
-        slider.set_value(level as f64 / 5.0);
+        let adj = slider.adjustment();
+        adj.set_lower(s.manual_fan_floor.min(s.caps.fan_max_percent) as f64);
+        adj.set_upper(s.caps.fan_max_percent as f64);
+        adj.set_step_increment(s.fan_step() as f64);
+        // The EC keeps the last manual level around in Auto/Max too; only
+        // show it while it is actually driving the fan.  A move still
+        // waiting to be sent wins over the polled level.
+        slider.set_sensitive(is_manual);
+        if pending_speed.borrow().is_none() {
+            slider.set_value(if is_manual { level as f64 } else { 0.0 });
+        }
     });
 
     FanCol { widget: vbox, update }
 }
 
+fn set_voltage_labels(info: &VoltageInfo, cur: &Label, min: &Label, max: &Label) {
+    cur.set_label(&format!("{:.3} V", info.voltage));
+    // Seed values (min > max) mean nothing has been recorded yet.
+    if info.min_recorded <= info.max_recorded {
+        min.set_label(&format!("{:.3} V", info.min_recorded));
+        max.set_label(&format!("{:.3} V", info.max_recorded));
+    } else {
+        min.set_label("—");
+        max.set_label("—");
+    }
+}
+
+fn set_css_class(widget: &impl IsA<gtk4::Widget>, class: &str, on: bool) {
+    if on {
+        widget.add_css_class(class);
+    } else {
+        widget.remove_css_class(class);
+    }
+}
+
+fn apply_theme(provider: &CssProvider, theme: Theme) {
+    let display = gdk::Display::default().expect("Could not connect to a display.");
+    match theme {
+        Theme::Dark => StyleContext::add_provider_for_display(
+            &display,
+            provider,
+            gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION,
+        ),
+        Theme::System => StyleContext::remove_provider_for_display(&display, provider),
+    }
+}
+
 fn make_row(label: &str, widget: &impl IsA<gtk4::Widget>) -> GtkBox {
     let box_ = GtkBox::new(Orientation::Horizontal, 10);
     let lbl = Label::new(Some(label));
@@ -923,6 +1740,100 @@ fn make_row(label: &str, widget: &impl IsA<gtk4::Widget>) -> GtkBox {
     box_
 }
 
+fn rgb_to_rgba(c: Rgb) -> gdk::RGBA {
+    gdk::RGBA::new(c.r as f32 / 255.0, c.g as f32 / 255.0, c.b as f32 / 255.0, 1.0)
+}
+
+fn rgba_to_rgb(c: &gdk::RGBA) -> Rgb {
+    Rgb { r: (c.red() * 255.0) as u8, g: (c.green() * 255.0) as u8, b: (c.blue() * 255.0) as u8 }
+}
+
+/// Position of `direction` in the direction dropdown.
+fn direction_index(direction: Direction) -> u32 {
+    Direction::ALL.iter().position(|&d| d == direction).unwrap_or(0) as u32
+}
+
+/// Colour of each preview zone at `t` seconds, as cairo RGB components
+/// already dimmed by the configured brightness.
+fn preview_colors(cfg: &RgbConfig, zones: u8, t: f64) -> Vec<(f64, f64, f64)> {
+    let zones = zones.max(1) as usize;
+    let dim = cfg.brightness as f64 / 100.0;
+    let rgb = |c: Rgb, k: f64| (c.r as f64 / 255.0 * k, c.g as f64 / 255.0 * k, c.b as f64 / 255.0 * k);
+    let off = (0.08, 0.08, 0.08);
+    // Cycles per second; speed 0-9, higher is faster.
+    let phase = t * keyboard::effect_rate(cfg.speed);
+    let forward = cfg.direction == Direction::Right;
+
+    match cfg.mode {
+        0 => match cfg.gradient_end {
+            Some(end) if zones > 1 => keyboard::gradient(cfg.color, end, zones as u8)
+                .into_iter()
+                .map(|c| rgb(c, dim))
+                .collect(),
+            _ => (1..=zones)
+                .map(|z| if cfg.zone == 0 || cfg.zone as usize == z { rgb(cfg.color, dim) } else { off })
+                .collect(),
+        },
+        // Breathing
+        1 if cfg.software_breathing() => {
+            vec![rgb(keyboard::breathing_frame(&cfg.breathing_colors, cfg.speed, t), dim); zones]
+        }
+        1 => {
+            let k = (1.0 - (phase * std::f64::consts::TAU).cos()) / 2.0;
+            vec![rgb(cfg.color, dim * k); zones]
+        }
+        // Neon: whole keyboard cycles through the hues
+        2 => vec![hue_to_rgb(phase, dim); zones],
+        // Wave: the hue cycle, offset per zone
+        3 => (0..zones)
+            .map(|z| {
+                let offset = z as f64 / zones as f64;
+                hue_to_rgb(if forward { phase - offset } else { phase + offset }, dim)
+            })
+            .collect(),
+        // Shifting, Zoom, Meteor: a lit zone travelling across
+        _ => {
+            let pos = (phase * zones as f64) as usize % zones;
+            let lit = if forward { pos } else { zones - 1 - pos };
+            let color = if keyboard::effect_uses_color(cfg.mode) { rgb(cfg.color, dim) } else { hue_to_rgb(phase, dim) };
+            (0..zones).map(|z| if z == lit { color } else { off }).collect()
+        }
+    }
+}
+
+/// Fully saturated colour for hue `h` (in turns), scaled by `k`.
+fn hue_to_rgb(h: f64, k: f64) -> (f64, f64, f64) {
+    let h = h.rem_euclid(1.0) * 6.0;
+    let x = 1.0 - (h % 2.0 - 1.0).abs();
+    let (r, g, b) = match h as u8 {
+        0 => (1.0, x, 0.0),
+        1 => (x, 1.0, 0.0),
+        2 => (0.0, 1.0, x),
+        3 => (0.0, x, 1.0),
+        4 => (x, 0.0, 1.0),
+        _ => (1.0, 0.0, x),
+    };
+    (r * k, g * k, b * k)
+}
+
+/// Replace the contents of `container` with one row per sensor.
+fn fill_sensor_rows(container: &GtkBox, zones: &[ThermalZone], unit: TempUnit) {
+    while let Some(child) = container.first_child() {
+        container.remove(&child);
+    }
+    if zones.is_empty() {
+        let empty = Label::new(Some("No additional sensors found"));
+        empty.add_css_class("label-secondary");
+        empty.set_halign(Align::Start);
+        container.append(&empty);
+        return;
+    }
+    for zone in zones {
+        let val = Label::new(Some(&format!("{:.1}{}", unit.celsius_to_unit(zone.temp_c as f64), unit.symbol())));
+        container.append(&make_row_multi(&zone.name, &val));
+    }
+}
+
 fn make_row_multi(label: &str, widget: &impl IsA<gtk4::Widget>) -> GtkBox {
     let box_ = GtkBox::new(Orientation::Horizontal, 0);
     let lbl = Label::new(Some(label));
@@ -937,7 +1848,12 @@ fn make_row_multi(label: &str, widget: &impl IsA<gtk4::Widget>) -> GtkBox {
     box_
 }
 
-fn build_keyboard_tab(state: &Rc<RefCell<AppState>>) -> GtkBox {
+struct KeyboardTab {
+    container: GtkBox,
+    update: Box<dyn Fn(&AppState)>,
+}
+
+fn build_keyboard_tab(state: &Rc<RefCell<AppState>>) -> KeyboardTab {
     let container = GtkBox::new(Orientation::Vertical, 12);
     container.set_margin_top(20);
     container.set_margin_bottom(20);
@@ -948,6 +1864,8 @@ fn build_keyboard_tab(state: &Rc<RefCell<AppState>>) -> GtkBox {
     let label = Label::new(Some("Keyboard RGB Settings"));
     container.append(&label);
 
+    let controls = GtkBox::new(Orientation::Vertical, 12);
+
     // Initial state
     let st = state.borrow();
     let initial_mode = st.rgb_config.mode;
@@ -956,41 +1874,123 @@ fn build_keyboard_tab(state: &Rc<RefCell<AppState>>) -> GtkBox {
     let initial_brit = st.rgb_config.brightness;
     let initial_dir = st.rgb_config.direction;
     let initial_color = st.rgb_config.color;
+    let initial_gradient = st.rgb_config.gradient_end;
+    let initial_logo = st.rgb_config.logo_color.unwrap_or_default();
+    let has_logo = st.caps.logo_zone;
+    let initial_cycle = if st.rgb_config.software_breathing() { st.rgb_config.breathing_colors.clone() } else { Vec::new() };
     drop(st);
 
+    // Preview: what the current settings should look like on the zones,
+    // drawn locally so nothing is written to the keyboard while adjusting.
+    let preview = DrawingArea::new();
+    preview.set_content_height(36);
+    preview.set_hexpand(true);
+    let started = Instant::now();
+    {
+        let s = Rc::clone(state);
+        preview.set_draw_func(move |_, cr, w, h| {
+            let Ok(st) = s.try_borrow() else { return };
+            let colors = preview_colors(&st.rgb_config, st.caps.keyboard.zone_count(), started.elapsed().as_secs_f64());
+            let gap = 4.0;
+            let zw = (w as f64 - gap * (colors.len() as f64 - 1.0)) / colors.len() as f64;
+            for (i, (r, g, b)) in colors.into_iter().enumerate() {
+                cr.set_source_rgb(r, g, b);
+                cr.rectangle(i as f64 * (zw + gap), 0.0, zw, h as f64);
+                let _ = cr.fill();
+            }
+        });
+    }
+    {
+        // Effects animate every frame; static colour only redraws on change.
+        let s = Rc::clone(state);
+        let last = RefCell::new(String::new());
+        preview.add_tick_callback(move |area, _| {
+            if let Ok(st) = s.try_borrow() {
+                let key = format!("{:?}{:?}", st.rgb_config, st.caps.keyboard);
+                if st.rgb_config.mode != 0 || *last.borrow() != key {
+                    area.queue_draw();
+                    *last.borrow_mut() = key;
+                }
+            }
+            glib::ControlFlow::Continue
+        });
+    }
+    controls.append(&make_row_multi("Preview", &preview));
+
     // Mode
     let list_modes = StringList::new(&["Static", "Breathing", "Neon", "Wave", "Shifting", "Zoom", "Meteor"]);
     let mode_dd = DropDown::new(Some(list_modes), gtk4::Expression::NONE);
     mode_dd.set_selected(initial_mode as u32);
-    container.append(&make_row_multi("Mode", &mode_dd));
+    controls.append(&make_row_multi("Mode", &mode_dd));
 
     // Zone (Static only)
     let list_zones = StringList::new(&["All Zones", "Zone 1", "Zone 2", "Zone 3", "Zone 4"]);
     let zone_dd = DropDown::new(Some(list_zones), gtk4::Expression::NONE);
     zone_dd.set_selected(initial_zone as u32);
     let zone_row = make_row_multi("Zone", &zone_dd);
-    container.append(&zone_row);
+    controls.append(&zone_row);
 
     // Color
+    // LEDs have no transparency, so the picker gets no alpha slider.
     let color_btn = ColorButton::new();
-    let rgba = gdk::RGBA::new(
-        initial_color.r as f32 / 255.0,
-        initial_color.g as f32 / 255.0,
-        initial_color.b as f32 / 255.0,
-        1.0
-    );
-    color_btn.set_rgba(&rgba);
+    color_btn.set_use_alpha(false);
+    color_btn.set_rgba(&rgb_to_rgba(initial_color));
     let color_row = make_row_multi("Color", &color_btn);
-    container.append(&color_row);
+    controls.append(&color_row);
+
+    // Logo / light bar: its own static colour whatever the keys are doing.
+    let logo_btn = ColorButton::new();
+    logo_btn.set_use_alpha(false);
+    logo_btn.set_rgba(&rgb_to_rgba(initial_logo));
+    let logo_row = make_row_multi("Logo", &logo_btn);
+    logo_row.set_visible(has_logo);
+    controls.append(&logo_row);
+
+    // Gradient (Static, multi-zone): "Color" is zone 1, this is zone 4
+    let gradient_check = CheckButton::with_label("Gradient");
+    gradient_check.set_active(initial_gradient.is_some());
+    let gradient_btn = ColorButton::new();
+    gradient_btn.set_use_alpha(false);
+    gradient_btn.set_rgba(&rgb_to_rgba(initial_gradient.unwrap_or(initial_color)));
+    gradient_btn.set_sensitive(initial_gradient.is_some());
+    let gradient_box = GtkBox::new(Orientation::Horizontal, 6);
+    gradient_box.append(&gradient_check);
+    gradient_box.append(&gradient_btn);
+    let gradient_row = make_row_multi("Gradient End", &gradient_box);
+    controls.append(&gradient_row);
+
+    // Colour cycle (Breathing): the firmware breathes one colour, so the
+    // daemon animates the cycle itself.  "Color" is the first colour.
+    let cycle_dd = DropDown::new(
+        Some(StringList::new(&["Off", "2 Colours", "3 Colours", "4 Colours"])),
+        gtk4::Expression::NONE,
+    );
+    cycle_dd.set_selected(initial_cycle.len().saturating_sub(1) as u32);
+    cycle_dd.set_tooltip_text(Some("Software effect: the daemon redraws the keyboard while it runs"));
+    let cycle_btns: Vec<ColorButton> = CYCLE_DEFAULTS
+        .iter()
+        .enumerate()
+        .map(|(i, &default)| {
+            let btn = ColorButton::new();
+            btn.set_use_alpha(false);
+            btn.set_rgba(&rgb_to_rgba(initial_cycle.get(i + 1).copied().unwrap_or(default)));
+            btn
+        })
+        .collect();
+    let cycle_box = GtkBox::new(Orientation::Horizontal, 6);
+    cycle_box.append(&cycle_dd);
+    for btn in &cycle_btns {
+        cycle_box.append(btn);
+    }
+    let cycle_row = make_row_multi("Colour Cycle", &cycle_box);
+    controls.append(&cycle_row);
 
-    // Direction (0=Right, 1=Left)
-    let list_direction = StringList::new(&["Right", "Left"]); 
+    // Direction, listed in `Direction::ALL` order
+    let list_direction = StringList::new(&Direction::ALL.map(Direction::label));
     let dir_dd = DropDown::new(Some(list_direction), gtk4::Expression::NONE);
-    
-    // Map initial value
-    dir_dd.set_selected(if initial_dir == 2 { 1 } else { 0 });
+    dir_dd.set_selected(direction_index(initial_dir));
     let dir_row = make_row_multi("Direction", &dir_dd);
-    container.append(&dir_row);
+    controls.append(&dir_row);
 
     // Brightness
     let b_adj = Adjustment::new(initial_brit as f64, 0.0, 100.0, 1.0, 10.0, 0.0);
@@ -999,7 +1999,7 @@ fn build_keyboard_tab(state: &Rc<RefCell<AppState>>) -> GtkBox {
     brightness_scale.set_hexpand(true);
     brightness_scale.set_width_request(200);
     let brit_row = make_row_multi("Brightness", &brightness_scale);
-    container.append(&brit_row);
+    controls.append(&brit_row);
 
     // Speed
     let s_adj = Adjustment::new(initial_speed as f64, 0.0, 9.0, 1.0, 1.0, 0.0);
@@ -1008,18 +2008,36 @@ fn build_keyboard_tab(state: &Rc<RefCell<AppState>>) -> GtkBox {
     speed_scale.set_hexpand(true);
     speed_scale.set_width_request(200);
     let speed_row = make_row_multi("Speed", &speed_scale);
-    container.append(&speed_row);
+    controls.append(&speed_row);
 
     // Show/hide rows based on mode
     let uv_zone = zone_row.clone();
     let uv_dir = dir_row.clone();
     let uv_speed = speed_row.clone();
-
+    let uv_gradient = gradient_row.clone();
+    let uv_gradient_check = gradient_check.clone();
+    let uv_color = color_row.clone();
+    let uv_cycle = cycle_row.clone();
+    let (uv_cycle_dd, uv_cycle_btns) = (cycle_dd.clone(), cycle_btns.clone());
+
+    // Zones only matter for static colour on multi-zone keyboards; the
+    // layout comes from the daemon and may change after the first poll.
+    let layout = Rc::new(Cell::new(state.borrow().caps.keyboard));
+    let uv_layout = Rc::clone(&layout);
     let update_visibility = Rc::new(move |mode: u32| {
         let is_static = mode == 0;
-        uv_zone.set_visible(is_static);
+        let multi_zone = uv_layout.get().zone_count() > 1;
+        uv_zone.set_visible(is_static && multi_zone && !uv_gradient_check.is_active());
+        uv_gradient.set_visible(is_static && multi_zone);
         uv_dir.set_visible(!is_static);
         uv_speed.set_visible(!is_static);
+        uv_cycle.set_visible(mode == keyboard::MODE_BREATHING as u32);
+        let extra = uv_cycle_dd.selected() as usize;
+        for (i, btn) in uv_cycle_btns.iter().enumerate() {
+            btn.set_visible(i < extra);
+        }
+        // Greyed out rather than hidden so the layout doesn't jump.
+        uv_color.set_sensitive(keyboard::effect_uses_color(mode as u8));
     });
     
     update_visibility(initial_mode as u32);
@@ -1046,11 +2064,9 @@ fn build_keyboard_tab(state: &Rc<RefCell<AppState>>) -> GtkBox {
 
     let s = Rc::clone(state);
     dir_dd.connect_selected_notify(move |d| {
-        let dir_idx = d.selected();
-        // Map index to EC value (Right=1, Left=2)
-        let dir_val = if dir_idx == 0 { 1 } else { 2 };
+        let Some(&direction) = Direction::ALL.get(d.selected() as usize) else { return };
         if let Ok(mut st) = s.try_borrow_mut() {
-            st.set_rgb_direction(dir_val as u8);
+            st.set_rgb_direction(direction);
         }
     });
 
@@ -1069,21 +2085,417 @@ fn build_keyboard_tab(state: &Rc<RefCell<AppState>>) -> GtkBox {
     });
 
     let s = Rc::clone(state);
+    let uv = update_visibility.clone();
+    let (mode, end_btn) = (mode_dd.clone(), gradient_btn.clone());
+    gradient_check.connect_toggled(move |c| {
+        let on = c.is_active();
+        end_btn.set_sensitive(on);
+        uv(mode.selected());
+        if let Ok(mut st) = s.try_borrow_mut() {
+            st.set_rgb_gradient(on.then(|| rgba_to_rgb(&end_btn.rgba())));
+        }
+    });
+
+    let s = Rc::clone(state);
+    logo_btn.connect_color_set(move |btn| {
+        if let Ok(mut st) = s.try_borrow_mut() {
+            st.set_rgb_logo(rgba_to_rgb(&btn.rgba()));
+        }
+    });
+
+    let s = Rc::clone(state);
+    gradient_btn.connect_color_set(move |btn| {
+        if let Ok(mut st) = s.try_borrow_mut() {
+            st.set_rgb_gradient(Some(rgba_to_rgb(&btn.rgba())));
+        }
+    });
+
+    // Any change to the cycle resends the whole colour list.
+    let send_cycle = {
+        let s = Rc::clone(state);
+        let (cycle_dd, cycle_btns, color_btn) = (cycle_dd.clone(), cycle_btns.clone(), color_btn.clone());
+        Rc::new(move || {
+            let count = cycle_dd.selected() as usize;
+            let colors: Vec<Rgb> = if count == 0 {
+                Vec::new()
+            } else {
+                std::iter::once(&color_btn).chain(&cycle_btns[..count]).map(|b| rgba_to_rgb(&b.rgba())).collect()
+            };
+            if let Ok(mut st) = s.try_borrow_mut() {
+                st.set_rgb_breathing(colors);
+            }
+        })
+    };
+    {
+        let (uv, mode, send) = (update_visibility.clone(), mode_dd.clone(), Rc::clone(&send_cycle));
+        cycle_dd.connect_selected_notify(move |_| {
+            uv(mode.selected());
+            send();
+        });
+    }
+    for btn in &cycle_btns {
+        let send = Rc::clone(&send_cycle);
+        btn.connect_color_set(move |_| send());
+    }
+
+    // Sliders fire on every step; in live mode only write once they settle.
+    let pending_commit: Rc<Cell<Option<glib::SourceId>>> = Rc::new(Cell::new(None));
+    let schedule_commit = {
+        let s = Rc::clone(state);
+        let pending = Rc::clone(&pending_commit);
+        Rc::new(move || {
+            if let Some(id) = pending.take() {
+                id.remove();
+            }
+            let (s, p) = (Rc::clone(&s), Rc::clone(&pending));
+            pending.set(Some(glib::timeout_add_local_once(KB_SLIDER_DEBOUNCE, move || {
+                p.set(None);
+                if let Ok(mut st) = s.try_borrow_mut() {
+                    if st.client_config.kb_live_apply {
+                        st.commit_rgb();
+                    }
+                }
+            })));
+        })
+    };
+
+    let s = Rc::clone(state);
+    let commit = Rc::clone(&schedule_commit);
     brightness_scale.connect_change_value(move |_, _, val| {
         if let Ok(mut st) = s.try_borrow_mut() {
             st.set_rgb_brightness(val as u8);
         }
+        commit();
         glib::Propagation::Proceed
     });
 
     let s = Rc::clone(state);
+    let commit = Rc::clone(&schedule_commit);
     speed_scale.connect_change_value(move |_, _, val| {
          if let Ok(mut st) = s.try_borrow_mut() {
             st.set_rgb_speed(val as u8);
         }
+        commit();
         glib::Propagation::Proceed
     });
-    
-    container
+
+    // Presets
+    // Callers hold the state borrow while syncing, so the controls' own
+    // handlers can't re-apply intermediate states.
+    let sync_controls = {
+        let (mode_dd, zone_dd, dir_dd) = (mode_dd.clone(), zone_dd.clone(), dir_dd.clone());
+        let (color_btn, brightness_scale, speed_scale) =
+            (color_btn.clone(), brightness_scale.clone(), speed_scale.clone());
+        let (gradient_check, logo_btn) = (gradient_check.clone(), logo_btn.clone());
+        let (cycle_dd, cycle_btns) = (cycle_dd.clone(), cycle_btns.clone());
+        Rc::new(move |cfg: &RgbConfig| {
+            mode_dd.set_selected(cfg.mode as u32);
+            zone_dd.set_selected(cfg.zone as u32);
+            dir_dd.set_selected(direction_index(cfg.direction));
+            color_btn.set_rgba(&rgb_to_rgba(cfg.color));
+            gradient_check.set_active(cfg.gradient_end.is_some());
+            if let Some(logo) = cfg.logo_color {
+                logo_btn.set_rgba(&rgb_to_rgba(logo));
+            }
+            let cycle = if cfg.software_breathing() { cfg.breathing_colors.as_slice() } else { &[] };
+            cycle_dd.set_selected(cycle.len().saturating_sub(1) as u32);
+            for (btn, color) in cycle_btns.iter().zip(cycle.iter().skip(1)) {
+                btn.set_rgba(&rgb_to_rgba(*color));
+            }
+            brightness_scale.set_value(cfg.brightness as f64);
+            speed_scale.set_value(cfg.speed as f64);
+        })
+    };
+    let presets_box = GtkBox::new(Orientation::Horizontal, 6);
+    for (name, preset) in KEYBOARD_PRESETS {
+        let btn = Button::with_label(name);
+        let s = Rc::clone(state);
+        let sync = Rc::clone(&sync_controls);
+        btn.connect_clicked(move |_| {
+            let mut st = s.borrow_mut();
+            st.apply_rgb_preset(preset.clone());
+            sync(preset);
+        });
+        presets_box.append(&btn);
+    }
+    controls.append(&make_row_multi("Presets", &presets_box));
+
+    // Factory default: unlike "off", this is the out-of-box look.
+    let reset_btn = Button::with_label("Factory Default");
+    reset_btn.set_tooltip_text(Some("Static white on every zone at full brightness"));
+    reset_btn.set_halign(Align::Start);
+    {
+        let s = Rc::clone(state);
+        let sync = Rc::clone(&sync_controls);
+        reset_btn.connect_clicked(move |_| {
+            let mut st = s.borrow_mut();
+            st.reset_rgb();
+            let cfg = st.rgb_config.clone();
+            sync(&cfg);
+        });
+    }
+    controls.append(&make_row_multi("Reset", &reset_btn));
+
+    // Mode lighting: the daemon tints the keyboard whenever the mode changes.
+    let mode_light_check = CheckButton::with_label("Match performance mode");
+    mode_light_check.set_tooltip_text(Some(
+        "Blue for Quiet, white for Default, red for Extreme, orange for Turbo; \
+         applying any other lighting takes over until the next mode change",
+    ));
+    {
+        let s = Rc::clone(state);
+        mode_light_check.connect_toggled(move |btn| {
+            if let Ok(mut st) = s.try_borrow_mut() {
+                st.toggle_mode_lighting(btn.is_active());
+            }
+        });
+    }
+    let mode_tint_label = Label::new(None);
+    mode_tint_label.add_css_class("label-secondary");
+    let mode_light_box = GtkBox::new(Orientation::Horizontal, 12);
+    mode_light_box.append(&mode_light_check);
+    mode_light_box.append(&mode_tint_label);
+    controls.append(&make_row_multi("Mode Lighting", &mode_light_box));
+
+    // Apply / live-apply
+    let live_check = CheckButton::with_label("Apply changes live");
+    live_check.set_active(state.borrow().client_config.kb_live_apply);
+    let apply_btn = Button::with_label("Apply");
+    apply_btn.add_css_class("suggested-action");
+    apply_btn.set_sensitive(!live_check.is_active());
+
+    let s = Rc::clone(state);
+    apply_btn.connect_clicked(move |_| {
+        if let Ok(mut st) = s.try_borrow_mut() {
+            st.commit_rgb();
+        }
+    });
+
+    let s = Rc::clone(state);
+    let apply = apply_btn.clone();
+    live_check.connect_toggled(move |c| {
+        let live = c.is_active();
+        apply.set_sensitive(!live);
+        if let Ok(mut st) = s.try_borrow_mut() {
+            st.update_client_config(|c| c.kb_live_apply = live);
+            if live {
+                st.commit_rgb();
+            }
+        }
+    });
+
+    let apply_box = GtkBox::new(Orientation::Horizontal, 12);
+    apply_box.set_halign(Align::End);
+    apply_box.append(&live_check);
+    apply_box.append(&apply_btn);
+    controls.append(&apply_box);
+
+    container.append(&controls);
+
+    let per_key_notice = Label::new(Some("Per-key RGB keyboards are not supported yet."));
+    per_key_notice.add_css_class("label-secondary");
+    per_key_notice.set_visible(false);
+    container.append(&per_key_notice);
+
+    let update = Box::new(move |s: &AppState| {
+        if layout.get() != s.caps.keyboard {
+            layout.set(s.caps.keyboard);
+            update_visibility(mode_dd.selected());
+        }
+        logo_row.set_visible(s.caps.logo_zone);
+        if brightness_scale.value() as u8 != s.rgb_config.brightness && !s.rgb_dirty {
+            brightness_scale.set_value(s.rgb_config.brightness as f64);
+        }
+        if mode_light_check.is_active() != s.mode_lighting {
+            mode_light_check.set_active(s.mode_lighting);
+        }
+        mode_tint_label.set_label(&match s.mode_tint {
+            Some(Rgb { r, g, b }) => format!("Showing #{r:02X}{g:02X}{b:02X}"),
+            None => String::new(),
+        });
+        let per_key = s.caps.keyboard == KeyboardLayout::PerKey;
+        controls.set_visible(!per_key);
+        per_key_notice.set_visible(per_key);
+    });
+
+    KeyboardTab { container, update }
 }
 
+
+fn build_settings_tab(state: &Rc<RefCell<AppState>>, provider: &CssProvider) -> GtkBox {
+    let container = GtkBox::new(Orientation::Vertical, 12);
+    container.set_margin_top(20);
+    container.set_margin_bottom(20);
+    container.set_margin_start(20);
+    container.set_margin_end(20);
+
+    let title = Label::new(Some("CLIENT SETTINGS"));
+    title.add_css_class("section-title");
+    title.set_halign(Align::Start);
+    container.append(&title);
+
+    let cfg = state.borrow().client_config.clone();
+
+    // Temperature unit
+    let unit_dd = DropDown::new(Some(StringList::new(&["Celsius (°C)", "Fahrenheit (°F)"])), gtk4::Expression::NONE);
+    unit_dd.set_selected(match cfg.temp_unit { TempUnit::Celsius => 0, TempUnit::Fahrenheit => 1 });
+    container.append(&make_row_multi("Temperature Unit", &unit_dd));
+
+    // Temperature alarm, shown in the selected unit but stored in °C
+    let alarm_spin = SpinButton::with_range(0.0, 250.0, 1.0);
+    let alarm_label = Label::new(None);
+    alarm_label.add_css_class("label-secondary");
+    let set_alarm_widgets = {
+        let (spin, label) = (alarm_spin.clone(), alarm_label.clone());
+        move |unit: TempUnit, celsius: u8| {
+            spin.set_range(unit.celsius_to_unit(40.0).round(), unit.celsius_to_unit(110.0).round());
+            spin.set_value(unit.celsius_to_unit(celsius as f64).round());
+            label.set_label(unit.symbol());
+        }
+    };
+    set_alarm_widgets(cfg.temp_unit, state.borrow().temp_alarm());
+    let alarm_box = GtkBox::new(Orientation::Horizontal, 6);
+    alarm_box.append(&alarm_spin);
+    alarm_box.append(&alarm_label);
+    container.append(&make_row_multi("Temperature Alarm", &alarm_box));
+
+    // Poll interval
+    let poll_spin = SpinButton::with_range(500.0, 10_000.0, 100.0);
+    poll_spin.set_value(cfg.poll_interval_ms as f64);
+    container.append(&make_row_multi("Poll Interval (ms)", &poll_spin));
+
+    // Fan RPM smoothing
+    let smooth_check = CheckButton::with_label("Smooth");
+    smooth_check.set_active(cfg.smooth_rpm);
+    let alpha_spin = SpinButton::with_range(0.05, 1.0, 0.05);
+    alpha_spin.set_digits(2);
+    alpha_spin.set_value(cfg.rpm_smoothing_alpha);
+    alpha_spin.set_sensitive(cfg.smooth_rpm);
+    alpha_spin.set_tooltip_text(Some("Weight of the newest reading (1.0 = no smoothing)"));
+    let smooth_box = GtkBox::new(Orientation::Horizontal, 6);
+    smooth_box.append(&smooth_check);
+    smooth_box.append(&alpha_spin);
+    container.append(&make_row_multi("Fan RPM Display", &smooth_box));
+
+    // Fan slider step
+    let fan_step_spin = SpinButton::with_range(1.0, 25.0, 1.0);
+    fan_step_spin.set_value(cfg.fan_step_percent as f64);
+    fan_step_spin.set_tooltip_text(Some("Rounded up to what the model supports"));
+    container.append(&make_row_multi("Fan Slider Step (%)", &fan_step_spin));
+
+    // Manual fan floor (enforced by the daemon for every client)
+    let fan_floor_spin = SpinButton::with_range(0.0, 100.0, 5.0);
+    fan_floor_spin.set_value(state.borrow().manual_fan_floor as f64);
+    fan_floor_spin.set_tooltip_text(Some("Custom fan speeds below this are raised to it (0 = no floor)"));
+    container.append(&make_row_multi("Manual Fan Floor (%)", &fan_floor_spin));
+
+    // Fan coupling for Turbo mode
+    let turbo_fans_check = CheckButton::with_label("Max fans in Turbo mode");
+    turbo_fans_check.set_active(cfg.turbo_max_fans);
+    turbo_fans_check.set_tooltip_text(Some("Switch both fans to Turbo with the mode, and back to Auto after"));
+    container.append(&make_row_multi("Turbo Mode Fans", &turbo_fans_check));
+
+    // Theme
+    let theme_dd = DropDown::new(Some(StringList::new(&["NitroSense Dark", "System"])), gtk4::Expression::NONE);
+    theme_dd.set_selected(match cfg.theme { Theme::Dark => 0, Theme::System => 1 });
+    container.append(&make_row_multi("Theme", &theme_dd));
+
+    // Socket path (used at next start)
+    let socket_entry = Entry::builder()
+        .placeholder_text(crate::protocol::SOCKET_PATH)
+        .width_chars(28)
+        .build();
+    socket_entry.set_text(cfg.socket_path.as_deref().unwrap_or(""));
+    container.append(&make_row_multi("Daemon Socket", &socket_entry));
+    let socket_hint = Label::new(Some("Socket changes apply the next time NitroSense starts."));
+    socket_hint.add_css_class("label-secondary");
+    socket_hint.set_halign(Align::Start);
+    container.append(&socket_hint);
+
+    // -- Signals --
+
+    let st = Rc::clone(state);
+    unit_dd.connect_selected_notify(move |d| {
+        let unit = if d.selected() == 1 { TempUnit::Fahrenheit } else { TempUnit::Celsius };
+        if let Ok(mut s) = st.try_borrow_mut() {
+            s.update_client_config(|c| c.temp_unit = unit);
+            // Re-display the alarm in the new unit; the spin handler is a
+            // no-op while we still hold the borrow.
+            set_alarm_widgets(unit, s.temp_alarm());
+        }
+    });
+
+    let st = Rc::clone(state);
+    alarm_spin.connect_value_changed(move |spin| {
+        if let Ok(mut s) = st.try_borrow_mut() {
+            let celsius = s.client_config.temp_unit.to_celsius(spin.value()).round();
+            s.update_client_config(|c| c.temp_alarm = Some(celsius.clamp(0.0, 255.0) as u8));
+        }
+    });
+
+    let st = Rc::clone(state);
+    poll_spin.connect_value_changed(move |spin| {
+        if let Ok(mut s) = st.try_borrow_mut() {
+            s.update_client_config(|c| c.poll_interval_ms = spin.value() as u32);
+        }
+    });
+
+    let st = Rc::clone(state);
+    fan_step_spin.connect_value_changed(move |spin| {
+        if let Ok(mut s) = st.try_borrow_mut() {
+            s.update_client_config(|c| c.fan_step_percent = spin.value() as u8);
+        }
+    });
+
+    let st = Rc::clone(state);
+    fan_floor_spin.connect_value_changed(move |spin| {
+        if let Ok(mut s) = st.try_borrow_mut() {
+            s.set_manual_fan_floor(spin.value() as u8);
+        }
+    });
+
+    let st = Rc::clone(state);
+    turbo_fans_check.connect_toggled(move |c| {
+        let on = c.is_active();
+        if let Ok(mut s) = st.try_borrow_mut() {
+            s.update_client_config(|c| c.turbo_max_fans = on);
+        }
+    });
+
+    let st = Rc::clone(state);
+    let alpha = alpha_spin.clone();
+    smooth_check.connect_toggled(move |c| {
+        let on = c.is_active();
+        alpha.set_sensitive(on);
+        if let Ok(mut s) = st.try_borrow_mut() {
+            s.update_client_config(|c| c.smooth_rpm = on);
+        }
+    });
+
+    let st = Rc::clone(state);
+    alpha_spin.connect_value_changed(move |spin| {
+        if let Ok(mut s) = st.try_borrow_mut() {
+            s.update_client_config(|c| c.rpm_smoothing_alpha = spin.value());
+        }
+    });
+
+    let st = Rc::clone(state);
+    let provider = provider.clone();
+    theme_dd.connect_selected_notify(move |d| {
+        let theme = if d.selected() == 1 { Theme::System } else { Theme::Dark };
+        apply_theme(&provider, theme);
+        if let Ok(mut s) = st.try_borrow_mut() {
+            s.update_client_config(|c| c.theme = theme);
+        }
+    });
+
+    let st = Rc::clone(state);
+    socket_entry.connect_changed(move |e| {
+        let text = e.text().trim().to_string();
+        if let Ok(mut s) = st.try_borrow_mut() {
+            s.update_client_config(|c| c.socket_path = (!text.is_empty()).then_some(text));
+        }
+    });
+
+    container
+}
@@ -1 +1,5 @@
+pub mod ec_watch;
+pub mod fan_response;
 pub mod gui;
+pub mod stress;
+pub mod wizard;
@@ -0,0 +1,146 @@
+//! Cooling check: runs `stress-ng` on every CPU for a short while and
+//! reports the highest temperatures and fan speeds the EC showed meanwhile,
+//! e.g. to confirm a repaste worked.
+
+use std::cell::RefCell;
+use std::process::{Child, Command, Stdio};
+use std::rc::Rc;
+use std::time::Duration;
+
+use gtk4::prelude::*;
+use gtk4::{glib, Align, Box as GtkBox, Button, Label, Orientation};
+
+use crate::core::cpu_ctl;
+use crate::ui::gui::AppState;
+
+const STRESS_CMD: &str = "stress-ng";
+
+const RUN_SECS: u32 = 30;
+
+/// How often the peaks are sampled.  The readings themselves come from the
+/// main window's poll, so this only needs to keep up with it.
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Highest readings seen during a run, plus where they started.
+#[derive(Default)]
+struct Peaks {
+    start_cpu_temp: u8,
+    start_gpu_temp: u8,
+    cpu_temp: u8,
+    gpu_temp: u8,
+    cpu_fan: u16,
+    gpu_fan: u16,
+}
+
+impl Peaks {
+    fn new(s: &AppState) -> Self {
+        let mut peaks = Peaks { start_cpu_temp: s.cpu_temp, start_gpu_temp: s.gpu_temp, ..Peaks::default() };
+        peaks.sample(s);
+        peaks
+    }
+
+    fn sample(&mut self, s: &AppState) {
+        self.cpu_temp = self.cpu_temp.max(s.cpu_temp);
+        self.gpu_temp = self.gpu_temp.max(s.gpu_temp);
+        self.cpu_fan = self.cpu_fan.max(s.cpu_fan_speed);
+        self.gpu_fan = self.gpu_fan.max(s.gpu_fan_speed);
+    }
+
+    fn summary(&self, s: &AppState) -> String {
+        let unit = s.client_config.temp_unit;
+        let mut lines = vec![
+            format!("CPU {} → peak {}", unit.format(self.start_cpu_temp), unit.format(self.cpu_temp)),
+            format!("GPU {} → peak {}", unit.format(self.start_gpu_temp), unit.format(self.gpu_temp)),
+            format!("CPU fan peak {} RPM", self.cpu_fan),
+        ];
+        if s.caps.independent_gpu_fan {
+            lines.push(format!("GPU fan peak {} RPM", self.gpu_fan));
+        }
+        lines.join("\n")
+    }
+}
+
+/// A run in progress.
+struct Run {
+    child: Child,
+    peaks: Peaks,
+}
+
+fn start(s: &AppState) -> Result<Run, String> {
+    let child = Command::new(STRESS_CMD)
+        .args(["--cpu", "0", "--timeout", &format!("{RUN_SECS}s")])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to start {STRESS_CMD}: {e}"))?;
+    Ok(Run { child, peaks: Peaks::new(s) })
+}
+
+pub fn build(state: &Rc<RefCell<AppState>>) -> GtkBox {
+    let card = GtkBox::new(Orientation::Vertical, 12);
+    card.add_css_class("card");
+
+    let title = Label::new(Some("STRESS TEST"));
+    title.add_css_class("section-title");
+    title.set_halign(Align::Start);
+    card.append(&title);
+
+    let button = Button::with_label(&format!("Run {RUN_SECS}s stress test"));
+    button.set_halign(Align::Start);
+    card.append(&button);
+
+    let result = Label::new(None);
+    result.add_css_class("label-secondary");
+    result.set_halign(Align::Start);
+    result.set_xalign(0.0);
+    result.set_wrap(true);
+    card.append(&result);
+
+    if !cpu_ctl::in_path(STRESS_CMD) {
+        button.set_sensitive(false);
+        result.set_label("Install stress-ng to load every CPU core and watch how the cooling copes.");
+        return card;
+    }
+
+    let st = Rc::clone(state);
+    let weak = card.downgrade();
+    let label = result.clone();
+    button.connect_clicked(move |btn| {
+        let run = match start(&st.borrow()) {
+            Ok(run) => run,
+            Err(e) => {
+                label.set_label(&e);
+                return;
+            }
+        };
+        btn.set_sensitive(false);
+        label.set_label(&format!("Running for {RUN_SECS}s…"));
+
+        let run = RefCell::new(run);
+        let (st, weak, label, btn) = (Rc::clone(&st), weak.clone(), label.clone(), btn.clone());
+        glib::timeout_add_local(SAMPLE_INTERVAL, move || {
+            let mut run = run.borrow_mut();
+            // Window closed mid-run: don't leave the CPU pinned.
+            if weak.upgrade().is_none() {
+                let _ = run.child.kill();
+                let _ = run.child.wait();
+                return glib::ControlFlow::Break;
+            }
+            // Skip a tick if a handler is busy with the state.
+            let Ok(s) = st.try_borrow() else {
+                return glib::ControlFlow::Continue;
+            };
+            run.peaks.sample(&s);
+            match run.child.try_wait() {
+                Ok(None) => return glib::ControlFlow::Continue,
+                Ok(Some(status)) if status.success() => label.set_label(&run.peaks.summary(&s)),
+                Ok(Some(status)) => label.set_label(&format!("{STRESS_CMD} stopped early ({status})")),
+                Err(e) => label.set_label(&format!("Lost track of {STRESS_CMD}: {e}")),
+            }
+            btn.set_sensitive(true);
+            glib::ControlFlow::Break
+        });
+    });
+
+    card
+}
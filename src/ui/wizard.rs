@@ -0,0 +1,267 @@
+//! First-run setup: checks everything NitroSense depends on and fixes what
+//! it can from the desktop session.
+
+use std::process::Command;
+use std::rc::Rc;
+
+use gtk4::prelude::*;
+use gtk4::{Align, Box as GtkBox, Button, Label, Orientation, Window};
+
+use crate::client::Client;
+use crate::config::ClientConfig;
+use crate::core::device_regs::CpuType;
+use crate::protocol::{Request, Response};
+
+/// Unit installed by `install.sh` and the Nix module.
+const SERVICE_UNIT: &str = "linux-nitrosense.service";
+
+/// Something the wizard can do about a failed check.
+#[derive(Debug, Clone, Copy)]
+enum Fix {
+    /// Enable and start the daemon unit through `pkexec`.
+    EnableService,
+    /// Have the daemon reload the EC driver with write support.
+    ReloadEc,
+}
+
+impl Fix {
+    fn label(&self) -> &'static str {
+        match self {
+            Fix::EnableService => "Enable service",
+            Fix::ReloadEc => "Reload EC driver",
+        }
+    }
+
+    fn apply(&self, socket_path: &str) {
+        match self {
+            Fix::EnableService => {
+                if let Err(e) = Command::new("pkexec").args(["systemctl", "enable", "--now", SERVICE_UNIT]).status() {
+                    eprintln!("Failed to run pkexec: {}", e);
+                }
+            }
+            Fix::ReloadEc => {
+                if let Ok(mut client) = Client::new_with_path(socket_path) {
+                    let _ = client.send(Request::ReloadEc);
+                }
+            }
+        }
+    }
+}
+
+struct Check {
+    title: &'static str,
+    ok: bool,
+    detail: String,
+    fix: Option<Fix>,
+}
+
+impl Check {
+    fn new(title: &'static str, ok: bool, detail: impl Into<String>, fix: Fix) -> Self {
+        Self { title, ok, detail: detail.into(), fix: (!ok).then_some(fix) }
+    }
+
+    /// A failure the wizard can only explain.
+    fn manual(title: &'static str, ok: bool, detail: impl Into<String>) -> Self {
+        Self { title, ok, detail: detail.into(), fix: None }
+    }
+}
+
+/// Run every check.  Returns the results and whether the daemon answered,
+/// which is the one thing the main window cannot start without.
+fn run_checks(socket_path: &str) -> (Vec<Check>, bool) {
+    let mut checks = Vec::new();
+
+    let enabled = Command::new("systemctl")
+        .args(["is-enabled", "--quiet", SERVICE_UNIT])
+        .status()
+        .is_ok_and(|s| s.success());
+    checks.push(Check::new(
+        "Daemon service",
+        enabled,
+        if enabled {
+            format!("{SERVICE_UNIT} starts at boot")
+        } else {
+            format!("{SERVICE_UNIT} is not enabled, so the daemon will not start at boot")
+        },
+        Fix::EnableService,
+    ));
+
+    let mut client = match Client::new_with_path(socket_path) {
+        Ok(c) => c,
+        Err(e) => {
+            checks.push(Check::new(
+                "Daemon connection",
+                false,
+                format!("Cannot reach {socket_path}: {e}"),
+                Fix::EnableService,
+            ));
+            return (checks, false);
+        }
+    };
+    checks.push(Check::manual("Daemon connection", true, format!("Connected to {socket_path}")));
+
+    let info = match client.send(Request::GetSystemInfo) {
+        Ok(Response::SystemInfo(info)) => Some(info),
+        _ => None,
+    };
+    let ec = match client.send(Request::GetEcInfo) {
+        Ok(Response::EcInfo(ec)) => Some(ec),
+        _ => None,
+    };
+    let lockdown = info.as_ref().and_then(|i| i.kernel_lockdown.clone());
+
+    checks.push(match (ec, lockdown) {
+        (Some(ec), _) if ec.writes_verified => {
+            Check::manual("EC write support", true, format!("Writable through {}", ec.interface))
+        }
+        (_, Some(mode)) => Check::manual(
+            "EC write support",
+            false,
+            format!("Kernel lockdown ({mode}) blocks EC writes; disable Secure Boot to allow them"),
+        ),
+        (Some(ec), None) => Check::new(
+            "EC write support",
+            false,
+            format!("Writes through {} did not reach the EC; ec_sys needs write_support=1", ec.interface),
+            Fix::ReloadEc,
+        ),
+        (None, None) => Check::manual("EC write support", false, "The daemon did not report EC status"),
+    });
+
+    match info {
+        Some(info) => {
+            checks.push(Check::manual(
+                "Keyboard lighting",
+                info.keyboard_device,
+                if info.keyboard_device {
+                    "RGB keyboard driver loaded"
+                } else {
+                    "RGB keyboard driver not loaded (no /dev/acer-gkbbl-*); lighting controls will do nothing"
+                },
+            ));
+            checks.push(match info.cpu {
+                CpuType::Unknown => Check::manual(
+                    "CPU tooling",
+                    false,
+                    format!("CPU vendor not detected, so undervolting is disabled: {}", info.cpu_detail),
+                ),
+                _ => Check::manual(
+                    "CPU tooling",
+                    info.cpu_tooling,
+                    if info.cpu_tooling {
+                        "Voltage readings and undervolting are available"
+                    } else {
+                        "Install amdctl (AMD) or msr-tools (Intel) for voltage readings and undervolting"
+                    },
+                ),
+            });
+        }
+        None => checks.push(Check::manual("System info", false, "The daemon did not report system info")),
+    }
+
+    (checks, true)
+}
+
+/// Show the wizard; `on_done` builds the main window once the user continues.
+pub fn show(app: &gtk4::Application, socket_path: &str, on_done: impl Fn(&gtk4::Application) + 'static) {
+    let window = Window::builder()
+        .application(app)
+        .title("NitroSense Setup")
+        .default_width(560)
+        .build();
+
+    let container = GtkBox::new(Orientation::Vertical, 12);
+    container.set_margin_top(20);
+    container.set_margin_bottom(20);
+    container.set_margin_start(20);
+    container.set_margin_end(20);
+
+    let intro = Label::new(Some(
+        "NitroSense needs a root daemon and a few kernel drivers. \
+         Anything marked in red below will keep some features from working.",
+    ));
+    intro.set_wrap(true);
+    intro.set_xalign(0.0);
+    container.append(&intro);
+
+    let list = GtkBox::new(Orientation::Vertical, 10);
+    container.append(&list);
+
+    let recheck_btn = Button::with_label("Check Again");
+    let continue_btn = Button::with_label("Continue");
+    continue_btn.add_css_class("suggested-action");
+    let buttons = GtkBox::new(Orientation::Horizontal, 12);
+    buttons.set_halign(Align::End);
+    buttons.append(&recheck_btn);
+    buttons.append(&continue_btn);
+    container.append(&buttons);
+
+    window.set_child(Some(&container));
+
+    let socket_path: Rc<str> = Rc::from(socket_path);
+    refresh(&list, &continue_btn, &socket_path);
+
+    {
+        let (list, cont, path) = (list.clone(), continue_btn.clone(), Rc::clone(&socket_path));
+        recheck_btn.connect_clicked(move |_| refresh(&list, &cont, &path));
+    }
+    {
+        let (app, win) = (app.clone(), window.clone());
+        continue_btn.connect_clicked(move |_| {
+            // Writing the config marks setup as done for the next launch.
+            ClientConfig::load_or_default().save();
+            on_done(&app);
+            win.close();
+        });
+    }
+
+    window.present();
+}
+
+/// Re-run the checks and rebuild the result rows.
+fn refresh(list: &GtkBox, continue_btn: &Button, socket_path: &Rc<str>) {
+    while let Some(child) = list.first_child() {
+        list.remove(&child);
+    }
+
+    let (checks, daemon_ok) = run_checks(socket_path);
+    continue_btn.set_sensitive(daemon_ok);
+
+    for check in checks {
+        let row = GtkBox::new(Orientation::Horizontal, 10);
+
+        let mark = Label::new(None);
+        mark.set_markup(if check.ok {
+            "<span foreground=\"#4ade80\">✔</span>"
+        } else {
+            "<span foreground=\"#f87171\">✘</span>"
+        });
+        mark.set_valign(Align::Start);
+        row.append(&mark);
+
+        let text = GtkBox::new(Orientation::Vertical, 2);
+        text.set_hexpand(true);
+        let title = Label::new(Some(check.title));
+        title.set_xalign(0.0);
+        let detail = Label::new(Some(&check.detail));
+        detail.set_xalign(0.0);
+        detail.set_wrap(true);
+        detail.add_css_class("dim-label");
+        text.append(&title);
+        text.append(&detail);
+        row.append(&text);
+
+        if let Some(fix) = check.fix {
+            let btn = Button::with_label(fix.label());
+            btn.set_valign(Align::Center);
+            let (list, cont, path) = (list.clone(), continue_btn.clone(), Rc::clone(socket_path));
+            btn.connect_clicked(move |_| {
+                fix.apply(&path);
+                refresh(&list, &cont, &path);
+            });
+            row.append(&btn);
+        }
+
+        list.append(&row);
+    }
+}
@@ -1,16 +1,177 @@
 /// Acer per-zone RGB keyboard backlight control.
 
-use std::fs::OpenOptions;
+use std::env;
+use std::fs::{self, OpenOptions};
 use std::io::Write;
+use std::path::Path;
+use std::sync::OnceLock;
 
 const PAYLOAD_SIZE: usize = 16;
 const PAYLOAD_SIZE_STATIC: usize = 4;
 
-const DEVICE_DYNAMIC: &str = "/dev/acer-gkbbl-0";
-const DEVICE_STATIC: &str = "/dev/acer-gkbbl-static-0";
+/// Device node prefixes; the driver appends an instance index.
+const DEVICE_DYNAMIC_PREFIX: &str = "acer-gkbbl-";
+const DEVICE_STATIC_PREFIX: &str = "acer-gkbbl-static-";
+
+/// Environment variables that override the detected device nodes.
+const ENV_DEVICE_DYNAMIC: &str = "NITROSENSE_KB_DEVICE";
+const ENV_DEVICE_STATIC: &str = "NITROSENSE_KB_STATIC_DEVICE";
+/// Set to `1` to drive the logo zone on a model whose map doesn't list one.
+const ENV_LOGO: &str = "NITROSENSE_KB_LOGO";
+
+/// Highest speed value exposed by the UI slider (0 = slowest).
+const UI_SPEED_MAX: u8 = 9;
+/// Highest static zone; zone 0 means "all zones".
+const ZONE_MAX: u8 = 4;
+/// Static-zone bit of the logo / front light bar on models that have one.
+/// It is never part of zone 0 ("all"), which covers the keys only.
+const LOGO_ZONE: u8 = ZONE_MAX + 1;
+
+/// Effect modes (`RgbConfig::mode`): 0 Static, 1 Breathing, 2 Neon,
+/// 3 Wave, 4 Shifting, 5 Zoom, 6 Meteor.  All of them run in the keyboard
+/// firmware with at most one colour.  The one software-driven effect is
+/// breathing through several colours, which the daemon animates itself by
+/// rewriting static colours (see [`breathing_frame`]).
+pub const MODE_BREATHING: u8 = 1;
+/// Effect modes that need extra payload handling.
+const MODE_WAVE: u8 = 3;
+const MODE_METEOR: u8 = 6;
+
+/// Most colours a software breathing cycle takes.
+pub const MAX_BREATHING_COLORS: usize = 4;
+
+/// Physical lighting layout of the keyboard backlight.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum KeyboardLayout {
+    /// One colour for the whole keyboard (no static-zone device).
+    SingleZone,
+    /// Four independently coloured zones.
+    #[default]
+    FourZone,
+    /// Per-key RGB – uses a different protocol that is not supported yet.
+    PerKey,
+}
+
+impl KeyboardLayout {
+    /// Number of addressable zones (0 for unsupported per-key keyboards).
+    pub fn zone_count(&self) -> u8 {
+        match self {
+            KeyboardLayout::SingleZone => 1,
+            KeyboardLayout::FourZone => ZONE_MAX,
+            KeyboardLayout::PerKey => 0,
+        }
+    }
+}
+
+/// Device nodes of the keyboard driver.
+struct Devices {
+    dynamic: String,
+    static_zones: String,
+}
+
+/// Lowest-indexed `/dev/<prefix><N>`, if the driver has created one.
+fn find_device(prefix: &str) -> Option<String> {
+    let index = fs::read_dir("/dev")
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|e| e.file_name().to_str()?.strip_prefix(prefix)?.parse::<u32>().ok())
+        .min()?;
+    Some(format!("/dev/{prefix}{index}"))
+}
+
+/// One device path, from the environment or `/dev`.  It is cached, and
+/// logged, only once a node is really there: the driver may load after
+/// the daemon starts.  Until then the index-0 path is returned, and writes
+/// to it fail.
+fn resolve(cache: &OnceLock<String>, env_var: &str, prefix: &str, role: &str) -> String {
+    if let Some(path) = cache.get() {
+        return path.clone();
+    }
+    match env::var(env_var).ok().or_else(|| find_device(prefix)) {
+        Some(path) => cache
+            .get_or_init(|| {
+                println!("Keyboard {role} device: {path}");
+                path
+            })
+            .clone(),
+        None => format!("/dev/{prefix}0"),
+    }
+}
+
+/// Device paths of the keyboard driver.
+fn devices() -> Devices {
+    static DYNAMIC: OnceLock<String> = OnceLock::new();
+    static STATIC_ZONES: OnceLock<String> = OnceLock::new();
+    Devices {
+        dynamic: resolve(&DYNAMIC, ENV_DEVICE_DYNAMIC, DEVICE_DYNAMIC_PREFIX, "effects"),
+        static_zones: resolve(&STATIC_ZONES, ENV_DEVICE_STATIC, DEVICE_STATIC_PREFIX, "zones"),
+    }
+}
+
+/// Refine the model's expected layout with what the driver actually exposes:
+/// a four-zone model without the static-zone device is driven as one zone.
+pub fn detect_layout(model_layout: KeyboardLayout) -> KeyboardLayout {
+    match model_layout {
+        KeyboardLayout::FourZone if !Path::new(&devices().static_zones).exists() && Path::new(&devices().dynamic).exists() => {
+            KeyboardLayout::SingleZone
+        }
+        other => other,
+    }
+}
+
+/// Whether the logo / light-bar zone can be driven: the model has one (or
+/// `NITROSENSE_KB_LOGO=1` says so) and the static-zone device it sits on
+/// exists.
+pub fn detect_logo(model_has_logo: bool) -> bool {
+    let forced = env::var(ENV_LOGO).is_ok_and(|v| v == "1");
+    (model_has_logo || forced) && Path::new(&devices().static_zones).exists()
+}
+
+/// Direction of the moving effects, as the byte the driver takes in the
+/// effect payload.  Stored configs and the protocol carry the same byte.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(try_from = "u8", into = "u8")]
+pub enum Direction {
+    #[default]
+    Right = 1,
+    Left = 2,
+}
+
+impl Direction {
+    /// In the order the UI lists them.
+    pub const ALL: [Direction; 2] = [Direction::Right, Direction::Left];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Direction::Right => "Right",
+            Direction::Left => "Left",
+        }
+    }
+}
+
+impl From<Direction> for u8 {
+    fn from(d: Direction) -> u8 {
+        d as u8
+    }
+}
+
+impl TryFrom<u8> for Direction {
+    type Error = String;
+
+    fn try_from(byte: u8) -> Result<Self, Self::Error> {
+        match byte {
+            // 0 is what configs saved before any effect was picked hold;
+            // the app always showed it as Right.
+            0 | 1 => Ok(Direction::Right),
+            2 => Ok(Direction::Left),
+            other => Err(format!("invalid effect direction {other}")),
+        }
+    }
+}
 
 /// RGB colour.
-#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct Rgb {
     pub r: u8,
     pub g: u8,
@@ -27,51 +188,154 @@ impl Default for Rgb {
     }
 }
 
+/// Whether the firmware honours the colour bytes for `mode`.  Neon and Wave
+/// cycle through the rainbow, and Meteor draws its own colours: unlike Wave
+/// it has no flag byte that switches it to a fixed colour, so the colour in
+/// the payload is simply ignored.
+pub fn effect_uses_color(mode: u8) -> bool {
+    !matches!(mode, 2 | MODE_WAVE | MODE_METEOR)
+}
+
 pub fn set_mode(
     mode: u8,
     zone: u8,
     speed: u8,
     brightness: u8,
-    direction: u8,
+    direction: Direction,
     color: Rgb,
 ) {
     if mode == 0 {
         set_static(zone, color, brightness);
     } else {
-        set_dynamic(mode, speed, brightness, direction, color);
+        set_dynamic(mode, speed, brightness, Some(direction), color);
     }
 }
 
 fn set_static(zone: u8, color: Rgb, brightness: u8) {
+    if !Path::new(&devices().static_zones).exists() {
+        // Single-zone keyboard: the dynamic device's mode 0 is a plain colour.
+        set_dynamic(0, 0, brightness, None, color);
+        return;
+    }
     if zone == 0 {
         // "all" – write to zones 1..=4
-        for z in 1..=4u8 {
-            write_device(DEVICE_STATIC, &static_payload(z, color));
+        for z in 1..=ZONE_MAX {
+            if let Some(payload) = static_payload(z, color) {
+                write_device(&devices().static_zones, &payload);
+            }
         }
+    } else if let Some(payload) = static_payload(zone, color) {
+        write_device(&devices().static_zones, &payload);
     } else {
-        write_device(DEVICE_STATIC, &static_payload(zone, color));
+        eprintln!("Ignoring invalid keyboard zone {zone}");
     }
     // Apply brightness payload after static colour change
-    write_device(DEVICE_DYNAMIC, &brightness_payload(brightness));
+    write_device(&devices().dynamic, &brightness_payload(brightness));
+}
+
+/// `zones` colours blending linearly from `start` (first zone) to `end`
+/// (last zone).
+pub fn gradient(start: Rgb, end: Rgb, zones: u8) -> Vec<Rgb> {
+    let lerp = |a: u8, b: u8, t: f32| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+    let steps = zones.saturating_sub(1).max(1) as f32;
+    (0..zones)
+        .map(|i| {
+            let t = i as f32 / steps;
+            Rgb { r: lerp(start.r, end.r, t), g: lerp(start.g, end.g, t), b: lerp(start.b, end.b, t) }
+        })
+        .collect()
 }
 
-fn set_dynamic(mode: u8, speed: u8, brightness: u8, direction: u8, color: Rgb) {
+/// Whether the RGB keyboard driver is loaded at all.
+pub fn device_present() -> bool {
+    Path::new(&devices().dynamic).exists()
+}
+
+/// Static colours for each zone: `colors[0]` goes to zone 1, and so on.
+pub fn set_zone_colors(colors: &[Rgb], brightness: u8) {
+    for (zone, color) in (1..=ZONE_MAX).zip(colors) {
+        if let Some(payload) = static_payload(zone, *color) {
+            write_device(&devices().static_zones, &payload);
+        }
+    }
+    write_device(&devices().dynamic, &brightness_payload(brightness));
+}
+
+/// Static colour for the logo / light-bar zone.
+pub fn set_logo_color(color: Rgb) {
+    write_device(&devices().static_zones, &[1 << (LOGO_ZONE - 1), color.r, color.g, color.b]);
+}
+
+/// Static gradient from `start` on zone 1 to `end` on the last zone.
+pub fn set_gradient(start: Rgb, end: Rgb, brightness: u8) {
+    if !Path::new(&devices().static_zones).exists() {
+        // No zones to spread the gradient over.
+        set_dynamic(0, 0, brightness, None, start);
+        return;
+    }
+    set_zone_colors(&gradient(start, end, ZONE_MAX), brightness);
+}
+
+fn set_dynamic(mode: u8, speed: u8, brightness: u8, direction: Option<Direction>, color: Rgb) {
+    write_device(&devices().dynamic, &dynamic_payload(mode, speed, brightness, direction, color));
+}
+
+/// Effect payload; `direction` is `None` for the plain colour of mode 0,
+/// which moves nowhere and gets a 0 byte.
+fn dynamic_payload(mode: u8, speed: u8, brightness: u8, direction: Option<Direction>, color: Rgb) -> [u8; PAYLOAD_SIZE] {
     let mut payload = [0u8; PAYLOAD_SIZE];
     payload[0] = mode;
-    payload[1] = speed;
+    payload[1] = speed_to_payload(mode, speed);
     payload[2] = brightness;
-    payload[3] = if mode == 3 { 8 } else { 0 }; // Wave mode requires special flag
-    payload[4] = direction;
+    payload[3] = if mode == MODE_WAVE { 8 } else { 0 }; // Wave mode requires special flag; Meteor takes none
+    payload[4] = direction.map_or(0, u8::from);
     payload[5] = color.r;
     payload[6] = color.g;
     payload[7] = color.b;
     payload[9] = 1;
-    write_device(DEVICE_DYNAMIC, &payload);
+    payload
+}
+
+/// Animation cycles per second for the 0–9 UI speed, as drawn by the
+/// preview and the software effects.
+pub fn effect_rate(ui_speed: u8) -> f64 {
+    0.1 + ui_speed.min(UI_SPEED_MAX) as f64 * 0.1
+}
+
+/// Colour `t` seconds into software breathing: each colour fades in and
+/// out once before the next one takes over.
+pub fn breathing_frame(colors: &[Rgb], ui_speed: u8, t: f64) -> Rgb {
+    if colors.is_empty() {
+        return Rgb { r: 0, g: 0, b: 0 };
+    }
+    let breaths = t * effect_rate(ui_speed);
+    let color = colors[breaths as usize % colors.len()];
+    let k = (1.0 - (breaths.fract() * std::f64::consts::TAU).cos()) / 2.0;
+    let scale = |c: u8| (c as f64 * k).round() as u8;
+    Rgb { r: scale(color.r), g: scale(color.g), b: scale(color.b) }
 }
 
-fn static_payload(zone: u8, color: Rgb) -> [u8; PAYLOAD_SIZE_STATIC] {
-    // Zone 1-4. Bitmask for zone selection.
-    [1 << (zone - 1), color.r, color.g, color.b]
+/// Map the 0–9 UI speed onto the byte the driver expects for `effect`.
+///
+/// Breathing, Neon, Shifting, Zoom and Meteor treat a higher byte as a
+/// faster animation.  Wave (3) treats it as a delay instead, so its scale
+/// is inverted to keep "right = faster" consistent in the UI.
+pub fn speed_to_payload(effect: u8, ui_speed: u8) -> u8 {
+    let speed = ui_speed.min(UI_SPEED_MAX);
+    match effect {
+        MODE_WAVE => UI_SPEED_MAX - speed,
+        _ => speed,
+    }
+}
+
+/// Payload for a single zone (1-4), or `None` for anything else – zone 0
+/// ("all") has to be expanded by the caller.
+fn static_payload(zone: u8, color: Rgb) -> Option<[u8; PAYLOAD_SIZE_STATIC]> {
+    if !(1..=ZONE_MAX).contains(&zone) {
+        return None;
+    }
+    // Bitmask for zone selection.
+    Some([1 << (zone - 1), color.r, color.g, color.b])
 }
 
 fn brightness_payload(brightness: u8) -> [u8; PAYLOAD_SIZE] {
@@ -94,3 +358,24 @@ fn write_device(path: &str, payload: &[u8]) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RgbConfig;
+
+    #[test]
+    fn saved_direction_reaches_the_device_unchanged() {
+        for direction in Direction::ALL {
+            let cfg = RgbConfig { mode: MODE_WAVE, direction, ..RgbConfig::factory() };
+            let saved: RgbConfig = serde_json::from_str(&serde_json::to_string(&cfg).unwrap()).unwrap();
+            assert_eq!(saved.direction, direction);
+            let payload = dynamic_payload(saved.mode, saved.speed, saved.brightness, Some(saved.direction), saved.color);
+            assert_eq!(payload[4], u8::from(direction));
+        }
+        // Older configs hold 0, shown as Right; nothing past Left is valid.
+        let old: RgbConfig = serde_json::from_str(r#"{"direction": 0}"#).unwrap();
+        assert_eq!(old.direction, Direction::Right);
+        assert!(serde_json::from_str::<RgbConfig>(r#"{"direction": 3}"#).is_err());
+    }
+}